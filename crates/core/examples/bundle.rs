@@ -0,0 +1,87 @@
+use dyadikos_core::prelude::*;
+use std::{sync::Arc, time::Instant};
+use wgpu::Color;
+
+const GRID: u32 = 32;
+const FRAMES_PER_REPORT: u32 = 120;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	@vertex
+	fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+		return transform * vec4<f32>(position, 1.0);
+	}
+
+	@fragment
+	fn fs_main() -> @location(0) vec4<f32> {
+		return vec4<f32>(1.0, 0.5, 0.0, 1.0);
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		background: Background::SolidColor(Color::BLACK),
+		..Default::default()
+	})
+	.await?;
+
+	// One small quad per grid cell, each as its own mesh
+	let cell = 2.0 / GRID as f32;
+	let mut meshes: Vec<_> = (0..GRID * GRID)
+		.map(|i| {
+			let x = -1.0 + (i % GRID) as f32 * cell;
+			let y = -1.0 + (i / GRID) as f32 * cell;
+			let size = cell * 0.8;
+			let vertices = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)]
+				.map(|(dx, dy)| Vertex::new([x + dx, y + dy, 0.0]))
+				.to_vec();
+
+			Mesh::new(&app, vertices, vec![0, 1, 2, 0, 2, 3])
+		})
+		.collect();
+
+	// Run with any argument to encode every mesh each frame instead
+	let use_bundle = std::env::args().len() == 1;
+	let bundle = Arc::new(Mesh::bake_bundle(&app, &meshes));
+
+	let matrix = dyadikos_math::identity();
+	let mut frames = 0;
+	let mut encoding_time = 0.0;
+
+	app.run(
+		&matrix,
+		Box::new(move |rpass, _| {
+			let start = Instant::now();
+			if use_bundle {
+				rpass.execute_bundle(bundle.clone());
+			} else {
+				for mesh in &mut meshes {
+					mesh.render(rpass);
+				}
+			}
+			encoding_time += start.elapsed().as_secs_f64();
+
+			frames += 1;
+			if frames == FRAMES_PER_REPORT {
+				tracing::info!(
+					"{} encoding: {:.3}ms per frame",
+					if use_bundle { "Bundle" } else { "Per-frame" },
+					encoding_time * 1000.0 / frames as f64
+				);
+				frames = 0;
+				encoding_time = 0.0;
+			}
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
@@ -0,0 +1,65 @@
+use dyadikos_core::{debug_draw::DebugDraw, prelude::*};
+use dyadikos_math::bounds::Aabb;
+use glam::{Mat4, Vec3};
+use wgpu::Color;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	@vertex
+	fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+		return transform * vec4<f32>(position, 1.0);
+	}
+
+	@fragment
+	fn fs_main() -> @location(0) vec4<f32> {
+		return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		background: Background::SolidColor(Color::BLACK),
+		..Default::default()
+	})
+	.await?;
+
+	let mut transform = RenderTransformation::default();
+	transform.proj =
+		Mat4::perspective_rh(60.0_f32.to_radians(), 1.0, 0.01, 1000.0);
+	transform.view = Mat4::look_at_rh(
+		Vec3::new(4.0, 3.0, 5.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, -1.0, 0.0),
+	);
+
+	let matrix = (transform.proj * transform.view).to_cols_array();
+	let aabb = Aabb::new([-0.5, 0.0, -0.5], [0.5, 1.0, 0.5]);
+	let mut debug_draw = DebugDraw::new(&app);
+
+	app.run(
+		&matrix,
+		Box::new(move |rpass, app| {
+			app.get_queue().write_buffer(
+				app.get_uniform_buffer(),
+				0,
+				bytemuck::cast_slice(&[matrix]),
+			);
+
+			debug_draw.draw_grid(10.0, 1.0);
+			debug_draw.draw_axes(2.0);
+			debug_draw.draw_aabb(&aabb, Color::WHITE);
+			debug_draw.render(app, rpass);
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
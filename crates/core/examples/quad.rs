@@ -1,6 +1,4 @@
-use dyadikos_core::{mesh::Mesh, native::NativeApp, App, AppSettings};
-use dyadikos_math::{transform::RenderTransformation, Vertex};
-use glam::{Mat4, Vec3};
+use dyadikos_core::{camera::CameraController, prelude::*};
 use wgpu::Color;
 
 #[tokio::main]
@@ -31,52 +29,33 @@ async fn main() -> anyhow::Result<()> {
 	}
 	"#;
 
+	// Fly around with WASD, Space and Left Shift, and look around by
+	// dragging with the right mouse button
+	let camera = Camera::default();
 	let app = NativeApp::new(AppSettings {
 		shader: shader.to_string(),
-		background_color: Color::BLACK,
+		background: Background::SolidColor(Color::BLACK),
+		camera: Some(CameraController::new(camera)),
 		..Default::default()
 	})
 	.await?;
 
-	let mut transform = RenderTransformation::default();
-	transform.proj =
-		Mat4::perspective_rh(60.0_f32.to_radians(), 1.0, 0.01, 1000.0);
-	transform.view = Mat4::look_at_rh(
-		Vec3::new(0.0, 0.0, 1.0),
-		Vec3::new(0.0, 0.0, 0.0),
-		Vec3::new(0.0, -1.0, 0.0),
-	);
-	transform.model = Mat4::from_scale(Vec3::new(1.0, 1.0, 1.0));
-
-	let matrix =
-		(transform.proj * transform.view * transform.model).to_cols_array();
-
 	let vertices = vec![
-		Vertex {
-			position: [0.5, 0.5, 0.0],
-		},
-		Vertex {
-			position: [0.5, -0.5, 0.0],
-		},
-		Vertex {
-			position: [-0.5, -0.5, 0.0],
-		},
-		Vertex {
-			position: [-0.5, 0.5, 0.0],
-		},
+		Vertex::new([0.5, 0.5, 0.0]),
+		Vertex::new([0.5, -0.5, 0.0]),
+		Vertex::new([-0.5, -0.5, 0.0]),
+		Vertex::new([-0.5, 0.5, 0.0]),
 	];
 	let indices = vec![0, 1, 3, 1, 2, 3];
 	let mut mesh = Mesh::new(&app, vertices, indices);
 
-	app.clone().run(
-		&matrix,
-		Box::new(move |rpass, uniform_buffer| {
-			app.queue.write_buffer(
-				uniform_buffer,
-				0,
-				bytemuck::cast_slice(&[matrix]),
-			);
+	// The camera replaces the matrix every frame
+	app.run(
+		&camera.view_proj(),
+		Box::new(move |rpass, _| {
 			mesh.render(rpass);
+
+			Ok(())
 		}),
 	);
 
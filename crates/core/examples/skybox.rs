@@ -0,0 +1,232 @@
+use dyadikos_core::{
+	buffer::UniformBuffer,
+	prelude::*,
+	texture::{Image, SamplerSettings, Texture},
+};
+use glam::{Mat4, Vec3};
+use std::{borrow::Cow, sync::Arc, time::Instant};
+use wgpu::TextureFormat;
+
+const SKYBOX_SHADER: &str = r#"
+@group(0)
+@binding(0)
+var<uniform> inverse_view_proj: mat4x4<f32>;
+
+@group(0)
+@binding(1)
+var sky: texture_cube<f32>;
+
+@group(0)
+@binding(2)
+var sky_sampler: sampler;
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) ndc: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+	let ndc = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+
+	var result: VertexOutput;
+	result.position = vec4<f32>(ndc, 1.0, 1.0);
+	result.ndc = ndc;
+	return result;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	let far = inverse_view_proj * vec4<f32>(vertex.ndc, 1.0, 1.0);
+	return textureSample(sky, sky_sampler, normalize(far.xyz / far.w));
+}
+"#;
+
+/// A checkerboard tinted differently on every face, so the orientation of
+/// the cube is visible
+fn face_pixels(size: u32, tint: [u8; 3]) -> Vec<u8> {
+	let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+
+	for y in 0..size {
+		for x in 0..size {
+			let shade = if (x / 8 + y / 8) % 2 == 0 { 255 } else { 128 };
+			pixels.extend(tint.map(|c| (c as u32 * shade / 255) as u8));
+			pixels.push(255);
+		}
+	}
+
+	pixels
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	@vertex
+	fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+		return transform * vec4<f32>(position, 1.0);
+	}
+
+	@fragment
+	fn fs_main() -> @location(0) vec4<f32> {
+		return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		..Default::default()
+	})
+	.await?;
+	let device = app.get_device();
+
+	let size = 64;
+	let faces = [
+		[255, 64, 64],
+		[128, 32, 32],
+		[64, 255, 64],
+		[32, 128, 32],
+		[64, 64, 255],
+		[32, 32, 128],
+	]
+	.map(|tint| face_pixels(size, tint));
+	let images = faces.each_ref().map(|data| Image {
+		width: size,
+		height: size,
+		format: TextureFormat::Rgba8UnormSrgb,
+		data,
+	});
+	let cubemap = Texture::cubemap_from_images(&app, images)?;
+	let sampler = SamplerSettings::default().create_sampler(device)?;
+
+	let camera_buffer =
+		UniformBuffer::new(&app, &Mat4::IDENTITY.to_cols_array());
+
+	let bind_group_layout =
+		device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Skybox"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float {
+							filterable: true,
+						},
+						view_dimension: wgpu::TextureViewDimension::Cube,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(
+						wgpu::SamplerBindingType::Filtering,
+					),
+					count: None,
+				},
+			],
+		});
+
+	let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+		label: Some("Skybox"),
+		layout: &bind_group_layout,
+		entries: &[
+			wgpu::BindGroupEntry {
+				binding: 0,
+				resource: camera_buffer.as_entire_binding(),
+			},
+			wgpu::BindGroupEntry {
+				binding: 1,
+				resource: wgpu::BindingResource::TextureView(cubemap.view()),
+			},
+			wgpu::BindGroupEntry {
+				binding: 2,
+				resource: wgpu::BindingResource::Sampler(&sampler),
+			},
+		],
+	});
+
+	let pipeline_layout =
+		device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Skybox"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+	let skybox_shader =
+		device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("Skybox"),
+			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SKYBOX_SHADER)),
+		});
+
+	let pipeline =
+		device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("Skybox"),
+			layout: Some(&pipeline_layout),
+			vertex: wgpu::VertexState {
+				module: &skybox_shader,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &skybox_shader,
+				entry_point: "fs_main",
+				targets: &[Some(app.get_surface_format().into())],
+			}),
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			multiview: None,
+		});
+
+	let pipeline = Arc::new(pipeline);
+	let bind_group = Arc::new(bind_group);
+	let start = Instant::now();
+
+	app.run(
+		&Mat4::IDENTITY.to_cols_array(),
+		Box::new(move |rpass, app| {
+			let (width, height) = app.get_window_size();
+			let proj = Mat4::perspective_rh(
+				70.0_f32.to_radians(),
+				width as f32 / height as f32,
+				0.1,
+				10.0,
+			);
+			// Only the camera's rotation matters for a skybox
+			let angle = start.elapsed().as_secs_f32() * 0.3;
+			let view = Mat4::look_at_rh(
+				Vec3::ZERO,
+				Vec3::new(angle.cos(), 0.3, angle.sin()),
+				Vec3::Y,
+			);
+
+			camera_buffer.update(app, &(proj * view).inverse().to_cols_array());
+
+			rpass.set_pipeline(pipeline.clone());
+			rpass.set_bind_group(0, bind_group.clone(), &[]);
+			rpass.draw(0..3, 0..1);
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
@@ -0,0 +1,118 @@
+use dyadikos_core::{buffer::UniformBuffer, prelude::*};
+use glam::{Mat4, Vec3};
+use std::{sync::Arc, time::Instant};
+use wgpu::Color;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	struct VertexOutput {
+		@builtin(position) position: vec4<f32>,
+		@location(0) color: vec4<f32>,
+	};
+
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	@vertex
+	fn vs_main(
+		@location(0) position: vec3<f32>,
+		@location(1) color: vec4<f32>,
+	) -> VertexOutput {
+		var result: VertexOutput;
+		result.position = transform * vec4<f32>(position, 1.0);
+		result.color = color;
+		return result;
+	}
+
+	@fragment
+	fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+		return vertex.color;
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		background: Background::SolidColor(Color::BLACK),
+		..Default::default()
+	})
+	.await?;
+
+	let vertices = vec![
+		Vertex::with_color([0.0, 0.5, 0.0], [1.0, 0.0, 0.0, 1.0]),
+		Vertex::with_color([-0.5, -0.5, 0.0], [0.0, 1.0, 0.0, 1.0]),
+		Vertex::with_color([0.5, -0.5, 0.0], [0.0, 0.0, 1.0, 1.0]),
+	];
+	let mut mesh = Mesh::new(&app, vertices, vec![0, 1, 2]);
+
+	// Every camera needs its own uniform buffer, since writes to a single
+	// buffer only take effect once the whole frame is submitted
+	let cameras: Vec<_> = (0..2)
+		.map(|_| {
+			let buffer = UniformBuffer::new(&app, &dyadikos_math::identity());
+			let bind_group = app.get_device().create_bind_group(
+				&wgpu::BindGroupDescriptor {
+					label: Some("Camera"),
+					layout: app.get_bind_group_layout(),
+					entries: &[wgpu::BindGroupEntry {
+						binding: 0,
+						resource: buffer.as_entire_binding(),
+					}],
+				},
+			);
+
+			(buffer, Arc::new(bind_group))
+		})
+		.collect();
+
+	let start = Instant::now();
+	app.run(
+		&dyadikos_math::identity(),
+		Box::new(move |rpass, app| {
+			let (width, height) = app.get_window_size();
+			let half_width = width / 2;
+			let aspect = half_width as f32 / height.max(1) as f32;
+			let proj = Mat4::perspective_rh(
+				60.0_f32.to_radians(),
+				aspect,
+				0.01,
+				100.0,
+			);
+
+			// The left camera orbits the triangle, the right one looks at it
+			// head on
+			let angle = start.elapsed().as_secs_f32();
+			let eyes = [
+				Vec3::new(angle.sin() * 2.0, 0.0, angle.cos() * 2.0),
+				Vec3::new(0.0, 0.0, 2.0),
+			];
+
+			for (i, ((buffer, bind_group), eye)) in
+				cameras.iter().zip(eyes).enumerate()
+			{
+				let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+				buffer.update(app, &(proj * view).to_cols_array());
+
+				let x = i as u32 * half_width;
+				rpass.set_viewport(
+					x as f32,
+					0.0,
+					half_width as f32,
+					height as f32,
+					0.0,
+					1.0,
+				);
+				rpass.set_scissor_rect(x, 0, half_width, height);
+				rpass.set_bind_group(0, bind_group.clone(), &[]);
+				mesh.render(rpass);
+			}
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
@@ -0,0 +1,79 @@
+use dyadikos_core::prelude::*;
+use glam::{Mat4, Vec3};
+use wgpu::Color;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	struct VertexOutput {
+		@builtin(position) position: vec4<f32>,
+	};
+
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	@vertex
+	fn vs_main(
+		@location(0) position: vec3<f32>,
+	) -> VertexOutput {
+		var result: VertexOutput;
+		result.position = transform * vec4<f32>(position, 1.0);
+		return result;
+	}
+
+	@fragment
+	fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+		return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		background: Background::SolidColor(Color::BLACK),
+		..Default::default()
+	})
+	.await?;
+
+	let mut transform = RenderTransformation::default();
+	transform.proj =
+		Mat4::perspective_rh(60.0_f32.to_radians(), 1.0, 0.01, 1000.0);
+	transform.view = Mat4::look_at_rh(
+		Vec3::new(2.0, 1.5, 3.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, -1.0, 0.0),
+	);
+
+	let matrix = (transform.proj * transform.view).to_cols_array();
+
+	// The twelve edges of a unit cube, joining corners that differ in
+	// exactly one coordinate
+	let corner =
+		|i: u32| [i & 1, i >> 1 & 1, i >> 2 & 1].map(|bit| bit as f32 - 0.5);
+	let edges: Vec<_> = (0..8)
+		.flat_map(|i| [1, 2, 4].map(|axis| (i, i | axis)))
+		.filter(|(i, j)| i != j)
+		.map(|(i, j)| [corner(i), corner(j)])
+		.collect();
+
+	let mut mesh =
+		Mesh::thick_lines(&app, &edges, 0.05, &transform.view.to_cols_array());
+
+	app.run(
+		&matrix,
+		Box::new(move |rpass, app| {
+			app.get_queue().write_buffer(
+				app.get_uniform_buffer(),
+				0,
+				bytemuck::cast_slice(&[matrix]),
+			);
+			mesh.render(rpass);
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
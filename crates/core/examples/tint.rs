@@ -0,0 +1,90 @@
+use dyadikos_core::prelude::*;
+use glam::{Mat4, Vec3};
+use wgpu::{Color, Features};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	struct VertexOutput {
+		@builtin(position) position: vec4<f32>,
+	};
+
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	var<push_constant> mesh_color: vec4<f32>;
+
+	@vertex
+	fn vs_main(
+		@location(0) position: vec3<f32>,
+	) -> VertexOutput {
+		var result: VertexOutput;
+		result.position = transform * vec4<f32>(position, 1.0);
+		return result;
+	}
+
+	@fragment
+	fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+		return mesh_color;
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		background: Background::SolidColor(Color::BLACK),
+		features: Features::PUSH_CONSTANTS,
+		..Default::default()
+	})
+	.await?;
+
+	let mut transform = RenderTransformation::default();
+	transform.proj =
+		Mat4::perspective_rh(60.0_f32.to_radians(), 1.0, 0.01, 1000.0);
+	transform.view = Mat4::look_at_rh(
+		Vec3::new(0.0, 0.0, 1.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, -1.0, 0.0),
+	);
+	transform.model = Mat4::from_scale(Vec3::new(1.0, 1.0, 1.0));
+
+	let matrix =
+		(transform.proj * transform.view * transform.model).to_cols_array();
+
+	// Two quads sharing one shader, told apart only by their color
+	let quad = |offset: f32, color| {
+		let vertices = vec![
+			Vertex::new([offset + 0.25, 0.25, 0.0]),
+			Vertex::new([offset + 0.25, -0.25, 0.0]),
+			Vertex::new([offset - 0.25, -0.25, 0.0]),
+			Vertex::new([offset - 0.25, 0.25, 0.0]),
+		];
+		let mut mesh = Mesh::new(&app, vertices, vec![0, 1, 3, 1, 2, 3]);
+		mesh.set_color(color);
+		mesh
+	};
+	let mut meshes = [
+		quad(-0.4, [1.0, 0.0, 0.0, 1.0]),
+		quad(0.4, [0.0, 0.5, 1.0, 1.0]),
+	];
+
+	app.run(
+		&matrix,
+		Box::new(move |rpass, app| {
+			app.get_queue().write_buffer(
+				app.get_uniform_buffer(),
+				0,
+				bytemuck::cast_slice(&[matrix]),
+			);
+			for mesh in &mut meshes {
+				mesh.render(rpass);
+			}
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
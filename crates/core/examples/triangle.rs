@@ -0,0 +1,59 @@
+use dyadikos_core::prelude::*;
+use wgpu::Color;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::fmt::init();
+
+	let shader = r#"
+	struct VertexOutput {
+		@builtin(position) position: vec4<f32>,
+		@location(0) color: vec4<f32>,
+	};
+
+	@group(0)
+	@binding(0)
+	var<uniform> transform: mat4x4<f32>;
+
+	@vertex
+	fn vs_main(
+		@location(0) position: vec3<f32>,
+		@location(1) color: vec4<f32>,
+	) -> VertexOutput {
+		var result: VertexOutput;
+		result.position = transform * vec4<f32>(position, 1.0);
+		result.color = color;
+		return result;
+	}
+
+	@fragment
+	fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+		return vertex.color;
+	}
+	"#;
+
+	let app = NativeApp::new(AppSettings {
+		shader: shader.to_string(),
+		background: Background::SolidColor(Color::BLACK),
+		..Default::default()
+	})
+	.await?;
+
+	let vertices = vec![
+		Vertex::with_color([0.0, 0.5, 0.0], [1.0, 0.0, 0.0, 1.0]),
+		Vertex::with_color([-0.5, -0.5, 0.0], [0.0, 1.0, 0.0, 1.0]),
+		Vertex::with_color([0.5, -0.5, 0.0], [0.0, 0.0, 1.0, 1.0]),
+	];
+	let mut mesh = Mesh::new(&app, vertices, vec![0, 1, 2]);
+
+	app.run(
+		&dyadikos_math::identity(),
+		Box::new(move |rpass, _| {
+			mesh.render(rpass);
+
+			Ok(())
+		}),
+	);
+
+	Ok(())
+}
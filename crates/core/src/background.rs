@@ -0,0 +1,153 @@
+use crate::{
+	depth::DepthSettings, fullscreen::fullscreen_pipeline, scene_color_targets,
+};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+	util::DeviceExt, BindGroup, Color, CompareFunction, DepthStencilState,
+	Device, MultisampleState, RenderPass, RenderPipeline, TextureFormat,
+};
+
+const GRADIENT_SHADER: &str = r#"
+struct Gradient {
+	top: vec4<f32>,
+	bottom: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> gradient: Gradient;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	return mix(gradient.bottom, gradient.top, vertex.uv.y);
+}
+"#;
+
+/// What is drawn behind the scene at the start of every frame
+///
+/// Colors are linear, like the clear color they replace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+	SolidColor(Color),
+	/// Vertical gradient from the top to the bottom of the screen
+	Gradient {
+		top: Color,
+		bottom: Color,
+	},
+}
+
+impl Default for Background {
+	fn default() -> Self {
+		Background::SolidColor(Color::default())
+	}
+}
+
+impl Background {
+	/// Color the render target is cleared to before the background is drawn
+	pub fn clear_color(&self) -> Color {
+		match self {
+			Background::SolidColor(color) => *color,
+			Background::Gradient { .. } => Color::BLACK,
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GradientUniform {
+	top: [f32; 4],
+	bottom: [f32; 4],
+}
+
+fn color_array(color: Color) -> [f32; 4] {
+	[color.r, color.g, color.b, color.a].map(|channel| channel as f32)
+}
+
+/// Full-screen pass drawing backgrounds that are more than a clear color
+pub struct BackgroundPass {
+	pipeline: RenderPipeline,
+	bind_group: BindGroup,
+}
+
+impl BackgroundPass {
+	/// Create the pass for a background, or `None` if clearing is enough
+	///
+	/// The gradient is only written to `format`, leaving the
+	/// [`crate::AppSettings::extra_targets`] untouched. With `depth` set the
+	/// pass is drawn into a render pass with a depth
+	/// buffer, which it leaves untouched.
+	pub fn new(
+		device: &Device,
+		format: TextureFormat,
+		extra_targets: &[TextureFormat],
+		depth: Option<&DepthSettings>,
+		multisample: MultisampleState,
+		background: &Background,
+	) -> Option<Self> {
+		let Background::Gradient { top, bottom } = *background else {
+			return None;
+		};
+
+		let uniform = GradientUniform {
+			top: color_array(top),
+			bottom: color_array(bottom),
+		};
+		let buffer =
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Background Gradient"),
+				contents: bytemuck::bytes_of(&uniform),
+				usage: wgpu::BufferUsages::UNIFORM,
+			});
+
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Background Gradient"),
+				entries: &[wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				}],
+			});
+
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Background Gradient"),
+			layout: &bind_group_layout,
+			entries: &[wgpu::BindGroupEntry {
+				binding: 0,
+				resource: buffer.as_entire_binding(),
+			}],
+		});
+
+		let pipeline = fullscreen_pipeline(
+			device,
+			"Background Gradient",
+			GRADIENT_SHADER,
+			&bind_group_layout,
+			&scene_color_targets(format, None, extra_targets),
+			depth.map(|depth| DepthStencilState {
+				depth_write_enabled: false,
+				depth_compare: CompareFunction::Always,
+				..depth.depth_stencil_state()
+			}),
+			multisample,
+		);
+
+		Some(Self {
+			pipeline,
+			bind_group,
+		})
+	}
+
+	/// Draw the background, leaving the pass ready for the scene's own
+	/// pipeline to be set
+	pub fn draw<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+		rpass.set_pipeline(&self.pipeline);
+		rpass.set_bind_group(0, &self.bind_group, &[]);
+		rpass.draw(0..3, 0..1);
+	}
+}
@@ -0,0 +1,359 @@
+use crate::{
+	fullscreen::{draw_fullscreen, fullscreen_pipeline},
+	tonemap::HDR_FORMAT,
+};
+use std::sync::Arc;
+use wgpu::{
+	util::DeviceExt, BindGroup, BindGroupLayout, BlendComponent, BlendFactor,
+	BlendOperation, BlendState, ColorTargetState, ColorWrites, CommandEncoder,
+	Device, Extent3d, LoadOp, MultisampleState, RenderPipeline, Sampler,
+	TextureDescriptor, TextureDimension, TextureUsages, TextureView,
+	TextureViewDescriptor,
+};
+
+/// Number of progressively halved textures the bright areas are blurred in
+const LEVELS: u32 = 4;
+
+const BLOOM_BINDINGS: &str = r#"
+@group(0)
+@binding(0)
+var source: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+@group(0)
+@binding(2)
+var<uniform> params: vec4<f32>;
+
+fn source_coords(vertex: VertexOutput) -> vec2<f32> {
+	return vec2<f32>(vertex.uv.x, 1.0 - vertex.uv.y);
+}
+"#;
+
+// params.x is the brightness threshold, 0 past the first level
+const DOWNSAMPLE_SHADER: &str = r#"
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	let color = textureSample(source, source_sampler, source_coords(vertex));
+	return vec4<f32>(max(color.rgb - vec3<f32>(params.x), vec3<f32>(0.0)), 1.0);
+}
+"#;
+
+// params.xy is the step between taps, one texel along the blur direction
+const BLUR_SHADER: &str = r#"
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+	let coords = source_coords(vertex);
+
+	var color = textureSample(source, source_sampler, coords).rgb * weights[0];
+	for (var i = 1; i < 5; i += 1) {
+		let offset = params.xy * f32(i);
+		color += textureSample(source, source_sampler, coords + offset).rgb * weights[i];
+		color += textureSample(source, source_sampler, coords - offset).rgb * weights[i];
+	}
+
+	return vec4<f32>(color, 1.0);
+}
+"#;
+
+// params.x is the intensity the blurred level is added with
+const COMPOSITE_SHADER: &str = r#"
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	let color = textureSample(source, source_sampler, source_coords(vertex));
+	return vec4<f32>(color.rgb * params.x, 1.0);
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+	/// Brightness above which colors start to bloom
+	pub threshold: f32,
+	/// Scale of the blurred light added back onto the scene
+	pub intensity: f32,
+}
+
+impl Default for BloomSettings {
+	fn default() -> Self {
+		Self {
+			threshold: 1.0,
+			intensity: 0.5,
+		}
+	}
+}
+
+#[derive(Clone)]
+struct BloomLevel {
+	/// Holds the downsampled and then fully blurred image
+	view: Arc<TextureView>,
+	/// Holds the image between the horizontal and vertical blur
+	scratch: Arc<TextureView>,
+	downsample: Arc<BindGroup>,
+	blur_horizontal: Arc<BindGroup>,
+	blur_vertical: Arc<BindGroup>,
+	composite: Arc<BindGroup>,
+}
+
+/// Adds a blurred copy of the brightest parts of the HDR target back onto
+/// it, before tonemapping
+#[derive(Clone)]
+pub struct BloomPass {
+	settings: BloomSettings,
+	bind_group_layout: Arc<BindGroupLayout>,
+	sampler: Arc<Sampler>,
+	downsample: Arc<RenderPipeline>,
+	blur: Arc<RenderPipeline>,
+	composite: Arc<RenderPipeline>,
+	levels: Vec<BloomLevel>,
+}
+
+impl BloomPass {
+	/// Create the pass for an HDR target of the given size
+	pub fn new(
+		device: &Device,
+		settings: BloomSettings,
+		scene: &TextureView,
+		width: u32,
+		height: u32,
+	) -> Self {
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Bloom"),
+				entries: &[
+					wgpu::BindGroupLayoutEntry {
+						binding: 0,
+						visibility: wgpu::ShaderStages::FRAGMENT,
+						ty: wgpu::BindingType::Texture {
+							sample_type: wgpu::TextureSampleType::Float {
+								filterable: true,
+							},
+							view_dimension: wgpu::TextureViewDimension::D2,
+							multisampled: false,
+						},
+						count: None,
+					},
+					wgpu::BindGroupLayoutEntry {
+						binding: 1,
+						visibility: wgpu::ShaderStages::FRAGMENT,
+						ty: wgpu::BindingType::Sampler(
+							wgpu::SamplerBindingType::Filtering,
+						),
+						count: None,
+					},
+					wgpu::BindGroupLayoutEntry {
+						binding: 2,
+						visibility: wgpu::ShaderStages::FRAGMENT,
+						ty: wgpu::BindingType::Buffer {
+							ty: wgpu::BufferBindingType::Uniform,
+							has_dynamic_offset: false,
+							min_binding_size: None,
+						},
+						count: None,
+					},
+				],
+			});
+
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Bloom"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+
+		let pipeline = |label: &str, shader: &str, blend| {
+			Arc::new(fullscreen_pipeline(
+				device,
+				label,
+				&format!("{BLOOM_BINDINGS}{shader}"),
+				&bind_group_layout,
+				&[Some(ColorTargetState {
+					format: HDR_FORMAT,
+					blend,
+					write_mask: ColorWrites::ALL,
+				})],
+				None,
+				MultisampleState::default(),
+			))
+		};
+		let downsample = pipeline("Bloom Downsample", DOWNSAMPLE_SHADER, None);
+		let blur = pipeline("Bloom Blur", BLUR_SHADER, None);
+		// Add onto the scene, leaving its alpha untouched
+		let composite = pipeline(
+			"Bloom Composite",
+			COMPOSITE_SHADER,
+			Some(BlendState {
+				color: BlendComponent {
+					src_factor: BlendFactor::One,
+					dst_factor: BlendFactor::One,
+					operation: BlendOperation::Add,
+				},
+				alpha: BlendComponent {
+					src_factor: BlendFactor::Zero,
+					dst_factor: BlendFactor::One,
+					operation: BlendOperation::Add,
+				},
+			}),
+		);
+
+		let mut pass = Self {
+			settings,
+			bind_group_layout: Arc::new(bind_group_layout),
+			sampler: Arc::new(sampler),
+			downsample,
+			blur,
+			composite,
+			levels: Vec::new(),
+		};
+		pass.resize(device, scene, width, height);
+
+		pass
+	}
+
+	/// Recreate the blur levels for a resized HDR target
+	pub fn resize(
+		&mut self,
+		device: &Device,
+		scene: &TextureView,
+		width: u32,
+		height: u32,
+	) {
+		self.levels.clear();
+
+		let (mut width, mut height) = (width, height);
+		for _ in 0..LEVELS {
+			width = (width / 2).max(1);
+			height = (height / 2).max(1);
+
+			let view = Arc::new(create_level_view(device, width, height));
+			let scratch = Arc::new(create_level_view(device, width, height));
+			let previous = self.levels.last().map(|level| level.view.clone());
+			let (source, threshold) = match &previous {
+				None => (scene, self.settings.threshold),
+				Some(previous) => (&**previous, 0.0),
+			};
+
+			let texel = [1.0 / width as f32, 1.0 / height as f32];
+			let level = BloomLevel {
+				downsample: self.create_bind_group(
+					device,
+					source,
+					[threshold, 0.0, 0.0, 0.0],
+				),
+				blur_horizontal: self.create_bind_group(
+					device,
+					&view,
+					[texel[0], 0.0, 0.0, 0.0],
+				),
+				blur_vertical: self.create_bind_group(
+					device,
+					&scratch,
+					[0.0, texel[1], 0.0, 0.0],
+				),
+				composite: self.create_bind_group(
+					device,
+					&view,
+					[self.settings.intensity, 0.0, 0.0, 0.0],
+				),
+				view,
+				scratch,
+			};
+			self.levels.push(level);
+		}
+	}
+
+	/// Record the bloom passes, adding the result onto `scene`
+	pub fn encode(&self, encoder: &mut CommandEncoder, scene: &TextureView) {
+		let clear = LoadOp::Clear(wgpu::Color::BLACK);
+
+		for level in &self.levels {
+			draw_fullscreen(
+				encoder,
+				&level.view,
+				&self.downsample,
+				&level.downsample,
+				clear,
+			);
+			draw_fullscreen(
+				encoder,
+				&level.scratch,
+				&self.blur,
+				&level.blur_horizontal,
+				clear,
+			);
+			draw_fullscreen(
+				encoder,
+				&level.view,
+				&self.blur,
+				&level.blur_vertical,
+				clear,
+			);
+		}
+
+		for level in &self.levels {
+			draw_fullscreen(
+				encoder,
+				scene,
+				&self.composite,
+				&level.composite,
+				LoadOp::Load,
+			);
+		}
+	}
+
+	fn create_bind_group(
+		&self,
+		device: &Device,
+		source: &TextureView,
+		params: [f32; 4],
+	) -> Arc<BindGroup> {
+		let buffer =
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Bloom Parameters"),
+				contents: bytemuck::cast_slice(&params),
+				usage: wgpu::BufferUsages::UNIFORM,
+			});
+
+		Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Bloom"),
+			layout: &self.bind_group_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(source),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(&self.sampler),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: buffer.as_entire_binding(),
+				},
+			],
+		}))
+	}
+}
+
+fn create_level_view(device: &Device, width: u32, height: u32) -> TextureView {
+	device
+		.create_texture(&TextureDescriptor {
+			label: Some("Bloom Level"),
+			size: Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: HDR_FORMAT,
+			usage: TextureUsages::RENDER_ATTACHMENT
+				| TextureUsages::TEXTURE_BINDING,
+		})
+		.create_view(&TextureViewDescriptor::default())
+}
@@ -0,0 +1,346 @@
+use crate::App;
+use anyhow::{bail, Context, Result};
+use bytemuck::Pod;
+use std::{
+	future::Future,
+	marker::PhantomData,
+	pin::Pin,
+	sync::{Arc, Mutex},
+	task::{self, Poll, Waker},
+};
+use wgpu::{
+	util::DeviceExt, BindingResource, Buffer, BufferAddress, BufferAsyncError,
+	BufferUsages, CommandEncoderDescriptor, Device, Maintain, MapMode, Queue,
+};
+
+/// A storage buffer along with its size, which wgpu 0.13 buffers can't
+/// report themselves
+pub struct StorageBuffer {
+	buffer: Buffer,
+	size: BufferAddress,
+}
+
+impl StorageBuffer {
+	pub fn buffer(&self) -> &Buffer {
+		&self.buffer
+	}
+
+	/// Size in bytes, including the padding up to
+	/// [`wgpu::COPY_BUFFER_ALIGNMENT`]
+	pub fn size(&self) -> BufferAddress {
+		self.size
+	}
+
+	pub fn as_entire_binding(&self) -> BindingResource<'_> {
+		self.buffer.as_entire_binding()
+	}
+}
+
+/// Create a storage buffer initialized with `data`, checking that it fits in
+/// a single storage binding on this device
+///
+/// The buffer can also be copied to and from, so results written by a
+/// shader can be read back.
+pub fn create_storage_buffer<T: Pod>(
+	device: &Device,
+	data: &[T],
+) -> Result<StorageBuffer> {
+	let size = std::mem::size_of_val(data) as u64;
+	let max_size = device.limits().max_storage_buffer_binding_size as u64;
+	if size > max_size {
+		bail!(
+			"Storage buffer of {size} bytes exceeds the device limit of \
+			 {max_size} bytes"
+		);
+	}
+
+	let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: Some("Storage Buffer"),
+		contents: bytemuck::cast_slice(data),
+		usage: BufferUsages::STORAGE
+			| BufferUsages::COPY_SRC
+			| BufferUsages::COPY_DST,
+	});
+
+	// `create_buffer_init` pads the contents the same way
+	let alignment = wgpu::COPY_BUFFER_ALIGNMENT;
+	Ok(StorageBuffer {
+		buffer,
+		size: (size.div_ceil(alignment) * alignment).max(alignment),
+	})
+}
+
+/// Copy the first `len` elements of `buffer`, which is `buffer_size` bytes
+/// long, back to the CPU, once the work submitted before the call has
+/// written them
+///
+/// The buffer needs [`BufferUsages::COPY_SRC`]. The device is polled without
+/// blocking whenever the future is, so nothing else has to drive it, but the
+/// executor is kept busy until the copy can be read.
+pub async fn read_buffer<T: Pod>(
+	device: &Device,
+	queue: &Queue,
+	buffer: &Buffer,
+	buffer_size: BufferAddress,
+	len: usize,
+) -> Result<Vec<T>> {
+	let (staging, size) =
+		copy_to_staging::<T>(device, queue, buffer, buffer_size, len)?;
+
+	let state = Arc::new(Mutex::new(MapState::default()));
+	let callback_state = state.clone();
+	staging.slice(..).map_async(MapMode::Read, move |result| {
+		let mut state = callback_state.lock().unwrap();
+		state.result = Some(result);
+		if let Some(waker) = state.waker.take() {
+			waker.wake();
+		}
+	});
+	MapFuture { device, state }
+		.await
+		.context("Failed to map the staging buffer")?;
+
+	Ok(read_staging(&staging, size))
+}
+
+pub(crate) fn read_buffer_blocking<T: Pod>(
+	device: &Device,
+	queue: &Queue,
+	buffer: &Buffer,
+	buffer_size: BufferAddress,
+	len: usize,
+) -> Result<Vec<T>> {
+	let (staging, size) =
+		copy_to_staging::<T>(device, queue, buffer, buffer_size, len)?;
+
+	let (sender, receiver) = std::sync::mpsc::channel();
+	staging.slice(..).map_async(MapMode::Read, move |result| {
+		let _ = sender.send(result);
+	});
+	device.poll(Maintain::Wait);
+	receiver
+		.recv()
+		.context("Buffer mapping was never completed")?
+		.context("Failed to map the staging buffer")?;
+
+	Ok(read_staging(&staging, size))
+}
+
+/// Submit a copy of the first `len` elements of `buffer`, which is
+/// `buffer_size` bytes long, into a new staging buffer, returning it with the
+/// size of the elements in bytes
+///
+/// Copies have to be a multiple of [`wgpu::COPY_BUFFER_ALIGNMENT`] long, so
+/// the staging buffer can be a few bytes longer than the elements.
+fn copy_to_staging<T: Pod>(
+	device: &Device,
+	queue: &Queue,
+	buffer: &Buffer,
+	buffer_size: BufferAddress,
+	len: usize,
+) -> Result<(Buffer, usize)> {
+	let size = len
+		.checked_mul(std::mem::size_of::<T>())
+		.context("Buffer read size overflows")?;
+	let alignment = wgpu::COPY_BUFFER_ALIGNMENT;
+	let padded = (size as BufferAddress).div_ceil(alignment) * alignment;
+	if padded > buffer_size {
+		bail!(
+			"Reading {len} elements takes {padded} bytes, but the buffer \
+			 only has {buffer_size}"
+		);
+	}
+
+	let staging = device.create_buffer(&wgpu::BufferDescriptor {
+		label: Some("Staging Buffer"),
+		size: padded,
+		usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	});
+
+	let mut encoder = device
+		.create_command_encoder(&CommandEncoderDescriptor { label: None });
+	encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, padded);
+	queue.submit(Some(encoder.finish()));
+
+	Ok((staging, size))
+}
+
+/// The first `size` bytes of a mapped staging buffer, unmapping it
+fn read_staging<T: Pod>(staging: &Buffer, size: usize) -> Vec<T> {
+	let data =
+		bytemuck::cast_slice(&staging.slice(..).get_mapped_range()[..size])
+			.to_vec();
+	staging.unmap();
+
+	data
+}
+
+#[derive(Default)]
+struct MapState {
+	result: Option<std::result::Result<(), BufferAsyncError>>,
+	waker: Option<Waker>,
+}
+
+/// Resolves once a `map_async` callback has stored its result in `state`
+struct MapFuture<'a> {
+	device: &'a Device,
+	state: Arc<Mutex<MapState>>,
+}
+
+impl Future for MapFuture<'_> {
+	type Output = std::result::Result<(), BufferAsyncError>;
+
+	fn poll(
+		self: Pin<&mut Self>,
+		cx: &mut task::Context,
+	) -> Poll<Self::Output> {
+		self.device.poll(Maintain::Poll);
+
+		let mut state = self.state.lock().unwrap();
+		if let Some(result) = state.result.take() {
+			return Poll::Ready(result);
+		}
+		state.waker = Some(cx.waker().clone());
+		// Browsers run the callback on their own, but native devices only
+		// do when polled, so ask to be polled again
+		#[cfg(not(target_arch = "wasm32"))]
+		cx.waker().wake_by_ref();
+
+		Poll::Pending
+	}
+}
+
+/// A uniform buffer holding a single value of `T`
+pub struct UniformBuffer<T> {
+	buffer: Arc<Buffer>,
+	value: PhantomData<T>,
+}
+
+impl<T: Pod> UniformBuffer<T> {
+	pub fn new(app: &impl App, value: &T) -> Self {
+		Self::with_device(app.get_device(), value)
+	}
+
+	fn with_device(device: &Device, value: &T) -> Self {
+		let buffer =
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Uniform Buffer"),
+				contents: bytemuck::bytes_of(value),
+				// COPY_SRC lets the current value be read back
+				usage: BufferUsages::UNIFORM
+					| BufferUsages::COPY_DST
+					| BufferUsages::COPY_SRC,
+			});
+
+		Self {
+			buffer: Arc::new(buffer),
+			value: PhantomData,
+		}
+	}
+
+	/// Upload a new value, taking effect from the next submitted frame
+	pub fn update(&self, app: &dyn App, value: &T) {
+		self.write(app.get_queue(), value);
+	}
+
+	fn write(&self, queue: &Queue, value: &T) {
+		queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(value));
+	}
+
+	pub fn buffer(&self) -> &Arc<Buffer> {
+		&self.buffer
+	}
+
+	pub fn as_entire_binding(&self) -> BindingResource<'_> {
+		self.buffer.as_entire_binding()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{device::request_device, LimitsPreset};
+	use wgpu::{Backends, Features, Instance, PowerPreference};
+
+	#[tokio::test]
+	async fn read_buffer_round_trip() {
+		let instance = Instance::new(Backends::all());
+		let Ok((_, device, queue)) = request_device(
+			&instance,
+			None,
+			Features::empty(),
+			LimitsPreset::Downlevel,
+			PowerPreference::default(),
+			true,
+		)
+		.await
+		else {
+			eprintln!("Skipping, no adapter is available");
+			return;
+		};
+
+		let buffer = create_storage_buffer(&device, &[1u16, 2, 3]).unwrap();
+		assert_eq!(buffer.size(), 8);
+		let read = |len| {
+			read_buffer::<u16>(
+				&device,
+				&queue,
+				buffer.buffer(),
+				buffer.size(),
+				len,
+			)
+		};
+		assert_eq!(read(3).await.unwrap(), [1, 2, 3]);
+		// 2 bytes, padded to 4 for the copy
+		assert_eq!(read(1).await.unwrap(), [1]);
+		assert!(read(5).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn uniform_buffer_update_round_trip() {
+		#[derive(Clone, Copy, PartialEq, Debug, Pod, bytemuck::Zeroable)]
+		#[repr(C)]
+		struct Light {
+			position: [f32; 3],
+			intensity: f32,
+		}
+
+		let instance = Instance::new(Backends::all());
+		let Ok((_, device, queue)) = request_device(
+			&instance,
+			None,
+			Features::empty(),
+			LimitsPreset::Downlevel,
+			PowerPreference::default(),
+			true,
+		)
+		.await
+		else {
+			eprintln!("Skipping, no adapter is available");
+			return;
+		};
+
+		let light = Light {
+			position: [0.0; 3],
+			intensity: 1.0,
+		};
+		let buffer = UniformBuffer::with_device(&device, &light);
+		let updated = Light {
+			position: [1.0, 2.0, 3.0],
+			intensity: 0.5,
+		};
+		buffer.write(&queue, &updated);
+
+		let read = read_buffer::<Light>(
+			&device,
+			&queue,
+			buffer.buffer(),
+			std::mem::size_of::<Light>() as BufferAddress,
+			1,
+		)
+		.await
+		.unwrap();
+		assert_eq!(read, [updated]);
+	}
+}
@@ -0,0 +1,122 @@
+use dyadikos_math::camera::Camera;
+use glam::Vec3;
+use std::collections::HashSet;
+use winit::event::{
+	ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent,
+};
+
+/// Flies a [`Camera`] around with WASD, Space and Left Shift, and turns it
+/// with the mouse
+///
+/// Set it as [`crate::AppSettings::camera`] to have
+/// [`crate::native::NativeApp`] feed it events and upload the camera's
+/// view-projection every frame.
+#[derive(Debug, Clone)]
+pub struct CameraController {
+	pub camera: Camera,
+	/// Units moved per second
+	pub speed: f32,
+	/// Radians turned per unit of mouse movement, which is roughly a pixel
+	pub sensitivity: f32,
+	/// Button that has to be held to turn the camera, or `None` to turn it
+	/// whenever the mouse moves, which suits a grabbed cursor
+	pub look_button: Option<MouseButton>,
+	held: HashSet<VirtualKeyCode>,
+	looking: bool,
+}
+
+impl CameraController {
+	pub fn new(camera: Camera) -> Self {
+		Self {
+			camera,
+			speed: 2.0,
+			sensitivity: 0.003,
+			look_button: Some(MouseButton::Right),
+			held: HashSet::new(),
+			looking: false,
+		}
+	}
+
+	/// Track the movement keys and look button, returning whether the
+	/// event was one of them
+	pub fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+		match event {
+			WindowEvent::KeyboardInput {
+				input:
+					KeyboardInput {
+						state,
+						virtual_keycode: Some(key),
+						..
+					},
+				..
+			} if direction(*key).is_some() => {
+				match state {
+					ElementState::Pressed => self.held.insert(*key),
+					ElementState::Released => self.held.remove(key),
+				};
+
+				true
+			}
+			WindowEvent::MouseInput { state, button, .. }
+				if self.look_button == Some(*button) =>
+			{
+				self.looking = *state == ElementState::Pressed;
+
+				true
+			}
+			// Keys released while unfocused never send their release
+			WindowEvent::Focused(false) => {
+				self.held.clear();
+				self.looking = false;
+
+				false
+			}
+			_ => false,
+		}
+	}
+
+	/// Turn the camera by a raw mouse movement, as reported by
+	/// `DeviceEvent::MouseMotion`, returning whether it turned
+	pub fn process_mouse_motion(&mut self, (x, y): (f64, f64)) -> bool {
+		if self.look_button.is_some() && !self.looking {
+			return false;
+		}
+
+		self.camera
+			.rotate(-x as f32 * self.sensitivity, -y as f32 * self.sensitivity);
+
+		true
+	}
+
+	/// Whether a movement key is held, so the camera keeps moving
+	pub fn is_moving(&self) -> bool {
+		!self.held.is_empty()
+	}
+
+	/// Move the camera by the held keys over `elapsed` seconds
+	pub fn update(&mut self, elapsed: f32) {
+		let (forward, right) = (self.camera.forward(), self.camera.right());
+		let direction: Vec3 = self
+			.held
+			.iter()
+			.filter_map(|key| direction(*key))
+			.map(|[f, r, u]| forward * f + right * r + Vec3::Y * u)
+			.sum();
+
+		self.camera.position +=
+			direction.normalize_or_zero() * self.speed * elapsed;
+	}
+}
+
+/// Forward, right and up movement of a key
+fn direction(key: VirtualKeyCode) -> Option<[f32; 3]> {
+	Some(match key {
+		VirtualKeyCode::W => [1.0, 0.0, 0.0],
+		VirtualKeyCode::S => [-1.0, 0.0, 0.0],
+		VirtualKeyCode::D => [0.0, 1.0, 0.0],
+		VirtualKeyCode::A => [0.0, -1.0, 0.0],
+		VirtualKeyCode::Space => [0.0, 0.0, 1.0],
+		VirtualKeyCode::LShift => [0.0, 0.0, -1.0],
+		_ => return None,
+	})
+}
@@ -0,0 +1,222 @@
+use crate::{
+	buffer::{create_storage_buffer, read_buffer_blocking, StorageBuffer},
+	device::request_device,
+	error::DyadikosError,
+	reflect::{validate_limits, BindingInfo, ShaderReflection},
+	LimitsPreset,
+};
+use anyhow::{bail, Result};
+use bytemuck::Pod;
+use std::{borrow::Cow, sync::Arc};
+use wgpu::{
+	Backends, BindGroup, BindGroupLayout, BindingType,
+	CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline,
+	ComputePipelineDescriptor, Device, Features, Instance,
+	PipelineLayoutDescriptor, PowerPreference, Queue, ShaderModuleDescriptor,
+	ShaderSource, ShaderStages,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ComputeSettings {
+	/// WGSL source of the compute shader
+	pub shader: String,
+	pub entry_point: String,
+	pub features: Features,
+	pub power_preference: PowerPreference,
+	/// Retry with a software adapter, such as lavapipe or WARP, when no
+	/// hardware adapter is available
+	pub allow_software_fallback: bool,
+}
+
+/// Headless app running a single compute pipeline
+#[derive(Clone)]
+pub struct ComputeApp {
+	pub device: Arc<Device>,
+	pub queue: Arc<Queue>,
+	pub settings: ComputeSettings,
+	pub pipeline: Arc<ComputePipeline>,
+	pub bind_group: Option<Arc<BindGroup>>,
+	pub bind_group_layout: Arc<BindGroupLayout>,
+	pub bindings: Vec<BindingInfo>,
+}
+
+impl ComputeApp {
+	pub async fn new(settings: ComputeSettings) -> Result<Self, DyadikosError> {
+		let instance = Instance::new(Backends::all());
+		let (_, device, queue) = request_device(
+			&instance,
+			None,
+			settings.features,
+			LimitsPreset::Downlevel,
+			settings.power_preference,
+			settings.allow_software_fallback,
+		)
+		.await?;
+
+		let reflection = ShaderReflection::from_wgsl(&settings.shader)
+			.map_err(DyadikosError::shader)?;
+		let has_entry_point = reflection.module.entry_points.iter().any(|ep| {
+			ep.stage == naga::ShaderStage::Compute
+				&& ep.name == settings.entry_point
+		});
+		if !has_entry_point {
+			return Err(DyadikosError::ShaderCompile {
+				stage: Some(ShaderStages::COMPUTE),
+				message: format!(
+					"Shader has no compute entry point `{}`",
+					settings.entry_point
+				),
+			});
+		}
+
+		let bindings = reflection.bindings().map_err(DyadikosError::shader)?;
+		if let Some(info) = bindings.iter().find(|info| info.group != 0) {
+			return Err(DyadikosError::ShaderCompile {
+				stage: Some(ShaderStages::COMPUTE),
+				message: format!(
+					"Binding {} is in group {}, but compute shaders can only \
+					 bind group 0",
+					info.binding, info.group
+				),
+			});
+		}
+		validate_limits(&bindings, &device.limits()).map_err(|error| {
+			DyadikosError::LimitsExceeded(format!("{error:#}"))
+		})?;
+		let layout_entries: Vec<_> =
+			bindings.iter().map(BindingInfo::layout_entry).collect();
+
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: None,
+				entries: &layout_entries,
+			});
+
+		let pipeline_layout =
+			device.create_pipeline_layout(&PipelineLayoutDescriptor {
+				label: None,
+				bind_group_layouts: &[&bind_group_layout],
+				push_constant_ranges: &[],
+			});
+
+		let shader = device.create_shader_module(ShaderModuleDescriptor {
+			label: None,
+			source: ShaderSource::Wgsl(Cow::Borrowed(&settings.shader)),
+		});
+
+		let pipeline =
+			device.create_compute_pipeline(&ComputePipelineDescriptor {
+				label: None,
+				layout: Some(&pipeline_layout),
+				module: &shader,
+				entry_point: &settings.entry_point,
+			});
+
+		Ok(ComputeApp {
+			device: Arc::new(device),
+			queue: Arc::new(queue),
+			pipeline: Arc::new(pipeline),
+			bind_group: None,
+			bind_group_layout: Arc::new(bind_group_layout),
+			bindings,
+			settings,
+		})
+	}
+
+	pub fn create_storage_buffer<T: Pod>(
+		&self,
+		data: &[T],
+	) -> Result<StorageBuffer> {
+		create_storage_buffer(&self.device, data)
+	}
+
+	/// Bind one buffer to each of the shader's group 0 bindings, in
+	/// binding order
+	pub fn set_buffers(&mut self, buffers: &[&StorageBuffer]) -> Result<()> {
+		if buffers.len() != self.bindings.len() {
+			bail!(
+				"Compute shader expects {} buffers, got {}",
+				self.bindings.len(),
+				buffers.len()
+			);
+		}
+
+		if let Some(info) = self
+			.bindings
+			.iter()
+			.find(|info| !matches!(info.ty, BindingType::Buffer { .. }))
+		{
+			bail!(
+				"Compute shader binding {} is a {:?}, not a buffer",
+				info.binding,
+				info.ty
+			);
+		}
+
+		let entries: Vec<_> = self
+			.bindings
+			.iter()
+			.zip(buffers)
+			.map(|(info, buffer)| wgpu::BindGroupEntry {
+				binding: info.binding,
+				resource: buffer.as_entire_binding(),
+			})
+			.collect();
+
+		self.bind_group = Some(Arc::new(self.device.create_bind_group(
+			&wgpu::BindGroupDescriptor {
+				label: None,
+				layout: &self.bind_group_layout,
+				entries: &entries,
+			},
+		)));
+
+		Ok(())
+	}
+
+	/// Run the compute shader over `x * y * z` workgroups
+	///
+	/// Shaders with bindings need [`ComputeApp::set_buffers`] to be called
+	/// first.
+	pub fn dispatch(&self, x: u32, y: u32, z: u32) -> Result<()> {
+		if !self.bindings.is_empty() && self.bind_group.is_none() {
+			bail!(
+				"Compute shader has {} bindings, but no buffers were set",
+				self.bindings.len()
+			);
+		}
+
+		let mut encoder = self
+			.device
+			.create_command_encoder(&CommandEncoderDescriptor { label: None });
+		{
+			let mut cpass = encoder
+				.begin_compute_pass(&ComputePassDescriptor { label: None });
+			cpass.set_pipeline(&self.pipeline);
+			if let Some(bind_group) = &self.bind_group {
+				cpass.set_bind_group(0, bind_group, &[]);
+			}
+			cpass.dispatch_workgroups(x, y, z);
+		}
+
+		self.queue.submit(Some(encoder.finish()));
+
+		Ok(())
+	}
+
+	/// Copy the first `len` elements of a storage buffer back to the CPU,
+	/// blocking until the GPU has finished writing them
+	pub fn read_buffer<T: Pod>(
+		&self,
+		buffer: &StorageBuffer,
+		len: usize,
+	) -> Result<Vec<T>> {
+		read_buffer_blocking(
+			&self.device,
+			&self.queue,
+			buffer.buffer(),
+			buffer.size(),
+			len,
+		)
+	}
+}
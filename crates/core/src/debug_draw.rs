@@ -0,0 +1,233 @@
+use crate::{scene_color_targets, App, ArcRenderPass};
+use bytemuck::{Pod, Zeroable};
+use dyadikos_math::{bounds::Aabb, Vector3};
+use std::{borrow::Cow, sync::Arc};
+use wgpu::{
+	util::DeviceExt, BindGroup, Color, FragmentState, PipelineLayoutDescriptor,
+	PrimitiveState, PrimitiveTopology, RenderPipeline,
+	RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource,
+	VertexState,
+};
+
+const DEBUG_SHADER: &str = r#"
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> transform: mat4x4<f32>;
+
+@vertex
+fn vs_main(
+	@location(0) position: vec3<f32>,
+	@location(1) color: vec4<f32>,
+) -> VertexOutput {
+	var result: VertexOutput;
+	result.position = transform * vec4<f32>(position, 1.0);
+	result.color = color;
+	return result;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	return vertex.color;
+}
+"#;
+
+const GRID_COLOR: Color = Color {
+	r: 0.3,
+	g: 0.3,
+	b: 0.3,
+	a: 1.0,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct DebugVertex {
+	position: Vector3,
+	color: [f32; 4],
+}
+
+/// Batches debug lines into a single vertex buffer drawn once per frame
+///
+/// Lines are transformed by the app's uniform matrix, like the scene.
+pub struct DebugDraw {
+	vertices: Vec<DebugVertex>,
+	pipeline: Arc<RenderPipeline>,
+	bind_group: Arc<BindGroup>,
+}
+
+impl DebugDraw {
+	pub fn new(app: &impl App) -> Self {
+		let device = app.get_device();
+
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Debug Draw"),
+				entries: &[wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::VERTEX,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				}],
+			});
+
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Debug Draw"),
+			layout: &bind_group_layout,
+			entries: &[wgpu::BindGroupEntry {
+				binding: 0,
+				resource: app.get_uniform_buffer().as_entire_binding(),
+			}],
+		});
+
+		let pipeline_layout =
+			device.create_pipeline_layout(&PipelineLayoutDescriptor {
+				label: Some("Debug Draw"),
+				bind_group_layouts: &[&bind_group_layout],
+				push_constant_ranges: &[],
+			});
+
+		let shader = device.create_shader_module(ShaderModuleDescriptor {
+			label: Some("Debug Draw"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(DEBUG_SHADER)),
+		});
+
+		let pipeline =
+			device.create_render_pipeline(&RenderPipelineDescriptor {
+				label: Some("Debug Draw"),
+				layout: Some(&pipeline_layout),
+				vertex: VertexState {
+					module: &shader,
+					entry_point: "vs_main",
+					buffers: &[wgpu::VertexBufferLayout {
+						array_stride: std::mem::size_of::<DebugVertex>()
+							as wgpu::BufferAddress,
+						step_mode: wgpu::VertexStepMode::Vertex,
+						attributes: &wgpu::vertex_attr_array![
+							0 => Float32x3,
+							1 => Float32x4,
+						],
+					}],
+				},
+				fragment: Some(FragmentState {
+					module: &shader,
+					entry_point: "fs_main",
+					targets: &scene_color_targets(
+						app.get_surface_format(),
+						None,
+						&app.get_settings().extra_targets,
+					),
+				}),
+				primitive: PrimitiveState {
+					topology: PrimitiveTopology::LineList,
+					..Default::default()
+				},
+				depth_stencil: app
+					.get_settings()
+					.depth
+					.map(|depth| depth.depth_stencil_state()),
+				multisample: app.get_settings().multisample_state(),
+				multiview: None,
+			});
+
+		Self {
+			vertices: Vec::new(),
+			pipeline: Arc::new(pipeline),
+			bind_group: Arc::new(bind_group),
+		}
+	}
+
+	pub fn draw_line(&mut self, start: Vector3, end: Vector3, color: Color) {
+		let color = [color.r, color.g, color.b, color.a].map(|c| c as f32);
+
+		self.vertices.extend([
+			DebugVertex {
+				position: start,
+				color,
+			},
+			DebugVertex {
+				position: end,
+				color,
+			},
+		]);
+	}
+
+	/// Draw the X, Y and Z axes from the origin in red, green and blue
+	pub fn draw_axes(&mut self, length: f32) {
+		self.draw_line([0.0; 3], [length, 0.0, 0.0], Color::RED);
+		self.draw_line([0.0; 3], [0.0, length, 0.0], Color::GREEN);
+		self.draw_line([0.0; 3], [0.0, 0.0, length], Color::BLUE);
+	}
+
+	/// Draw a square grid on the XZ plane, centered on the origin
+	///
+	/// Nothing is drawn unless `spacing` is positive and divides `size` into
+	/// a finite number of cells.
+	pub fn draw_grid(&mut self, size: f32, spacing: f32) {
+		let half = size / 2.0;
+		let lines = (size / spacing).floor();
+		if spacing <= 0.0 || !lines.is_finite() {
+			return;
+		}
+		let lines = lines as u32;
+
+		for i in 0..=lines {
+			let offset = -half + i as f32 * spacing;
+			self.draw_line(
+				[offset, 0.0, -half],
+				[offset, 0.0, half],
+				GRID_COLOR,
+			);
+			self.draw_line(
+				[-half, 0.0, offset],
+				[half, 0.0, offset],
+				GRID_COLOR,
+			);
+		}
+	}
+
+	pub fn draw_aabb(&mut self, aabb: &Aabb, color: Color) {
+		let corners = aabb.corners();
+
+		// Join every pair of corners that differ along a single axis
+		for i in 0..corners.len() {
+			for axis in [1, 2, 4] {
+				if i & axis == 0 {
+					self.draw_line(corners[i], corners[i | axis], color);
+				}
+			}
+		}
+	}
+
+	/// Draw and clear the lines queued this frame
+	///
+	/// This replaces the pipeline and group 0 bind group, so it should come
+	/// after the scene has been drawn.
+	pub fn render(&mut self, app: &dyn App, rpass: &mut ArcRenderPass) {
+		if self.vertices.is_empty() {
+			return;
+		}
+
+		let vertex_buffer = app.get_device().create_buffer_init(
+			&wgpu::util::BufferInitDescriptor {
+				label: Some("Debug Draw Vertex Buffer"),
+				contents: bytemuck::cast_slice(&self.vertices),
+				usage: wgpu::BufferUsages::VERTEX,
+			},
+		);
+
+		rpass.set_pipeline(self.pipeline.clone());
+		rpass.set_bind_group(0, self.bind_group.clone(), &[]);
+		rpass.set_vertex_buffer(0, Arc::new(vertex_buffer));
+		rpass.draw(0..self.vertices.len() as u32, 0..1);
+
+		self.vertices.clear();
+	}
+}
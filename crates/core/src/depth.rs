@@ -0,0 +1,268 @@
+use crate::fullscreen::fullscreen_pipeline;
+use std::sync::Arc;
+use wgpu::{
+	BindGroup, BindGroupLayout, CommandEncoder, CompareFunction,
+	DepthBiasState, DepthStencilState, Device, Extent3d, LoadOp,
+	MultisampleState, Operations, RenderPassDepthStencilAttachment,
+	RenderPassDescriptor, RenderPipeline, StencilState, TextureDescriptor,
+	TextureDimension, TextureFormat, TextureUsages, TextureView,
+	TextureViewDescriptor,
+};
+
+/// Format of the depth buffer created when [`crate::AppSettings::depth`] is
+/// set
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// How the depth buffer is cleared and tested
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthSettings {
+	/// Value the depth buffer is cleared to at the start of every frame
+	pub clear_depth: f32,
+	/// Fragments pass when `compare(fragment depth, stored depth)` holds
+	pub compare: CompareFunction,
+	/// Offset added to the depth of every fragment, so coplanar geometry
+	/// such as decals and overlays doesn't z-fight with the surface below
+	///
+	/// The offset is `constant` times the smallest depth step plus
+	/// `slope_scale` times the polygon's depth slope, limited to `clamp`
+	/// unless that is 0. To pull geometry toward the camera use negative
+	/// values with [`CompareFunction::Less`] and positive ones with
+	/// [`DepthSettings::reverse_z`]; decals typically need a constant of 1
+	/// to 4 and a slope scale of 1 to 2 in that direction. Shadow maps push
+	/// the other way to avoid acne.
+	///
+	/// Every pipeline built from these settings is biased, so decals are
+	/// usually drawn with a pipeline of their own, declaring a biased copy
+	/// of the app's settings.
+	pub bias: DepthBiasState,
+}
+
+impl Default for DepthSettings {
+	/// Nearer fragments have smaller depths
+	fn default() -> Self {
+		Self {
+			clear_depth: 1.0,
+			compare: CompareFunction::Less,
+			bias: DepthBiasState::default(),
+		}
+	}
+}
+
+impl DepthSettings {
+	/// Nearer fragments have larger depths, which spreads float precision
+	/// evenly over the view distance
+	///
+	/// The projection has to map the near plane to 1 and the far plane to 0,
+	/// for example with `Mat4::perspective_infinite_reverse_rh` or by
+	/// swapping the near and far arguments of `Mat4::perspective_rh`.
+	pub fn reverse_z() -> Self {
+		Self {
+			clear_depth: 0.0,
+			compare: CompareFunction::GreaterEqual,
+			bias: DepthBiasState::default(),
+		}
+	}
+
+	/// Depth state for pipelines drawing scene geometry
+	pub fn depth_stencil_state(&self) -> DepthStencilState {
+		DepthStencilState {
+			format: DEPTH_FORMAT,
+			depth_write_enabled: true,
+			depth_compare: self.compare,
+			stencil: StencilState::default(),
+			bias: self.bias,
+		}
+	}
+}
+
+/// Full-screen pass resolving the multisampled depth buffer into a
+/// single-sampled one that later passes can bind, enabled with
+/// [`crate::AppSettings::resolve_depth`]
+///
+/// Depths can't be averaged like colors, since a blend of a foreground and
+/// a background sample lies on neither surface. Every pixel keeps its
+/// nearest sample instead, the minimum for the `Less` and `LessEqual`
+/// comparisons and the maximum for `Greater` and `GreaterEqual`, so edges
+/// resolve to the foreground. Passes reading the result still only see one
+/// depth per pixel, so effects that need every sample, such as
+/// anti-aliased deferred lighting, have to read the multisampled buffer
+/// themselves.
+#[derive(Clone)]
+pub struct DepthResolvePass {
+	pipeline: Arc<RenderPipeline>,
+	bind_group_layout: Arc<BindGroupLayout>,
+	view: Arc<TextureView>,
+	bind_group: Arc<BindGroup>,
+	clear_depth: f32,
+}
+
+impl DepthResolvePass {
+	pub fn new(
+		device: &Device,
+		settings: &DepthSettings,
+		source: &TextureView,
+		sample_count: u32,
+		width: u32,
+		height: u32,
+	) -> Self {
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Depth Resolve"),
+				entries: &[wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						// Depth textures can be bound as float ones, which
+						// every backend can load from
+						sample_type: wgpu::TextureSampleType::Float {
+							filterable: false,
+						},
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: true,
+					},
+					count: None,
+				}],
+			});
+
+		let nearest = match settings.compare {
+			CompareFunction::Greater | CompareFunction::GreaterEqual => "max",
+			_ => "min",
+		};
+		let shader = format!(
+			r#"
+@group(0)
+@binding(0)
+var depth: texture_multisampled_2d<f32>;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @builtin(frag_depth) f32 {{
+	let coords = vec2<i32>(vertex.position.xy);
+	var nearest = textureLoad(depth, coords, 0).r;
+	for (var i = 1; i < {sample_count}; i = i + 1) {{
+		nearest = {nearest}(nearest, textureLoad(depth, coords, i).r);
+	}}
+	return nearest;
+}}
+"#
+		);
+
+		let pipeline = fullscreen_pipeline(
+			device,
+			"Depth Resolve",
+			&shader,
+			&bind_group_layout,
+			&[],
+			Some(DepthStencilState {
+				depth_compare: CompareFunction::Always,
+				bias: DepthBiasState::default(),
+				..settings.depth_stencil_state()
+			}),
+			MultisampleState::default(),
+		);
+
+		let (view, bind_group) = create_resolve_target(
+			device,
+			&bind_group_layout,
+			source,
+			width,
+			height,
+		);
+
+		Self {
+			pipeline: Arc::new(pipeline),
+			bind_group_layout: Arc::new(bind_group_layout),
+			view: Arc::new(view),
+			bind_group: Arc::new(bind_group),
+			clear_depth: settings.clear_depth,
+		}
+	}
+
+	/// Recreate the resolved depth buffer for a new multisampled one
+	pub fn resize(
+		&mut self,
+		device: &Device,
+		source: &TextureView,
+		width: u32,
+		height: u32,
+	) {
+		let (view, bind_group) = create_resolve_target(
+			device,
+			&self.bind_group_layout,
+			source,
+			width,
+			height,
+		);
+		self.view = Arc::new(view);
+		self.bind_group = Arc::new(bind_group);
+	}
+
+	/// The resolved depth buffer, which can be bound as a `texture_depth_2d`
+	/// or an unfilterable `texture_2d<f32>`
+	pub fn view(&self) -> &TextureView {
+		&self.view
+	}
+
+	pub fn encode(&self, encoder: &mut CommandEncoder) {
+		let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+			label: Some("Depth Resolve"),
+			color_attachments: &[],
+			depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+				view: &self.view,
+				depth_ops: Some(Operations {
+					load: LoadOp::Clear(self.clear_depth),
+					store: true,
+				}),
+				stencil_ops: None,
+			}),
+		});
+		rpass.set_pipeline(&self.pipeline);
+		rpass.set_bind_group(0, &self.bind_group, &[]);
+		rpass.draw(0..3, 0..1);
+	}
+}
+
+fn create_resolve_target(
+	device: &Device,
+	bind_group_layout: &BindGroupLayout,
+	source: &TextureView,
+	width: u32,
+	height: u32,
+) -> (TextureView, BindGroup) {
+	let view = create_depth_view(device, width, height, 1);
+	let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+		label: Some("Depth Resolve"),
+		layout: bind_group_layout,
+		entries: &[wgpu::BindGroupEntry {
+			binding: 0,
+			resource: wgpu::BindingResource::TextureView(source),
+		}],
+	});
+
+	(view, bind_group)
+}
+
+/// The depth buffer can also be bound as a texture, so later passes can
+/// read it
+pub(crate) fn create_depth_view(
+	device: &Device,
+	width: u32,
+	height: u32,
+	sample_count: u32,
+) -> TextureView {
+	device
+		.create_texture(&TextureDescriptor {
+			label: Some("Depth Buffer"),
+			size: Extent3d {
+				width: width.max(1),
+				height: height.max(1),
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count,
+			dimension: TextureDimension::D2,
+			format: DEPTH_FORMAT,
+			usage: TextureUsages::RENDER_ATTACHMENT
+				| TextureUsages::TEXTURE_BINDING,
+		})
+		.create_view(&TextureViewDescriptor::default())
+}
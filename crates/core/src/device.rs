@@ -0,0 +1,60 @@
+use crate::{error::DyadikosError, mesh::MESH_COLOR_RANGE, LimitsPreset};
+use tracing::info;
+use wgpu::{
+	Adapter, Device, DeviceDescriptor, Features, Instance, PowerPreference,
+	Queue, RequestAdapterOptions, Surface,
+};
+
+/// Find an adapter, compatible with `surface` if one is given, and create
+/// the logical device and command queue on it
+///
+/// If no hardware adapter is found and `allow_software_fallback` is set,
+/// the search is retried with a software adapter.
+pub(crate) async fn request_device(
+	instance: &Instance,
+	surface: Option<&Surface>,
+	features: Features,
+	limits: LimitsPreset,
+	power_preference: PowerPreference,
+	allow_software_fallback: bool,
+) -> Result<(Adapter, Device, Queue), DyadikosError> {
+	let options = |force_fallback_adapter| RequestAdapterOptions {
+		power_preference,
+		force_fallback_adapter,
+		compatible_surface: surface,
+	};
+
+	let mut adapter = instance.request_adapter(&options(false)).await;
+	if adapter.is_none() && allow_software_fallback {
+		adapter = instance.request_adapter(&options(true)).await;
+	}
+	let adapter = adapter.ok_or(DyadikosError::NoSuitableDevice)?;
+
+	let info = adapter.get_info();
+	info!(
+		"Using {} ({:?}, {:?}), vendor {:#06x}, device {:#06x}",
+		info.name, info.device_type, info.backend, info.vendor, info.device
+	);
+
+	let mut limits = limits.limits(&adapter).using_resolution(adapter.limits());
+	if features.contains(Features::PUSH_CONSTANTS) {
+		// Room for the color meshes push, which no preset reserves
+		limits.max_push_constant_size = limits
+			.max_push_constant_size
+			.max(MESH_COLOR_RANGE.range.end);
+	}
+
+	let (device, queue) = adapter
+		.request_device(
+			&DeviceDescriptor {
+				label: None,
+				features,
+				limits,
+			},
+			None,
+		)
+		.await
+		.map_err(DyadikosError::DeviceRequest)?;
+
+	Ok((adapter, device, queue))
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use wgpu::{RequestDeviceError, ShaderStages};
+
+/// Failure returned by the app constructors
+///
+/// Implements [`std::error::Error`], so `?` still converts it into an
+/// `anyhow::Error` where matching on it is not needed.
+#[derive(Debug)]
+pub enum DyadikosError {
+	/// No adapter supports the surface and requested features
+	NoSuitableDevice,
+	/// The adapter was found but refused to create a device
+	DeviceRequest(RequestDeviceError),
+	WindowCreation(String),
+	/// The shader failed to parse, validate or match the pipeline
+	ShaderCompile {
+		/// Stage the error applies to, if it is not module-wide
+		stage: Option<ShaderStages>,
+		message: String,
+	},
+	/// The shader needs more resources than the device allows
+	LimitsExceeded(String),
+	SwapchainCreation(String),
+	/// The app settings contradict each other or the device
+	InvalidSettings(String),
+}
+
+impl DyadikosError {
+	/// Wrap an error from parsing or validating a shader module
+	pub(crate) fn shader(error: anyhow::Error) -> Self {
+		DyadikosError::ShaderCompile {
+			stage: None,
+			message: format!("{error:#}"),
+		}
+	}
+}
+
+impl fmt::Display for DyadikosError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DyadikosError::NoSuitableDevice => {
+				write!(f, "failed to find an appropriate adapter")
+			}
+			DyadikosError::DeviceRequest(error) => {
+				write!(f, "failed to create device: {error}")
+			}
+			DyadikosError::WindowCreation(message) => {
+				write!(f, "failed to create window: {message}")
+			}
+			DyadikosError::ShaderCompile {
+				stage: Some(stage),
+				message,
+			} => write!(f, "{stage:?} shader error: {message}"),
+			DyadikosError::ShaderCompile {
+				stage: None,
+				message,
+			} => write!(f, "shader error: {message}"),
+			DyadikosError::LimitsExceeded(message) => {
+				write!(f, "device limits exceeded: {message}")
+			}
+			DyadikosError::SwapchainCreation(message) => {
+				write!(f, "failed to create swapchain: {message}")
+			}
+			DyadikosError::InvalidSettings(message) => {
+				write!(f, "invalid settings: {message}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for DyadikosError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			DyadikosError::DeviceRequest(error) => Some(error),
+			_ => None,
+		}
+	}
+}
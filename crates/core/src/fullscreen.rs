@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+use wgpu::{
+	BindGroup, BindGroupLayout, Color, ColorTargetState, CommandEncoder,
+	DepthStencilState, Device, FragmentState, LoadOp, MultisampleState,
+	Operations, PipelineLayoutDescriptor, PrimitiveState,
+	RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+	RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource,
+	TextureView, VertexState,
+};
+
+/// Vertex stage drawing a single triangle that covers the whole screen,
+/// with `uv` running from 0 to 1 across it, bottom to top
+const VERTEX_SHADER: &str = r#"
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+	var result: VertexOutput;
+	result.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+	result.uv = uv;
+	return result;
+}
+"#;
+
+/// Create a pipeline running `fragment_shader` over the whole screen
+///
+/// The fragment shader must declare `fs_main`, taking a `VertexOutput`.
+/// Draw it with three vertices and no vertex buffers.
+pub(crate) fn fullscreen_pipeline(
+	device: &Device,
+	label: &str,
+	fragment_shader: &str,
+	bind_group_layout: &BindGroupLayout,
+	targets: &[Option<ColorTargetState>],
+	depth_stencil: Option<DepthStencilState>,
+	multisample: MultisampleState,
+) -> RenderPipeline {
+	let pipeline_layout =
+		device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some(label),
+			bind_group_layouts: &[bind_group_layout],
+			push_constant_ranges: &[],
+		});
+
+	let shader = device.create_shader_module(ShaderModuleDescriptor {
+		label: Some(label),
+		source: ShaderSource::Wgsl(Cow::Owned(format!(
+			"{VERTEX_SHADER}{fragment_shader}"
+		))),
+	});
+
+	device.create_render_pipeline(&RenderPipelineDescriptor {
+		label: Some(label),
+		layout: Some(&pipeline_layout),
+		vertex: VertexState {
+			module: &shader,
+			entry_point: "vs_main",
+			buffers: &[],
+		},
+		fragment: Some(FragmentState {
+			module: &shader,
+			entry_point: "fs_main",
+			targets,
+		}),
+		primitive: PrimitiveState::default(),
+		depth_stencil,
+		multisample,
+		multiview: None,
+	})
+}
+
+/// Record a render pass drawing a full-screen pipeline into `view`
+pub(crate) fn draw_fullscreen(
+	encoder: &mut CommandEncoder,
+	view: &TextureView,
+	pipeline: &RenderPipeline,
+	bind_group: &BindGroup,
+	load: LoadOp<Color>,
+) {
+	let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+		label: None,
+		color_attachments: &[Some(RenderPassColorAttachment {
+			view,
+			resolve_target: None,
+			ops: Operations { load, store: true },
+		})],
+		depth_stencil_attachment: None,
+	});
+	rpass.set_pipeline(pipeline);
+	rpass.set_bind_group(0, bind_group, &[]);
+	rpass.draw(0..3, 0..1);
+}
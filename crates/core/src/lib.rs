@@ -1,36 +1,316 @@
-use dyadikos_math::Matrix4;
-use std::{ops::Range, sync::Arc};
+use anyhow::{bail, Context, Result};
+use background::Background;
+use bloom::BloomSettings;
+use bytemuck::{Pod, Zeroable};
+use depth::DepthSettings;
+use dyadikos_math::{camera::Camera, Matrix4};
+use std::{ops::Range, path::PathBuf, sync::Arc};
 use typed_arena::Arena;
 use wgpu::{
-	BindGroup, Buffer, Color, Device, DynamicOffset, Features, IndexFormat,
-	PrimitiveState, RenderPass, RenderPipeline,
+	Adapter, BindGroup, BindGroupLayout, BlendComponent, BlendFactor,
+	BlendOperation, BlendState, Buffer, Color, ColorTargetState, ColorWrites,
+	Device, DynamicOffset, Features, IndexFormat, Limits, MultisampleState,
+	PowerPreference, PresentMode, PrimitiveState, Queue, RenderBundle,
+	RenderPass, RenderPipeline, TextureFormat, VertexBufferLayout,
 };
 
-pub type RenderCallback = dyn FnMut(ArcRenderPass, &mut Buffer);
+/// Records the frame's draws, called once per frame
+///
+/// Errors are logged, skipping the rest of that frame's callback, unless
+/// [`AppSettings::exit_on_error`] is set to stop the app instead.
+pub type RenderCallback = dyn FnMut(&mut ArcRenderPass, &dyn App) -> Result<()>;
+
+/// Bind group holding the per-frame [`FrameGlobals`] uniform at binding 0
+pub const FRAME_GLOBALS_GROUP: u32 = 1;
+
+/// Bind group holding a sampler for each texture the scene shader declares
+/// in group 0, at the same binding as the texture
+pub const SAMPLER_GROUP: u32 = 2;
+
+/// Built-in uniforms updated by the app before every frame
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct FrameGlobals {
+	/// Seconds elapsed since the app started rendering
+	pub time: f32,
+	_padding: f32,
+	/// Size of the render surface in pixels
+	pub resolution: [f32; 2],
+}
+
+impl FrameGlobals {
+	pub fn new(time: f32, resolution: [f32; 2]) -> Self {
+		Self {
+			time,
+			_padding: 0.0,
+			resolution,
+		}
+	}
+}
+
+/// Device limits to request when creating the app
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LimitsPreset {
+	/// Limits every WebGL2 implementation can provide
+	#[default]
+	DownlevelWebGl2,
+	/// Limits of GPUs that can't run all of WebGPU
+	Downlevel,
+	/// The WebGPU defaults
+	Default,
+	/// Everything the adapter supports
+	Adapter,
+}
+
+impl LimitsPreset {
+	pub fn limits(self, adapter: &Adapter) -> Limits {
+		match self {
+			LimitsPreset::DownlevelWebGl2 => {
+				Limits::downlevel_webgl2_defaults()
+			}
+			LimitsPreset::Downlevel => Limits::downlevel_defaults(),
+			LimitsPreset::Default => Limits::default(),
+			LimitsPreset::Adapter => adapter.limits(),
+		}
+	}
+}
+
+/// How fragment colors are combined with what is already in the target
+///
+/// Blending depends on draw order, so with [`BlendMode::AlphaBlend`]
+/// transparent meshes have to be drawn after opaque ones, sorted from back
+/// to front.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+	/// Replace the target color
+	#[default]
+	Opaque,
+	/// Blend by the fragment's alpha
+	AlphaBlend,
+	/// Add the fragment color, scaled by its alpha, to the target
+	Additive,
+}
+
+impl BlendMode {
+	pub fn blend_state(self) -> Option<BlendState> {
+		match self {
+			BlendMode::Opaque => None,
+			BlendMode::AlphaBlend => Some(BlendState::ALPHA_BLENDING),
+			BlendMode::Additive => Some(BlendState {
+				color: BlendComponent {
+					src_factor: BlendFactor::SrcAlpha,
+					dst_factor: BlendFactor::One,
+					operation: BlendOperation::Add,
+				},
+				alpha: BlendComponent::OVER,
+			}),
+		}
+	}
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct AppSettings {
 	pub primitive_state: PrimitiveState,
 	pub shader: String,
+	/// Vertex buffers the pipeline reads, checked against the shader's
+	/// inputs
+	///
+	/// Defaults to the single [`mesh::vertex_buffer_layout`] buffer of
+	/// [`dyadikos_math::Vertex`], which [`mesh::Mesh`] uploads.
+	pub vertex_layouts: Option<Vec<VertexBufferLayout<'static>>>,
 	pub features: Features,
-	pub background_color: Color,
+	/// Image shown in the title bar and taskbar, as a PNG or ICO file
+	pub window_icon: Option<PathBuf>,
+	/// Hide the cursor while it is over the window
+	pub hide_cursor: bool,
+	/// Confine the cursor to the window while it has focus, as FPS-style
+	/// camera controls need
+	pub grab_cursor: bool,
+	pub background: Background,
+	/// Swapchain format to use instead of the preferred sRGB one
+	///
+	/// With an sRGB swapchain, shaders should output linear color and the
+	/// hardware encodes it on write.
+	pub surface_format: Option<TextureFormat>,
+	/// Present mode to use instead of [`PresentMode::Mailbox`], or
+	/// [`PresentMode::Fifo`] where the surface has no mailbox mode
+	///
+	/// Has to be one of [`native::SurfaceInfo::present_modes`].
+	pub present_mode: Option<PresentMode>,
+	pub power_preference: PowerPreference,
+	/// Retry with a software adapter, such as lavapipe or WARP, when no
+	/// hardware adapter is available
+	pub allow_software_fallback: bool,
+	pub limits: LimitsPreset,
+	pub blend: BlendMode,
+	/// Render into an HDR target that is tonemapped onto the swapchain
+	///
+	/// Scene pipelines then target [`tonemap::HDR_FORMAT`] rather than the
+	/// swapchain format.
+	pub hdr: bool,
+	/// Only applied when [`AppSettings::hdr`] is set
+	pub bloom: Option<BloomSettings>,
+	/// Formats of further color targets the scene pass renders into, at
+	/// fragment shader locations 1 and up, such as the G-buffer of a deferred
+	/// renderer
+	///
+	/// The shader has to write every location, while the built-in pipelines
+	/// only write location 0. The targets are cleared to transparent black
+	/// every frame and can be read back through
+	/// [`native::NativeApp::extra_targets`].
+	pub extra_targets: Vec<TextureFormat>,
+	/// Samples per pixel for multisample anti-aliasing of the scene pass,
+	/// 1 or 4, resolved into the swapchain or HDR target
+	///
+	/// Can't be combined with [`AppSettings::extra_targets`].
+	pub msaa_samples: Option<u32>,
+	/// Give the scene pass a depth buffer, which every scene pipeline then
+	/// has to declare with [`DepthSettings::depth_stencil_state`]
+	pub depth: Option<DepthSettings>,
+	/// Resolve the multisampled depth buffer into
+	/// [`native::NativeApp::depth_resolve_pass`] after the scene pass, for
+	/// later passes that read depth
+	///
+	/// Needs both [`AppSettings::depth`] and [`AppSettings::msaa_samples`].
+	/// See [`depth::DepthResolvePass`] for how samples are combined.
+	pub resolve_depth: bool,
+	/// Stop the event loop when the render callback returns an error,
+	/// instead of logging it and rendering the next frame
+	pub exit_on_error: bool,
+	/// Save the next frame to `screenshot-<unix millis>.png` in the working
+	/// directory whenever this key is pressed
+	///
+	/// Only 8-bit RGBA and BGRA swapchain formats can be captured.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub screenshot_key: Option<winit::event::VirtualKeyCode>,
+	/// Camera to fly around, whose view-projection replaces the matrix
+	/// passed to [`App::run`] every frame
+	#[cfg(not(target_arch = "wasm32"))]
+	pub camera: Option<camera::CameraController>,
+}
+
+impl AppSettings {
+	/// Multisample state every scene pipeline has to use
+	pub fn multisample_state(&self) -> MultisampleState {
+		MultisampleState {
+			count: self.msaa_samples.unwrap_or(1),
+			..Default::default()
+		}
+	}
+
+	/// Set the background to an 8-bit sRGB color, converted to the linear
+	/// color space the clear operation expects
+	pub fn with_background_rgb8(mut self, r: u8, g: u8, b: u8) -> Self {
+		self.background = Background::SolidColor(Color {
+			r: srgb_to_linear(r),
+			g: srgb_to_linear(g),
+			b: srgb_to_linear(b),
+			a: 1.0,
+		});
+
+		self
+	}
+
+	/// Set the background from a `#RRGGBB` sRGB hex string
+	pub fn with_background_hex(self, hex: &str) -> Result<Self> {
+		let digits = hex.strip_prefix('#').unwrap_or(hex);
+		if digits.len() != 6 || !digits.is_ascii() {
+			bail!("Expected a color in the form #RRGGBB, got {hex:?}");
+		}
+
+		let channel = |i: usize| {
+			u8::from_str_radix(&digits[i..i + 2], 16)
+				.with_context(|| format!("Invalid hex color {hex:?}"))
+		};
+
+		Ok(self.with_background_rgb8(channel(0)?, channel(2)?, channel(4)?))
+	}
+}
+
+/// Color targets of a pipeline drawn in the scene pass that writes `format`
+/// at location 0 and leaves `extra_targets` untouched
+pub(crate) fn scene_color_targets(
+	format: TextureFormat,
+	blend: Option<BlendState>,
+	extra_targets: &[TextureFormat],
+) -> Vec<Option<ColorTargetState>> {
+	std::iter::once(ColorTargetState {
+		format,
+		blend,
+		write_mask: ColorWrites::ALL,
+	})
+	.chain(extra_targets.iter().map(|&format| ColorTargetState {
+		format,
+		blend: None,
+		write_mask: ColorWrites::empty(),
+	}))
+	.map(Some)
+	.collect()
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+	let value = value as f64 / 255.0;
+
+	if value <= 0.04045 {
+		value / 12.92
+	} else {
+		((value + 0.055) / 1.055).powf(2.4)
+	}
 }
 
 pub trait App {
 	fn get_window_size(&self) -> (u32, u32);
 	fn get_settings(&self) -> &AppSettings;
 	fn get_device(&self) -> &Device;
+	fn get_queue(&self) -> &Queue;
 	fn get_pipeline(&self) -> &RenderPipeline;
 	fn get_bind_group(&self) -> &BindGroup;
-	fn run(self, matrix: &Matrix4, callback: Box<RenderCallback>);
+	fn get_bind_group_layout(&self) -> &BindGroupLayout;
+	/// Bind group for [`FRAME_GLOBALS_GROUP`]
+	fn get_globals_bind_group(&self) -> &BindGroup;
+	/// Current state of the app's camera, if it has one
+	fn get_camera(&self) -> Option<&Camera> {
+		None
+	}
+	/// Buffer the matrix passed to [`App::run`] is uploaded to
+	fn get_uniform_buffer(&self) -> &Buffer;
+	/// Buffer backing the uniform the shader declares at `binding` in group
+	/// 0, where binding 0 is [`App::get_uniform_buffer`]
+	fn get_uniform_buffer_at(&self, binding: u32) -> Option<&Buffer>;
+	/// Bind group for [`SAMPLER_GROUP`], if the shader samples textures
+	fn get_sampler_bind_group(&self) -> Option<&BindGroup> {
+		None
+	}
+	/// Format of the color target scene pipelines render into
+	fn get_surface_format(&self) -> TextureFormat;
+	fn run(self, matrix: &Matrix4, callback: Box<RenderCallback>)
+	where
+		Self: Sized;
 }
 
 pub struct ArcRenderPass<'a> {
 	arena: &'a Arena<Arc<Buffer>>,
+	bind_groups: &'a Arena<Arc<BindGroup>>,
+	bundles: &'a Arena<Arc<RenderBundle>>,
+	pipelines: &'a Arena<Arc<RenderPipeline>>,
 	render_pass: RenderPass<'a>,
 }
 
 impl<'a> ArcRenderPass<'a> {
+	/// Access the wrapped render pass directly
+	///
+	/// This is an escape hatch for wgpu features that aren't wrapped yet.
+	/// Resources passed to it must outlive the pass, which the arena-backed
+	/// methods otherwise take care of.
+	pub fn inner(&mut self) -> &mut RenderPass<'a> {
+		&mut self.render_pass
+	}
+
+	pub fn set_pipeline(&mut self, pipeline: Arc<RenderPipeline>) {
+		let pipeline = self.pipelines.alloc(pipeline);
+		self.render_pass.set_pipeline(pipeline);
+	}
+
 	pub fn set_vertex_buffer(&mut self, slot: u32, buffer: Arc<Buffer>) {
 		let buffer = self.arena.alloc(buffer);
 		self.render_pass.set_vertex_buffer(slot, buffer.slice(..));
@@ -45,6 +325,10 @@ impl<'a> ArcRenderPass<'a> {
 		self.render_pass.set_index_buffer(buffer.slice(..), format);
 	}
 
+	pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+		self.render_pass.draw(vertices, instances)
+	}
+
 	pub fn draw_indexed(
 		&mut self,
 		indices: Range<u32>,
@@ -55,16 +339,91 @@ impl<'a> ArcRenderPass<'a> {
 			.draw_indexed(indices, base_vertex, instances)
 	}
 
+	/// Map clip space onto a sub-rectangle of the target, in pixels, so
+	/// several cameras can share one frame
+	pub fn set_viewport(
+		&mut self,
+		x: f32,
+		y: f32,
+		width: f32,
+		height: f32,
+		min_depth: f32,
+		max_depth: f32,
+	) {
+		self.render_pass
+			.set_viewport(x, y, width, height, min_depth, max_depth)
+	}
+
+	/// Discard fragments outside a sub-rectangle of the target, in pixels
+	pub fn set_scissor_rect(
+		&mut self,
+		x: u32,
+		y: u32,
+		width: u32,
+		height: u32,
+	) {
+		self.render_pass.set_scissor_rect(x, y, width, height)
+	}
+
+	/// Replay a prerecorded bundle
+	///
+	/// The pipeline and bind groups are unset afterwards, so they need to be
+	/// set again before drawing anything else.
+	pub fn execute_bundle(&mut self, bundle: Arc<RenderBundle>) {
+		let bundle = self.bundles.alloc(bundle);
+		self.render_pass.execute_bundles(std::iter::once(&**bundle));
+	}
+
 	pub fn set_bind_group(
 		&mut self,
 		slot: u32,
-		bind_group: &'a Arc<BindGroup>,
+		bind_group: Arc<BindGroup>,
 		offsets: &[DynamicOffset],
 	) {
+		let bind_group = self.bind_groups.alloc(bind_group);
 		self.render_pass.set_bind_group(slot, bind_group, offsets);
 	}
 }
+pub mod background;
+pub mod bloom;
+pub mod buffer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod camera;
+pub mod compute;
+pub mod debug_draw;
+pub mod depth;
+mod device;
+pub mod error;
+mod fullscreen;
+pub mod material;
 pub mod mesh;
+pub mod picking;
+pub mod prelude;
+pub mod profiler;
+pub mod reflect;
+#[cfg(not(target_arch = "wasm32"))]
+mod screenshot;
+pub mod texture;
+pub mod tonemap;
 
-#[cfg(not(target_arch = "wasm"))]
+#[cfg(not(target_arch = "wasm32"))]
 pub mod native;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn background_hex_is_linear() {
+		let settings = AppSettings::default()
+			.with_background_hex("#FF8000")
+			.unwrap();
+		let Background::SolidColor(color) = settings.background else {
+			panic!("Expected a solid color, got {:?}", settings.background);
+		};
+
+		// 0x80 is 0.502 in sRGB, which decodes to 0.216 linear
+		assert_eq!((color.r, color.b, color.a), (1.0, 0.0, 1.0));
+		assert!((color.g - 0.21586).abs() < 1e-5, "{}", color.g);
+	}
+}
@@ -0,0 +1,250 @@
+use crate::{
+	mesh::{push_constant_ranges, vertex_buffer_layout},
+	scene_color_targets, App, ArcRenderPass,
+};
+use bytemuck::{Pod, Zeroable};
+use dyadikos_math::{transform::RenderTransformation, Matrix4, Vector3};
+use glam::{Mat4, Vec3};
+use std::{borrow::Cow, sync::Arc};
+use wgpu::{
+	util::DeviceExt, BindGroup, Buffer, FragmentState,
+	PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPipeline,
+	RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource,
+	VertexState,
+};
+
+const LIT_SHADER: &str = r#"
+struct Lit {
+	model: mat4x4<f32>,
+	normal_matrix: mat4x4<f32>,
+	camera_position: vec4<f32>,
+	light_direction: vec4<f32>,
+	light_color: vec4<f32>,
+	diffuse: vec4<f32>,
+	// The shininess exponent is stored in w
+	specular: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> transform: mat4x4<f32>;
+
+@group(0)
+@binding(1)
+var<uniform> lit: Lit;
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) world_position: vec3<f32>,
+	@location(1) normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+	@location(0) position: vec3<f32>,
+	@location(2) normal: vec3<f32>,
+) -> VertexOutput {
+	let world_position = lit.model * vec4<f32>(position, 1.0);
+
+	var result: VertexOutput;
+	result.position = transform * world_position;
+	result.world_position = world_position.xyz;
+	result.normal = (lit.normal_matrix * vec4<f32>(normal, 0.0)).xyz;
+	return result;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	let normal = normalize(vertex.normal);
+	let to_light = normalize(-lit.light_direction.xyz);
+	let to_camera = normalize(lit.camera_position.xyz - vertex.world_position);
+	let halfway = normalize(to_light + to_camera);
+
+	let ambient = 0.05 * lit.diffuse.rgb;
+	let diffuse = max(dot(normal, to_light), 0.0) * lit.diffuse.rgb;
+	let specular = pow(max(dot(normal, halfway), 0.0), lit.specular.w)
+		* lit.specular.rgb;
+
+	return vec4<f32>((ambient + diffuse + specular) * lit.light_color.rgb, 1.0);
+}
+"#;
+
+/// Parameters of the directional-light Blinn-Phong material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LitParams {
+	/// Places the mesh in the world, while the app's uniform matrix is used
+	/// as the view-projection
+	pub model: Matrix4,
+	pub camera_position: Vector3,
+	/// Direction the light travels in
+	pub light_direction: Vector3,
+	pub light_color: Vector3,
+	pub diffuse: Vector3,
+	pub specular: Vector3,
+	pub shininess: f32,
+}
+
+impl Default for LitParams {
+	fn default() -> Self {
+		Self {
+			model: dyadikos_math::identity(),
+			camera_position: [0.0, 0.0, 1.0],
+			light_direction: [-0.5, -1.0, -0.5],
+			light_color: [1.0; 3],
+			diffuse: [0.8; 3],
+			specular: [0.5; 3],
+			shininess: 32.0,
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LitUniform {
+	model: Matrix4,
+	normal_matrix: Matrix4,
+	camera_position: [f32; 4],
+	light_direction: [f32; 4],
+	light_color: [f32; 4],
+	diffuse: [f32; 4],
+	specular: [f32; 4],
+}
+
+impl From<&LitParams> for LitUniform {
+	fn from(params: &LitParams) -> Self {
+		let transform = RenderTransformation {
+			model: Mat4::from_cols_array(&params.model),
+			..RenderTransformation::default()
+		};
+		let extend = |v: Vector3, w: f32| Vec3::from(v).extend(w).to_array();
+
+		Self {
+			model: params.model,
+			normal_matrix: Mat4::from_mat3(transform.normal_matrix())
+				.to_cols_array(),
+			camera_position: extend(params.camera_position, 1.0),
+			light_direction: extend(params.light_direction, 0.0),
+			light_color: extend(params.light_color, 1.0),
+			diffuse: extend(params.diffuse, 1.0),
+			specular: extend(params.specular, params.shininess),
+		}
+	}
+}
+
+/// A ready-to-use pipeline and bind group for shading meshes
+pub struct Material {
+	pipeline: Arc<RenderPipeline>,
+	bind_group: Arc<BindGroup>,
+	buffer: Buffer,
+}
+
+impl Material {
+	/// Blinn-Phong shading lit by a single directional light
+	pub fn lit(app: &impl App, params: &LitParams) -> Self {
+		let device = app.get_device();
+
+		let buffer =
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Lit Material"),
+				contents: bytemuck::bytes_of(&LitUniform::from(params)),
+				usage: wgpu::BufferUsages::UNIFORM
+					| wgpu::BufferUsages::COPY_DST,
+			});
+
+		let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Uniform,
+				has_dynamic_offset: false,
+				min_binding_size: None,
+			},
+			count: None,
+		};
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Lit Material"),
+				entries: &[
+					uniform_entry(0, wgpu::ShaderStages::VERTEX),
+					uniform_entry(1, wgpu::ShaderStages::VERTEX_FRAGMENT),
+				],
+			});
+
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Lit Material"),
+			layout: &bind_group_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: app.get_uniform_buffer().as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: buffer.as_entire_binding(),
+				},
+			],
+		});
+
+		let pipeline_layout =
+			device.create_pipeline_layout(&PipelineLayoutDescriptor {
+				label: Some("Lit Material"),
+				bind_group_layouts: &[&bind_group_layout],
+				push_constant_ranges: push_constant_ranges(device.features()),
+			});
+
+		let shader = device.create_shader_module(ShaderModuleDescriptor {
+			label: Some("Lit Material"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(LIT_SHADER)),
+		});
+
+		let pipeline =
+			device.create_render_pipeline(&RenderPipelineDescriptor {
+				label: Some("Lit Material"),
+				layout: Some(&pipeline_layout),
+				vertex: VertexState {
+					module: &shader,
+					entry_point: "vs_main",
+					buffers: &[vertex_buffer_layout()],
+				},
+				fragment: Some(FragmentState {
+					module: &shader,
+					entry_point: "fs_main",
+					targets: &scene_color_targets(
+						app.get_surface_format(),
+						app.get_settings().blend.blend_state(),
+						&app.get_settings().extra_targets,
+					),
+				}),
+				primitive: PrimitiveState::default(),
+				depth_stencil: app
+					.get_settings()
+					.depth
+					.map(|depth| depth.depth_stencil_state()),
+				multisample: app.get_settings().multisample_state(),
+				multiview: None,
+			});
+
+		Self {
+			pipeline: Arc::new(pipeline),
+			bind_group: Arc::new(bind_group),
+			buffer,
+		}
+	}
+
+	/// Upload new parameters, taking effect from the next submitted frame
+	pub fn update(&self, queue: &Queue, params: &LitParams) {
+		queue.write_buffer(
+			&self.buffer,
+			0,
+			bytemuck::bytes_of(&LitUniform::from(params)),
+		);
+	}
+
+	/// Use the material for the following draws
+	///
+	/// This replaces the app's pipeline and group 0 bind group.
+	pub fn bind(&self, rpass: &mut ArcRenderPass) {
+		rpass.set_pipeline(self.pipeline.clone());
+		rpass.set_bind_group(0, self.bind_group.clone(), &[]);
+	}
+}
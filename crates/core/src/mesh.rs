@@ -1,13 +1,65 @@
-use crate::{App, ArcRenderPass};
-use dyadikos_math::Vertex;
+use crate::{
+	depth::DEPTH_FORMAT, App, ArcRenderPass, FRAME_GLOBALS_GROUP, SAMPLER_GROUP,
+};
+use dyadikos_math::{bounds::Aabb, Matrix4, Vector3, Vertex};
+use glam::{Mat4, Vec2, Vec3};
 use std::sync::Arc;
 use wgpu::util::DeviceExt;
-use wgpu::Buffer;
+use wgpu::{
+	Buffer, BufferUsages, Features, IndexFormat, PushConstantRange,
+	RenderBundle, RenderBundleDepthStencil, RenderBundleDescriptor,
+	RenderBundleEncoderDescriptor, RenderPass, ShaderStages, VertexAttribute,
+	VertexBufferLayout,
+};
+
+/// Attributes of [`Vertex`]: position, color, normal, uv and tangent at
+/// shader locations 0 to 4
+pub const VERTEX_ATTRIBUTES: [VertexAttribute; 5] = wgpu::vertex_attr_array![
+	0 => Float32x3,
+	1 => Float32x4,
+	2 => Float32x3,
+	3 => Float32x2,
+	4 => Float32x4,
+];
+
+pub fn vertex_buffer_layout() -> VertexBufferLayout<'static> {
+	VertexBufferLayout {
+		array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+		step_mode: wgpu::VertexStepMode::Vertex,
+		attributes: &VERTEX_ATTRIBUTES,
+	}
+}
+
+/// Push constant range [`Mesh::set_color`] writes to, which fragment
+/// shaders read by declaring `var<push_constant> mesh_color: vec4<f32>;`
+pub const MESH_COLOR_RANGE: PushConstantRange = PushConstantRange {
+	stages: ShaderStages::FRAGMENT,
+	range: 0..16,
+};
+
+/// Push constant ranges of pipelines that meshes are drawn with, which are
+/// only available with [`Features::PUSH_CONSTANTS`]
+pub(crate) fn push_constant_ranges(
+	features: Features,
+) -> &'static [PushConstantRange] {
+	if features.contains(Features::PUSH_CONSTANTS) {
+		&[MESH_COLOR_RANGE]
+	} else {
+		&[]
+	}
+}
 
 pub struct Mesh {
 	vertex_buffer: Arc<Buffer>,
 	index_buffer: Arc<Buffer>,
+	/// Sizes of the GPU buffers in bytes, which edits are written into in
+	/// place as long as they fit
+	vertex_capacity: u64,
+	index_capacity: u64,
+	index_format: IndexFormat,
+	color: Option<[f32; 4]>,
 	pub vertex_data: Vec<Vertex>,
+	/// Indices are kept as u32 on the CPU even when uploaded as u16
 	pub index_data: Vec<u32>,
 }
 
@@ -16,36 +68,417 @@ impl Mesh {
 		app: &impl App,
 		vertex_data: Vec<Vertex>,
 		index_data: Vec<u32>,
+	) -> Self {
+		Self::with_index_format(
+			app,
+			vertex_data,
+			index_data,
+			IndexFormat::Uint32,
+		)
+	}
+
+	/// Create a mesh with 16-bit indices, halving the index buffer size for
+	/// meshes with fewer than 65536 vertices
+	pub fn new_u16(
+		app: &impl App,
+		vertex_data: Vec<Vertex>,
+		index_data: Vec<u16>,
+	) -> Self {
+		Self::with_index_format(
+			app,
+			vertex_data,
+			index_data.into_iter().map(u32::from).collect(),
+			IndexFormat::Uint16,
+		)
+	}
+
+	/// Create a mesh of camera-facing quads, one per line segment, since
+	/// wgpu can only rasterize lines one pixel wide
+	///
+	/// `width` is in world units. The quads are turned toward the camera
+	/// described by `view`, so the mesh has to be rebuilt when it moves.
+	pub fn thick_lines(
+		app: &impl App,
+		segments: &[[Vector3; 2]],
+		width: f32,
+		view: &Matrix4,
+	) -> Self {
+		let (vertex_data, index_data) =
+			thick_line_geometry(segments, width, view);
+
+		Self::new(app, vertex_data, index_data)
+	}
+
+	/// Combine several meshes into one, so static geometry can be drawn
+	/// with a single buffer pair
+	///
+	/// The result keeps 16-bit indices only if every source mesh uses them
+	/// and the merged vertices still fit.
+	pub fn merge(app: &impl App, meshes: &[Mesh]) -> Self {
+		let (vertex_data, index_data) = merge_geometry(
+			meshes
+				.iter()
+				.map(|mesh| (&mesh.vertex_data[..], &mesh.index_data[..])),
+		);
+
+		let index_format = if vertex_data.len() <= u16::MAX as usize + 1
+			&& meshes
+				.iter()
+				.all(|mesh| mesh.index_format == IndexFormat::Uint16)
+		{
+			IndexFormat::Uint16
+		} else {
+			IndexFormat::Uint32
+		};
+
+		Self::with_index_format(app, vertex_data, index_data, index_format)
+	}
+
+	/// Record the draw calls for a set of static meshes once, to be replayed
+	/// every frame with [`ArcRenderPass::execute_bundle`]
+	///
+	/// The bundle keeps drawing the geometry and app bind groups it was
+	/// baked with, so it has to be baked again whenever the meshes or the
+	/// app's textures change.
+	pub fn bake_bundle(app: &impl App, meshes: &[Mesh]) -> RenderBundle {
+		let mut encoder = app.get_device().create_render_bundle_encoder(
+			&RenderBundleEncoderDescriptor {
+				label: Some("Bundle"),
+				color_formats: &std::iter::once(app.get_surface_format())
+					.chain(app.get_settings().extra_targets.iter().copied())
+					.map(Some)
+					.collect::<Vec<_>>(),
+				depth_stencil: app.get_settings().depth.map(|_| {
+					RenderBundleDepthStencil {
+						format: DEPTH_FORMAT,
+						depth_read_only: false,
+						stencil_read_only: true,
+					}
+				}),
+				sample_count: app.get_settings().multisample_state().count,
+				multiview: None,
+			},
+		);
+		encoder.set_pipeline(app.get_pipeline());
+		encoder.set_bind_group(0, app.get_bind_group(), &[]);
+		encoder.set_bind_group(
+			FRAME_GLOBALS_GROUP,
+			app.get_globals_bind_group(),
+			&[],
+		);
+		if let Some(sampler_bind_group) = app.get_sampler_bind_group() {
+			encoder.set_bind_group(SAMPLER_GROUP, sampler_bind_group, &[]);
+		}
+
+		for mesh in meshes {
+			if let Some(color) = &mesh.color {
+				encoder.set_push_constants(
+					MESH_COLOR_RANGE.stages,
+					MESH_COLOR_RANGE.range.start,
+					bytemuck::bytes_of(color),
+				);
+			}
+			encoder.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+			encoder.set_index_buffer(
+				mesh.index_buffer.slice(..),
+				mesh.index_format,
+			);
+			encoder.draw_indexed(0..mesh.index_data.len() as u32, 0, 0..1);
+		}
+
+		encoder.finish(&RenderBundleDescriptor {
+			label: Some("Bundle"),
+		})
+	}
+
+	fn with_index_format(
+		app: &impl App,
+		vertex_data: Vec<Vertex>,
+		index_data: Vec<u32>,
+		index_format: IndexFormat,
 	) -> Self {
 		let device = app.get_device();
+		let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertex_data);
 		let vertex_buffer =
 			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 				label: Some("Vertex Buffer"),
-				contents: bytemuck::cast_slice(&vertex_data),
-				usage: wgpu::BufferUsages::VERTEX,
+				contents: vertex_bytes,
+				usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
 			});
 
+		let index_bytes = index_bytes(&index_data, index_format);
 		let index_buffer =
 			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 				label: Some("Index Buffer"),
-				contents: bytemuck::cast_slice(&index_data),
-				usage: wgpu::BufferUsages::INDEX,
+				contents: &index_bytes,
+				usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
 			});
 
 		Mesh {
+			vertex_capacity: vertex_bytes.len() as u64,
+			index_capacity: index_bytes.len() as u64,
 			vertex_data,
 			index_data,
+			index_format,
+			color: None,
 			vertex_buffer: Arc::new(vertex_buffer),
 			index_buffer: Arc::new(index_buffer),
 		}
 	}
 
-	pub fn render(&mut self, mut rpass: ArcRenderPass) {
-		rpass.set_vertex_buffer(0, self.vertex_buffer.clone());
-		rpass.set_index_buffer(
-			wgpu::IndexFormat::Uint32,
-			self.index_buffer.clone(),
+	/// Upload [`Mesh::vertex_data`] and [`Mesh::index_data`] after editing
+	/// them, taking effect from the next submitted frame
+	///
+	/// Edits that fit in the current buffers are written in place, so
+	/// bundles baked with the mesh draw the new geometry too, as long as the
+	/// index count stays the same. Bundles record the index count and
+	/// format, so they have to be baked again after any change to the
+	/// number of indices, and after a mesh grew and got new buffers. 16-bit
+	/// indices are widened to 32 bits once an index no longer fits, which
+	/// always allocates a new index buffer.
+	///
+	/// Every call copies the whole mesh through the queue's staging memory.
+	/// That's fine for editing tools and occasional deformation, but meshes
+	/// rewritten every frame are better off deformed in a vertex or compute
+	/// shader than re-uploaded from the CPU.
+	pub fn apply_vertex_edits(&mut self, app: &impl App) {
+		if self.index_format == IndexFormat::Uint16
+			&& self.index_data.iter().any(|&i| i > u16::MAX as u32)
+		{
+			self.index_format = IndexFormat::Uint32;
+			// Never write the wider indices into a buffer that bundles still
+			// read as 16-bit
+			self.index_capacity = 0;
+		}
+
+		self.upload_vertices(app);
+		upload(
+			app,
+			&mut self.index_buffer,
+			&mut self.index_capacity,
+			&index_bytes(&self.index_data, self.index_format),
+			"Index Buffer",
+			BufferUsages::INDEX,
+		);
+	}
+
+	fn upload_vertices(&mut self, app: &impl App) {
+		upload(
+			app,
+			&mut self.vertex_buffer,
+			&mut self.vertex_capacity,
+			bytemuck::cast_slice(&self.vertex_data),
+			"Vertex Buffer",
+			BufferUsages::VERTEX,
 		);
+	}
+
+	/// Box around every vertex, or [`Aabb::EMPTY`] for meshes without any
+	pub fn bounds(&self) -> Aabb {
+		Aabb::from_points(self.vertex_data.iter().map(|vertex| vertex.position))
+			.unwrap_or(Aabb::EMPTY)
+	}
+
+	pub fn index_format(&self) -> IndexFormat {
+		self.index_format
+	}
+
+	/// Tint the mesh with a linear RGBA color, pushed to
+	/// [`MESH_COLOR_RANGE`] every time it is drawn
+	///
+	/// This needs [`Features::PUSH_CONSTANTS`] in
+	/// [`crate::AppSettings::features`]. Meshes without a color don't push
+	/// anything, so they can be drawn with any pipeline.
+	pub fn set_color(&mut self, color: [f32; 4]) {
+		self.color = Some(color);
+	}
+
+	pub fn color(&self) -> Option<[f32; 4]> {
+		self.color
+	}
+
+	/// Derive per-vertex tangents from the positions, normals and UVs of
+	/// the triangles, and upload them for normal mapping
+	///
+	/// Triangles with degenerate UVs don't contribute, and vertices only
+	/// touched by such triangles keep their previous tangent.
+	pub fn compute_tangents(&mut self, app: &impl App) {
+		compute_tangents(&mut self.vertex_data, &self.index_data);
+		self.upload_vertices(app);
+	}
+
+	/// Record the draw into a pass that keeps the mesh borrowed
+	pub(crate) fn draw<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+		rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+		rpass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
 		rpass.draw_indexed(0..self.index_data.len() as u32, 0, 0..1);
 	}
+
+	pub fn render(&mut self, rpass: &mut ArcRenderPass) {
+		if let Some(color) = &self.color {
+			rpass.inner().set_push_constants(
+				MESH_COLOR_RANGE.stages,
+				MESH_COLOR_RANGE.range.start,
+				bytemuck::bytes_of(color),
+			);
+		}
+		rpass.set_vertex_buffer(0, self.vertex_buffer.clone());
+		rpass.set_index_buffer(self.index_format, self.index_buffer.clone());
+		rpass.draw_indexed(0..self.index_data.len() as u32, 0, 0..1);
+	}
+}
+
+/// Write `contents` into `buffer` if it fits, otherwise replace the buffer
+/// with a new one holding them
+fn upload(
+	app: &impl App,
+	buffer: &mut Arc<Buffer>,
+	capacity: &mut u64,
+	contents: &[u8],
+	label: &str,
+	usage: BufferUsages,
+) {
+	if contents.len() as u64 <= *capacity {
+		if !contents.is_empty() {
+			app.get_queue().write_buffer(buffer, 0, contents);
+		}
+		return;
+	}
+
+	*buffer = Arc::new(app.get_device().create_buffer_init(
+		&wgpu::util::BufferInitDescriptor {
+			label: Some(label),
+			contents,
+			usage: usage | BufferUsages::COPY_DST,
+		},
+	));
+	*capacity = contents.len() as u64;
+}
+
+/// Indices are padded to a multiple of 4 bytes, so they can be written with
+/// [`wgpu::Queue::write_buffer`]
+fn index_bytes(index_data: &[u32], format: IndexFormat) -> Vec<u8> {
+	match format {
+		IndexFormat::Uint16 => {
+			let mut indices: Vec<u16> =
+				index_data.iter().map(|&i| i as u16).collect();
+			if indices.len() % 2 == 1 {
+				indices.push(0);
+			}
+
+			bytemuck::cast_slice(&indices).to_vec()
+		}
+		IndexFormat::Uint32 => bytemuck::cast_slice(index_data).to_vec(),
+	}
+}
+
+/// Lengyel's method: accumulate the UV-space directions of every triangle
+/// onto its vertices, then orthogonalize them against the normals
+fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+	let mut tangents = vec![Vec3::ZERO; vertices.len()];
+	let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+
+	for triangle in indices.chunks_exact(3) {
+		let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+		let position = |i: usize| Vec3::from(vertices[i].position);
+		let uv = |i: usize| Vec2::from(vertices[i].uv);
+
+		let (edge1, edge2) =
+			(position(b) - position(a), position(c) - position(a));
+		let (duv1, duv2) = (uv(b) - uv(a), uv(c) - uv(a));
+		let determinant = duv1.perp_dot(duv2);
+		if determinant.abs() <= f32::EPSILON {
+			continue;
+		}
+
+		let tangent = (edge1 * duv2.y - edge2 * duv1.y) / determinant;
+		let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / determinant;
+		for i in [a, b, c] {
+			tangents[i] += tangent;
+			bitangents[i] += bitangent;
+		}
+	}
+
+	for (vertex, (tangent, bitangent)) in vertices
+		.iter_mut()
+		.zip(tangents.into_iter().zip(bitangents))
+	{
+		let normal = Vec3::from(vertex.normal);
+		let tangent =
+			(tangent - normal * normal.dot(tangent)).normalize_or_zero();
+		if tangent == Vec3::ZERO {
+			continue;
+		}
+
+		// Mirrored UVs flip the bitangent relative to the normal and tangent
+		let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+			-1.0
+		} else {
+			1.0
+		};
+		vertex.tangent = tangent.extend(handedness).to_array();
+	}
+}
+
+fn thick_line_geometry(
+	segments: &[[Vector3; 2]],
+	width: f32,
+	view: &Matrix4,
+) -> (Vec<Vertex>, Vec<u32>) {
+	let eye = Mat4::from_cols_array(view).inverse().w_axis.truncate();
+	let mut vertices = Vec::with_capacity(segments.len() * 4);
+	let mut indices = Vec::with_capacity(segments.len() * 6);
+
+	for [start, end] in segments {
+		let (start, end) = (Vec3::from(*start), Vec3::from(*end));
+		let to_eye = eye - (start + end) / 2.0;
+		let side =
+			(end - start).cross(to_eye).normalize_or_zero() * width / 2.0;
+
+		let base = vertices.len() as u32;
+		for position in [start - side, start + side, end + side, end - side] {
+			vertices.push(Vertex::new(position.to_array()));
+		}
+		indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+	}
+
+	(vertices, indices)
+}
+
+/// Concatenate vertex and index lists, shifting each list's indices past
+/// the vertices that came before it
+fn merge_geometry<'a>(
+	parts: impl IntoIterator<Item = (&'a [Vertex], &'a [u32])>,
+) -> (Vec<Vertex>, Vec<u32>) {
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	for (part_vertices, part_indices) in parts {
+		let offset = vertices.len() as u32;
+		vertices.extend_from_slice(part_vertices);
+		indices.extend(part_indices.iter().map(|i| i + offset));
+	}
+
+	(vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn merge_offsets_second_triangle() {
+		let triangle = [
+			Vertex::new([0.0, 0.0, 0.0]),
+			Vertex::new([1.0, 0.0, 0.0]),
+			Vertex::new([0.0, 1.0, 0.0]),
+		];
+		let indices = [0, 1, 2];
+
+		let (vertices, indices) =
+			merge_geometry([(&triangle[..], &indices[..]); 2]);
+
+		assert_eq!(vertices.len(), 6);
+		assert_eq!(indices, [0, 1, 2, 3, 4, 5]);
+	}
 }
@@ -1,28 +1,62 @@
-use crate::{App, AppSettings, ArcRenderPass, RenderCallback};
-use anyhow::{Context, Result};
-use dyadikos_math::{Matrix4, Vertex};
+use crate::{
+	background::BackgroundPass,
+	bloom::BloomPass,
+	camera::CameraController,
+	depth::{create_depth_view, DepthResolvePass},
+	device::request_device,
+	error::DyadikosError,
+	mesh::{push_constant_ranges, vertex_buffer_layout},
+	profiler::GpuProfiler,
+	reflect::{validate_limits, BindingInfo, ShaderReflection},
+	screenshot::Screenshot,
+	texture::SamplerSettings,
+	tonemap::{TonemapPass, HDR_FORMAT},
+	App, AppSettings, ArcRenderPass, FrameGlobals, RenderCallback,
+	FRAME_GLOBALS_GROUP, SAMPLER_GROUP,
+};
+use anyhow::{bail, Context, Result};
+use dyadikos_math::{camera::Camera, Matrix4};
 use std::{
 	borrow::Cow,
+	collections::BTreeMap,
+	path::Path,
 	sync::{Arc, Mutex, RwLock},
+	time::Instant,
 };
+use tracing::{error, info, warn};
 use typed_arena::Arena;
 use wgpu::{
-	util::DeviceExt, Backends, BindGroup, BindGroupLayout,
-	CommandEncoderDescriptor, Device, DeviceDescriptor, FragmentState,
-	Instance, Limits, LoadOp, MultisampleState, Operations,
-	PipelineLayoutDescriptor, PowerPreference, PresentMode, PrimitiveState,
-	Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
-	RenderPipelineDescriptor, RequestAdapterOptions, ShaderModuleDescriptor,
-	ShaderSource, Surface, SurfaceConfiguration, TextureUsages,
-	TextureViewDescriptor, VertexState,
+	util::DeviceExt, AdapterInfo, Backends, BindGroup, BindGroupLayout,
+	BindingResource, BindingType, Buffer, BufferBindingType, Color,
+	ColorTargetState, ColorWrites, CommandEncoderDescriptor, Device, Extent3d,
+	Features, FragmentState, Instance, LoadOp, Operations,
+	PipelineLayoutDescriptor, PresentMode, PrimitiveState, Queue,
+	RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+	RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler,
+	SamplerBindingType, ShaderModuleDescriptor, ShaderSource, Surface,
+	SurfaceConfiguration, SurfaceError, TextureDescriptor, TextureDimension,
+	TextureFormat, TextureFormatFeatureFlags, TextureSampleType, TextureUsages,
+	TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
 };
 use winit::{
-	event::{Event, WindowEvent},
+	dpi::PhysicalSize,
+	event::{DeviceEvent, ElementState, Event, KeyboardInput, WindowEvent},
 	event_loop::{ControlFlow, EventLoop},
 	platform::run_return::EventLoopExtRunReturn,
-	window::Window,
+	window::{Icon, Window, WindowBuilder},
 };
 
+/// What the window surface supports on the app's adapter
+///
+/// wgpu 0.13 doesn't expose composite alpha modes, so surfaces are always
+/// configured as opaque.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceInfo {
+	/// Supported formats, the adapter's preferred format first
+	pub formats: Vec<TextureFormat>,
+	pub present_modes: Vec<PresentMode>,
+}
+
 #[derive(Clone)]
 pub struct NativeApp {
 	pub event_loop: Arc<RwLock<EventLoop<()>>>,
@@ -30,11 +64,54 @@ pub struct NativeApp {
 	pub surface: Arc<Surface>,
 	pub device: Arc<Device>,
 	pub config: Arc<Mutex<SurfaceConfiguration>>,
+	pub swapchain_format: TextureFormat,
+	pub adapter_info: AdapterInfo,
+	pub surface_info: SurfaceInfo,
 	pub queue: Arc<Queue>,
 	pub settings: AppSettings,
 	pub render_pipeline: Arc<RenderPipeline>,
-	pub bind_group: Option<Arc<BindGroup>>,
+	pub bind_group: Arc<BindGroup>,
 	pub bind_group_layout: Arc<BindGroupLayout>,
+	/// Resource bindings the scene shader declares
+	pub bindings: Vec<BindingInfo>,
+	/// Holds the matrix passed to [`App::run`], bound at group 0, binding 0
+	/// when the shader declares it
+	pub uniform_buffer: Arc<Buffer>,
+	/// Zeroed buffers for every other uniform the shader declares in group 0,
+	/// keyed by binding
+	pub uniform_buffers: BTreeMap<u32, Arc<Buffer>>,
+	/// Views bound to the textures the shader declares in group 0, keyed by
+	/// binding, which are 1x1 white placeholders until replaced with
+	/// [`NativeApp::set_texture`]
+	pub textures: BTreeMap<u32, Arc<TextureView>>,
+	/// Samplers bound in [`SAMPLER_GROUP`], keyed by binding
+	pub samplers: BTreeMap<u32, Arc<Sampler>>,
+	pub sampler_bind_group_layout: Arc<BindGroupLayout>,
+	/// Set when the shader declares samplers
+	pub sampler_bind_group: Option<Arc<BindGroup>>,
+	pub globals_buffer: Arc<Buffer>,
+	pub globals_bind_group: Arc<BindGroup>,
+	/// Depth buffer the scene pass tests against, created when
+	/// [`AppSettings::depth`] is set
+	pub depth_view: Option<Arc<TextureView>>,
+	/// Resolves [`NativeApp::depth_view`] every frame when
+	/// [`AppSettings::resolve_depth`] is set
+	pub depth_resolve_pass: Option<DepthResolvePass>,
+	/// Multisampled color target resolved into the scene target, created
+	/// when [`AppSettings::msaa_samples`] is above 1
+	pub msaa_view: Option<Arc<TextureView>>,
+	/// Targets for [`AppSettings::extra_targets`], in the same order
+	pub extra_targets: Vec<Arc<TextureView>>,
+	pub background_pass: Option<Arc<BackgroundPass>>,
+	pub tonemap_pass: Option<TonemapPass>,
+	pub bloom_pass: Option<BloomPass>,
+	/// Times the scene, bloom and tonemap passes when the device has
+	/// [`wgpu::Features::TIMESTAMP_QUERY`], waiting for every frame to finish
+	/// on the GPU to read them back
+	pub profiler: Arc<Mutex<GpuProfiler>>,
+	/// Moved by input events while running, starting from
+	/// [`AppSettings::camera`]
+	pub camera: Option<CameraController>,
 }
 
 impl App for NativeApp {
@@ -46,12 +123,50 @@ impl App for NativeApp {
 		&self.device
 	}
 
+	fn get_queue(&self) -> &Queue {
+		&self.queue
+	}
+
 	fn get_pipeline(&self) -> &RenderPipeline {
 		&self.render_pipeline
 	}
 
 	fn get_bind_group(&self) -> &BindGroup {
-		self.bind_group.as_ref().unwrap()
+		&self.bind_group
+	}
+
+	fn get_bind_group_layout(&self) -> &BindGroupLayout {
+		&self.bind_group_layout
+	}
+
+	fn get_globals_bind_group(&self) -> &BindGroup {
+		&self.globals_bind_group
+	}
+
+	fn get_uniform_buffer(&self) -> &Buffer {
+		&self.uniform_buffer
+	}
+
+	fn get_sampler_bind_group(&self) -> Option<&BindGroup> {
+		self.sampler_bind_group.as_deref()
+	}
+
+	fn get_camera(&self) -> Option<&Camera> {
+		self.camera.as_ref().map(|controller| &controller.camera)
+	}
+
+	fn get_uniform_buffer_at(&self, binding: u32) -> Option<&Buffer> {
+		match binding {
+			0 => Some(&self.uniform_buffer),
+			binding => self.uniform_buffers.get(&binding).map(AsRef::as_ref),
+		}
+	}
+
+	fn get_surface_format(&self) -> TextureFormat {
+		match self.tonemap_pass {
+			Some(_) => HDR_FORMAT,
+			None => self.swapchain_format,
+		}
 	}
 
 	fn get_window_size(&self) -> (u32, u32) {
@@ -61,76 +176,275 @@ impl App for NativeApp {
 	}
 
 	fn run(mut self, matrix: &Matrix4, mut callback: Box<RenderCallback>) {
-		let mut uniform_buffer =
-			self.device
-				.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-					label: Some("Uniform Buffer"),
-					contents: bytemuck::cast_slice(matrix),
-					usage: wgpu::BufferUsages::UNIFORM
-						| wgpu::BufferUsages::COPY_DST,
-				});
-
-		self.event_loop.try_write().unwrap().run_return(
+		self.queue.write_buffer(
+			&self.uniform_buffer,
+			0,
+			bytemuck::cast_slice(matrix),
+		);
+
+		let start = Instant::now();
+		let mut screenshot_requested = false;
+		// Only set while the camera moves, so it doesn't jump by the time
+		// spent idle when a key is pressed
+		let mut last_camera_update: Option<Instant> = None;
+
+		// The closure takes ownership of self so it can be handed to the
+		// callback, which means the event loop has to be borrowed separately
+		let event_loop = self.event_loop.clone();
+		event_loop.try_write().unwrap().run_return(
 			move |event, _, control_flow| {
 				let config = self.config.clone();
-				let mut config = config.try_lock().unwrap();
+				let config = config.try_lock().unwrap();
 				let surface = self.surface.clone();
 				let window = self.window.clone();
 				let device = self.device.clone();
 
 				*control_flow = ControlFlow::Wait;
+				if let Some(camera) = &mut self.camera {
+					let changed = match &event {
+						Event::WindowEvent { event, .. } => {
+							camera.process_window_event(event)
+						}
+						Event::DeviceEvent {
+							event: DeviceEvent::MouseMotion { delta },
+							..
+						} => camera.process_mouse_motion(*delta),
+						_ => false,
+					};
+					if changed {
+						window.request_redraw();
+					}
+				}
 				match event {
 					Event::WindowEvent {
 						event: WindowEvent::Resized(size),
 						..
 					} => {
-						// Reconfigure the surface with the new size
-						config.width = size.width;
-						config.height = size.height;
-						surface.configure(&device, &config);
+						// Release the config so resize can lock it
+						drop(config);
+						self.resize(size);
 						// On macos the window needs to be redrawn manually after resizing
 						window.request_redraw();
 					}
+					// Moving to a monitor with a different DPI changes the
+					// physical size without a Resized event
+					Event::WindowEvent {
+						event:
+							WindowEvent::ScaleFactorChanged {
+								new_inner_size, ..
+							},
+						..
+					} => {
+						drop(config);
+						self.resize(*new_inner_size);
+						window.request_redraw();
+					}
+					Event::WindowEvent {
+						event:
+							WindowEvent::KeyboardInput {
+								input:
+									KeyboardInput {
+										state: ElementState::Pressed,
+										virtual_keycode: Some(key),
+										..
+									},
+								..
+							},
+						..
+					} if self.settings.screenshot_key == Some(key) => {
+						screenshot_requested = true;
+						window.request_redraw();
+					}
 					Event::RedrawRequested(_) => {
-						let frame = self
-							.surface
-							.get_current_texture()
-							.context(
-								"Failed to acquire next swap chain texture",
-							)
-							.unwrap();
+						let size = window.inner_size();
+						if size.width == 0 || size.height == 0 {
+							return;
+						}
+
+						let frame = match surface.get_current_texture() {
+							Ok(frame) => frame,
+							// The surface no longer matches the window, so
+							// reconfigure it and try again next frame
+							Err(
+								SurfaceError::Lost | SurfaceError::Outdated,
+							) => {
+								surface.configure(&device, &config);
+								window.request_redraw();
+								return;
+							}
+							Err(SurfaceError::Timeout) => {
+								warn!("Timed out acquiring the next frame");
+								return;
+							}
+							Err(SurfaceError::OutOfMemory) => {
+								error!(
+									"Out of memory acquiring the next frame"
+								);
+								*control_flow = ControlFlow::Exit;
+								return;
+							}
+						};
 						let view = frame
 							.texture
 							.create_view(&TextureViewDescriptor::default());
 
-						self.bind_group =
-							Some(Arc::new(device.create_bind_group(
-								&wgpu::BindGroupDescriptor {
-									layout: &self.bind_group_layout,
-									entries: &[wgpu::BindGroupEntry {
-										binding: 0,
-										resource:
-											uniform_buffer.as_entire_binding(),
-									}],
-									label: None,
-								},
-							)));
+						let globals = FrameGlobals::new(
+							start.elapsed().as_secs_f32(),
+							[config.width as f32, config.height as f32],
+						);
+						self.write_frame_globals(&globals);
+
+						if let Some(camera) = &mut self.camera {
+							let elapsed = last_camera_update
+								.map_or(0.0, |last| {
+									last.elapsed().as_secs_f32()
+								});
+							last_camera_update =
+								camera.is_moving().then(Instant::now);
+
+							camera.update(elapsed);
+							camera.camera.aspect =
+								config.width as f32 / config.height as f32;
+							self.queue.write_buffer(
+								&self.uniform_buffer,
+								0,
+								bytemuck::cast_slice(
+									&camera.camera.view_proj(),
+								),
+							);
+						}
 
 						let mut encoder = self.device.create_command_encoder(
 							&CommandEncoderDescriptor { label: None },
 						);
+						let profiler = self.profiler.clone();
+						let mut profiler = profiler.lock().unwrap();
+						// With HDR on, the scene goes to the offscreen target
+						// and is tonemapped onto the frame afterwards
+						let target = match &self.tonemap_pass {
+							Some(tonemap_pass) => tonemap_pass.view(),
+							None => &view,
+						};
+						// With MSAA on, the scene is drawn multisampled and
+						// resolved into the target
+						let (target, resolve_target) = match &self.msaa_view {
+							Some(msaa_view) => {
+								(msaa_view.as_ref(), Some(target))
+							}
+							None => (target, None),
+						};
+						let color_attachments: Vec<_> = std::iter::once((
+							target,
+							resolve_target,
+							self.settings.background.clear_color(),
+						))
+						.chain(self.extra_targets.iter().map(|view| {
+							(view.as_ref(), None, Color::TRANSPARENT)
+						}))
+						.map(|(view, resolve_target, clear)| {
+							Some(RenderPassColorAttachment {
+								view,
+								resolve_target,
+								ops: Operations {
+									load: LoadOp::Clear(clear),
+									store: true,
+								},
+							})
+						})
+						.collect();
+						profiler.begin("Scene", &mut encoder);
 						{
 							let mut rpass = encoder.begin_render_pass(
 								&RenderPassDescriptor {
 									label: None,
+									color_attachments: &color_attachments,
+									depth_stencil_attachment: self
+										.settings
+										.depth
+										.zip(self.depth_view.as_deref())
+										.map(|(depth, view)| {
+											RenderPassDepthStencilAttachment {
+												view,
+												depth_ops: Some(Operations {
+													load: LoadOp::Clear(
+														depth.clear_depth,
+													),
+													store: true,
+												}),
+												stencil_ops: None,
+											}
+										}),
+								},
+							);
+							if let Some(background_pass) = &self.background_pass
+							{
+								background_pass.draw(&mut rpass);
+							}
+							rpass.set_pipeline(&self.render_pipeline);
+
+							let mut rpass = ArcRenderPass {
+								arena: &Arena::new(),
+								bind_groups: &Arena::new(),
+								bundles: &Arena::new(),
+								pipelines: &Arena::new(),
+								render_pass: rpass,
+							};
+							rpass.set_bind_group(
+								0,
+								self.bind_group.clone(),
+								&[],
+							);
+							rpass.set_bind_group(
+								FRAME_GLOBALS_GROUP,
+								self.globals_bind_group.clone(),
+								&[],
+							);
+							if let Some(sampler_bind_group) =
+								&self.sampler_bind_group
+							{
+								rpass.set_bind_group(
+									SAMPLER_GROUP,
+									sampler_bind_group.clone(),
+									&[],
+								);
+							}
+
+							if let Err(error) = callback(&mut rpass, &self) {
+								error!("Render callback failed: {error:#}");
+								if self.settings.exit_on_error {
+									*control_flow = ControlFlow::Exit;
+								}
+							}
+						}
+						profiler.end(&mut encoder);
+
+						if let Some(depth_resolve_pass) =
+							&self.depth_resolve_pass
+						{
+							profiler.begin("Depth Resolve", &mut encoder);
+							depth_resolve_pass.encode(&mut encoder);
+							profiler.end(&mut encoder);
+						}
+
+						if let Some(tonemap_pass) = &self.tonemap_pass {
+							if let Some(bloom_pass) = &self.bloom_pass {
+								profiler.begin("Bloom", &mut encoder);
+								bloom_pass
+									.encode(&mut encoder, tonemap_pass.view());
+								profiler.end(&mut encoder);
+							}
+
+							profiler.begin("Tonemap", &mut encoder);
+							let mut rpass = encoder.begin_render_pass(
+								&RenderPassDescriptor {
+									label: Some("Tonemap"),
 									color_attachments: &[Some(
 										RenderPassColorAttachment {
 											view: &view,
 											resolve_target: None,
 											ops: Operations {
 												load: LoadOp::Clear(
-													self.settings
-														.background_color,
+													Color::BLACK,
 												),
 												store: true,
 											},
@@ -139,24 +453,64 @@ impl App for NativeApp {
 									depth_stencil_attachment: None,
 								},
 							);
-							rpass.set_pipeline(&self.render_pipeline);
-
-							let mut rpass = ArcRenderPass {
-								arena: &Arena::new(),
-								render_pass: rpass,
-							};
-							rpass.set_bind_group(
-								0,
-								self.bind_group.as_ref().unwrap(),
-								&[],
-							);
-
-							callback(rpass, &mut uniform_buffer);
+							tonemap_pass.draw(&mut rpass);
+							drop(rpass);
+							profiler.end(&mut encoder);
 						}
+						profiler.resolve(&mut encoder);
+
+						// Copied once everything is drawn, so the image
+						// matches what is presented
+						let screenshot =
+							std::mem::take(&mut screenshot_requested)
+								.then(|| {
+									Screenshot::encode(
+										&device,
+										&mut encoder,
+										&frame.texture,
+										config.format,
+										config.width,
+										config.height,
+									)
+								})
+								.transpose()
+								.unwrap_or_else(|error| {
+									error!("Failed to take a screenshot: {error:#}");
+									None
+								});
 
 						self.queue.submit(Some(encoder.finish()));
 						frame.present();
+
+						if let Some(screenshot) = screenshot {
+							match screenshot.save(&device, &self.queue) {
+								Ok(path) => {
+									info!("Saved screenshot to {path:?}")
+								}
+								Err(error) => {
+									error!(
+										"Failed to save screenshot: {error:#}"
+									)
+								}
+							}
+						}
+
+						if let Err(error) = profiler.collect(&device) {
+							warn!("Failed to read GPU timings: {error:#}");
+						}
+
+						// Keep drawing frames while the camera moves
+						if matches!(&self.camera, Some(camera) if camera.is_moving())
+						{
+							window.request_redraw();
+						}
 					}
+					// Release the cursor while another window has focus,
+					// so it isn't trapped
+					Event::WindowEvent {
+						event: WindowEvent::Focused(focused),
+						..
+					} if self.settings.grab_cursor => grab_cursor(&window, focused),
 					Event::WindowEvent {
 						event: WindowEvent::CloseRequested,
 						..
@@ -169,75 +523,434 @@ impl App for NativeApp {
 }
 
 impl NativeApp {
-	pub async fn new(settings: AppSettings) -> Result<Self> {
+	/// Backend, device name and driver of the GPU the app is running on
+	pub fn adapter_info(&self) -> &AdapterInfo {
+		&self.adapter_info
+	}
+
+	/// Physical pixels per logical pixel of the monitor the window is on,
+	/// for scaling UI drawn in the render callback
+	pub fn scale_factor(&self) -> f64 {
+		self.window.scale_factor()
+	}
+
+	/// Formats and present modes the surface can be configured with
+	pub fn surface_info(&self) -> &SurfaceInfo {
+		&self.surface_info
+	}
+
+	/// Reconfigure the surface for a new window size and recreate every
+	/// texture sized to match it, so none of them are ever out of step
+	///
+	/// This is called for every resize event. Zero sizes, reported while
+	/// the window is minimized, can't be configured and are skipped.
+	pub fn resize(&mut self, size: PhysicalSize<u32>) {
+		if size.width == 0 || size.height == 0 {
+			return;
+		}
+
+		let mut config = self.config.lock().unwrap();
+		config.width = size.width;
+		config.height = size.height;
+		self.surface.configure(&self.device, &config);
+		drop(config);
+
+		let device = &self.device;
+		let sample_count = self.settings.multisample_state().count;
+		if self.msaa_view.is_some() {
+			self.msaa_view = Some(Arc::new(create_color_target(
+				device,
+				"MSAA Target",
+				self.get_surface_format(),
+				sample_count,
+				size.width,
+				size.height,
+			)));
+		}
+		if self.depth_view.is_some() {
+			let depth_view = Arc::new(create_depth_view(
+				device,
+				size.width,
+				size.height,
+				sample_count,
+			));
+			if let Some(depth_resolve_pass) = &mut self.depth_resolve_pass {
+				depth_resolve_pass.resize(
+					device,
+					&depth_view,
+					size.width,
+					size.height,
+				);
+			}
+			self.depth_view = Some(depth_view);
+		}
+		self.extra_targets = create_extra_targets(
+			device,
+			&self.settings.extra_targets,
+			size.width,
+			size.height,
+		);
+		if let Some(tonemap_pass) = &mut self.tonemap_pass {
+			tonemap_pass.resize(device, size.width, size.height);
+
+			if let Some(bloom_pass) = &mut self.bloom_pass {
+				bloom_pass.resize(
+					device,
+					tonemap_pass.view(),
+					size.width,
+					size.height,
+				);
+			}
+		}
+	}
+
+	/// Bind `view` to the texture the shader declares at `binding` in group
+	/// 0, in place of its placeholder
+	///
+	/// The view has to match the dimension the shader declares. Render
+	/// bundles keep the textures they were baked with.
+	pub fn set_texture(
+		&mut self,
+		binding: u32,
+		view: Arc<TextureView>,
+	) -> Result<()> {
+		if !self.textures.contains_key(&binding) {
+			bail!(
+				"The shader declares no texture at group 0, binding {binding}"
+			);
+		}
+
+		self.textures.insert(binding, view);
+		self.bind_group = Arc::new(create_scene_bind_group(
+			&self.device,
+			&self.bind_group_layout,
+			&self.bindings,
+			&self.uniform_buffer,
+			&self.uniform_buffers,
+			&self.textures,
+		));
+
+		Ok(())
+	}
+
+	/// Replace the default linear, clamped sampler the shader declares at
+	/// `binding` in [`SAMPLER_GROUP`]
+	pub fn set_sampler(
+		&mut self,
+		binding: u32,
+		settings: &SamplerSettings,
+	) -> Result<()> {
+		if !self.samplers.contains_key(&binding) {
+			bail!(
+				"The shader declares no sampler at group {SAMPLER_GROUP}, \
+				 binding {binding}"
+			);
+		}
+
+		let sampler = settings.create_sampler(&self.device)?;
+		self.samplers.insert(binding, Arc::new(sampler));
+		self.sampler_bind_group = Some(Arc::new(create_sampler_bind_group(
+			&self.device,
+			&self.sampler_bind_group_layout,
+			&self.samplers,
+		)));
+
+		Ok(())
+	}
+
+	/// Upload the built-in uniforms read through [`FRAME_GLOBALS_GROUP`]
+	pub fn write_frame_globals(&self, globals: &FrameGlobals) {
+		self.queue.write_buffer(
+			&self.globals_buffer,
+			0,
+			bytemuck::bytes_of(globals),
+		);
+	}
+
+	pub async fn new(settings: AppSettings) -> Result<Self, DyadikosError> {
+		match settings.msaa_samples {
+			Some(1 | 4) | None => {}
+			Some(samples) => {
+				return Err(DyadikosError::InvalidSettings(format!(
+					"MSAA needs 1 or 4 samples, got {samples}"
+				)))
+			}
+		}
+		if settings.multisample_state().count > 1
+			&& !settings.extra_targets.is_empty()
+		{
+			return Err(DyadikosError::InvalidSettings(
+				"MSAA can't be combined with extra targets".to_string(),
+			));
+		}
+		if settings.resolve_depth
+			&& (settings.depth.is_none()
+				|| settings.multisample_state().count == 1)
+		{
+			return Err(DyadikosError::InvalidSettings(
+				"Resolving depth needs a depth buffer and MSAA".to_string(),
+			));
+		}
+
 		let event_loop = EventLoop::new();
-		let window = Window::new(&event_loop)?;
+		let window_icon = settings
+			.window_icon
+			.as_deref()
+			.map(load_icon)
+			.transpose()
+			.map_err(|error| {
+				DyadikosError::WindowCreation(format!("{error:#}"))
+			})?;
+		let window = WindowBuilder::new()
+			.with_window_icon(window_icon)
+			.build(&event_loop)
+			.map_err(|error| {
+				DyadikosError::WindowCreation(error.to_string())
+			})?;
+		window.set_cursor_visible(!settings.hide_cursor);
+		if settings.grab_cursor {
+			grab_cursor(&window, true);
+		}
 
 		let size = window.inner_size();
 		let instance = Instance::new(Backends::all());
 		let surface = unsafe { instance.create_surface(&window) };
-		let adapter = instance
-			.request_adapter(&RequestAdapterOptions {
-				power_preference: PowerPreference::default(),
-				force_fallback_adapter: false,
-				// Request an adapter which can render to our surface
-				compatible_surface: Some(&surface),
-			})
-			.await
-			.context("Failed to find an appropriate adapter")?;
-
-		// Create the logical device and command queue
-		let (device, queue) = adapter
-			.request_device(
-				&DeviceDescriptor {
-					label: None,
-					features: settings.features,
-					limits: Limits::downlevel_webgl2_defaults()
-						.using_resolution(adapter.limits()),
-				},
-				None,
-			)
-			.await
-			.context("Failed to create device")?;
+		// Request an adapter which can render to our surface
+		let (adapter, device, queue) = request_device(
+			&instance,
+			Some(&surface),
+			settings.features,
+			settings.limits,
+			settings.power_preference,
+			settings.allow_software_fallback,
+		)
+		.await?;
+
+		let reflection = ShaderReflection::from_wgsl(&settings.shader)
+			.map_err(DyadikosError::shader)?;
+		let bindings = reflection.bindings().map_err(DyadikosError::shader)?;
+		validate_limits(&bindings, &device.limits()).map_err(|error| {
+			DyadikosError::LimitsExceeded(format!("{error:#}"))
+		})?;
+		check_scene_bindings(&bindings).map_err(DyadikosError::shader)?;
+		let layout_entries = |group| -> Vec<_> {
+			bindings
+				.iter()
+				.filter(|info| info.group == group)
+				.map(BindingInfo::layout_entry)
+				.collect()
+		};
 
 		let bind_group_layout =
 			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 				label: None,
+				entries: &layout_entries(0),
+			});
+		let sampler_layout_entries = layout_entries(SAMPLER_GROUP);
+		let sampler_bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Samplers"),
+				entries: &sampler_layout_entries,
+			});
+
+		let create_uniform_buffer = |size| {
+			Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+				label: Some("Uniform Buffer"),
+				size,
+				usage: wgpu::BufferUsages::UNIFORM
+					| wgpu::BufferUsages::COPY_DST,
+				mapped_at_creation: false,
+			}))
+		};
+		let mut uniform_sizes: BTreeMap<_, _> = bindings
+			.iter()
+			.filter(|info| {
+				info.group == 0
+					&& matches!(
+						info.ty,
+						BindingType::Buffer {
+							ty: BufferBindingType::Uniform,
+							..
+						}
+					)
+			})
+			.map(|info| (info.binding, info.size.unwrap_or_default()))
+			.collect();
+		// Binding 0 always holds at least the matrix passed to run
+		let matrix_size = std::mem::size_of::<Matrix4>() as u64;
+		let uniform_buffer = create_uniform_buffer(
+			uniform_sizes
+				.remove(&0)
+				.unwrap_or_default()
+				.max(matrix_size),
+		);
+		let uniform_buffers: BTreeMap<_, _> = uniform_sizes
+			.into_iter()
+			.map(|(binding, size)| (binding, create_uniform_buffer(size)))
+			.collect();
+		let textures = bindings
+			.iter()
+			.filter(|info| info.group == 0)
+			.filter_map(|info| match info.ty {
+				BindingType::Texture { view_dimension, .. } => Some((
+					info.binding,
+					Arc::new(create_placeholder_texture(
+						&device,
+						&queue,
+						view_dimension,
+					)),
+				)),
+				_ => None,
+			})
+			.collect();
+		let bind_group = create_scene_bind_group(
+			&device,
+			&bind_group_layout,
+			&bindings,
+			&uniform_buffer,
+			&uniform_buffers,
+			&textures,
+		);
+
+		let default_sampler = Arc::new(
+			SamplerSettings::default()
+				.create_sampler(&device)
+				.expect("the default sampler settings are valid"),
+		);
+		let samplers: BTreeMap<_, _> = sampler_layout_entries
+			.iter()
+			.map(|entry| (entry.binding, default_sampler.clone()))
+			.collect();
+		let sampler_bind_group = (!samplers.is_empty()).then(|| {
+			Arc::new(create_sampler_bind_group(
+				&device,
+				&sampler_bind_group_layout,
+				&samplers,
+			))
+		});
+
+		let globals_size = std::mem::size_of::<FrameGlobals>() as u64;
+		let globals_bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Frame Globals"),
 				entries: &[wgpu::BindGroupLayoutEntry {
 					binding: 0,
-					visibility: wgpu::ShaderStages::VERTEX,
+					visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
 					ty: wgpu::BindingType::Buffer {
 						ty: wgpu::BufferBindingType::Uniform,
 						has_dynamic_offset: false,
-						min_binding_size: wgpu::BufferSize::new(64),
+						min_binding_size: wgpu::BufferSize::new(globals_size),
 					},
 					count: None,
 				}],
 			});
 
+		let globals_buffer =
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Frame Globals"),
+				contents: bytemuck::bytes_of(&FrameGlobals::default()),
+				usage: wgpu::BufferUsages::UNIFORM
+					| wgpu::BufferUsages::COPY_DST,
+			});
+
+		let globals_bind_group =
+			device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("Frame Globals"),
+				layout: &globals_bind_group_layout,
+				entries: &[wgpu::BindGroupEntry {
+					binding: 0,
+					resource: globals_buffer.as_entire_binding(),
+				}],
+			});
+
+		// The sampler group is left out when unused, so shaders without
+		// textures don't need it bound
+		let mut bind_group_layouts =
+			vec![&bind_group_layout, &globals_bind_group_layout];
+		if sampler_bind_group.is_some() {
+			bind_group_layouts.push(&sampler_bind_group_layout);
+		}
 		let pipeline_layout =
 			device.create_pipeline_layout(&PipelineLayoutDescriptor {
 				label: None,
-				bind_group_layouts: &[&bind_group_layout],
-				push_constant_ranges: &[],
+				bind_group_layouts: &bind_group_layouts,
+				push_constant_ranges: push_constant_ranges(device.features()),
 			});
 
-		let swapchain_format = surface.get_supported_formats(&adapter)[0];
+		let surface_info = SurfaceInfo {
+			formats: surface.get_supported_formats(&adapter),
+			present_modes: surface.get_supported_modes(&adapter),
+		};
+
+		let swapchain_format = select_surface_format(
+			&surface_info.formats,
+			settings.surface_format,
+		)
+		.map_err(|error| {
+			DyadikosError::SwapchainCreation(format!("{error:#}"))
+		})?;
+		let present_mode = select_present_mode(
+			&surface_info.present_modes,
+			settings.present_mode,
+		)
+		.map_err(|error| {
+			DyadikosError::SwapchainCreation(format!("{error:#}"))
+		})?;
+
+		let target_format = if settings.hdr {
+			HDR_FORMAT
+		} else {
+			swapchain_format
+		};
+
+		let sample_count = settings.multisample_state().count;
+		let format_features = if device
+			.features()
+			.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+		{
+			adapter.get_texture_format_features(target_format)
+		} else {
+			target_format.describe().guaranteed_format_features
+		};
+		if sample_count > 1
+			&& !format_features
+				.flags
+				.contains(TextureFormatFeatureFlags::MULTISAMPLE)
+		{
+			return Err(DyadikosError::InvalidSettings(format!(
+				"{target_format:?} can't be multisampled on this device"
+			)));
+		}
 
 		let shader = device.create_shader_module(ShaderModuleDescriptor {
 			label: None,
 			source: ShaderSource::Wgsl(Cow::Borrowed(&settings.shader)),
 		});
 
-		let vertex_buffer_layout = wgpu::VertexBufferLayout {
-			array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-			step_mode: wgpu::VertexStepMode::Vertex,
-			attributes: &[wgpu::VertexAttribute {
-				format: wgpu::VertexFormat::Float32x3,
-				offset: 0,
-				shader_location: 0,
-			}],
-		};
+		let vertex_buffer_layouts = settings
+			.vertex_layouts
+			.clone()
+			.unwrap_or_else(|| vec![vertex_buffer_layout()]);
+
+		reflection
+			.validate_vertex_layout("vs_main", &vertex_buffer_layouts)
+			.map_err(|error| DyadikosError::ShaderCompile {
+				stage: Some(wgpu::ShaderStages::VERTEX),
+				message: format!(
+					"Shader does not match the vertex layout: {error:#}"
+				),
+			})?;
+
+		// The scene shader writes every extra target, unlike the built-in
+		// pipelines
+		let targets: Vec<_> = std::iter::once(ColorTargetState {
+			format: target_format,
+			blend: settings.blend.blend_state(),
+			write_mask: ColorWrites::ALL,
+		})
+		.chain(settings.extra_targets.iter().map(|&format| format.into()))
+		.map(Some)
+		.collect();
 
 		let render_pipeline =
 			device.create_render_pipeline(&RenderPipelineDescriptor {
@@ -246,28 +959,107 @@ impl NativeApp {
 				vertex: VertexState {
 					module: &shader,
 					entry_point: "vs_main",
-					buffers: &[vertex_buffer_layout],
+					buffers: &vertex_buffer_layouts,
 				},
 				fragment: Some(FragmentState {
 					module: &shader,
 					entry_point: "fs_main",
-					targets: &[Some(swapchain_format.into())],
+					targets: &targets,
 				}),
 				primitive: PrimitiveState::default(),
-				depth_stencil: None,
-				multisample: MultisampleState::default(),
+				depth_stencil: settings
+					.depth
+					.map(|depth| depth.depth_stencil_state()),
+				multisample: settings.multisample_state(),
 				multiview: None,
 			});
 
+		let background_pass = BackgroundPass::new(
+			&device,
+			target_format,
+			&settings.extra_targets,
+			settings.depth.as_ref(),
+			settings.multisample_state(),
+			&settings.background,
+		);
+		let extra_targets = create_extra_targets(
+			&device,
+			&settings.extra_targets,
+			size.width,
+			size.height,
+		);
+		let msaa_view = (sample_count > 1).then(|| {
+			Arc::new(create_color_target(
+				&device,
+				"MSAA Target",
+				target_format,
+				sample_count,
+				size.width,
+				size.height,
+			))
+		});
+		let depth_view = settings.depth.map(|_| {
+			Arc::new(create_depth_view(
+				&device,
+				size.width,
+				size.height,
+				sample_count,
+			))
+		});
+		let depth_resolve_pass = settings
+			.depth
+			.zip(depth_view.as_ref())
+			.filter(|_| settings.resolve_depth)
+			.map(|(depth, depth_view)| {
+				DepthResolvePass::new(
+					&device,
+					&depth,
+					depth_view,
+					sample_count,
+					size.width,
+					size.height,
+				)
+			});
+		let tonemap_pass = settings.hdr.then(|| {
+			TonemapPass::new(&device, swapchain_format, size.width, size.height)
+		});
+		if settings.bloom.is_some() && !settings.hdr {
+			warn!("Bloom needs an HDR target, so it is disabled");
+		}
+		let bloom_pass = settings.bloom.zip(tonemap_pass.as_ref()).map(
+			|(bloom, tonemap_pass)| {
+				BloomPass::new(
+					&device,
+					bloom,
+					tonemap_pass.view(),
+					size.width,
+					size.height,
+				)
+			},
+		);
+
 		let config = SurfaceConfiguration {
-			usage: TextureUsages::RENDER_ATTACHMENT,
+			usage: if settings.screenshot_key.is_some() {
+				TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC
+			} else {
+				TextureUsages::RENDER_ATTACHMENT
+			},
 			format: swapchain_format,
 			width: size.width,
 			height: size.height,
-			present_mode: PresentMode::Mailbox,
+			present_mode,
 		};
 
-		surface.configure(&device, &config);
+		if size.width > 0 && size.height > 0 {
+			surface.configure(&device, &config);
+		}
+
+		// One scope for the scene pass and one for each pass after it
+		let scopes = 1
+			+ depth_resolve_pass.is_some() as u32
+			+ bloom_pass.is_some() as u32
+			+ tonemap_pass.is_some() as u32;
+		let profiler = GpuProfiler::new(&device, &queue, scopes);
 
 		Ok(NativeApp {
 			event_loop: Arc::new(RwLock::new(event_loop)),
@@ -275,11 +1067,294 @@ impl NativeApp {
 			surface: Arc::new(surface),
 			device: Arc::new(device),
 			config: Arc::new(Mutex::new(config)),
+			swapchain_format,
+			adapter_info: adapter.get_info(),
+			surface_info,
 			render_pipeline: Arc::new(render_pipeline),
 			queue: Arc::new(queue),
-			bind_group: None,
+			bind_group: Arc::new(bind_group),
 			bind_group_layout: Arc::new(bind_group_layout),
+			bindings,
+			uniform_buffer,
+			uniform_buffers,
+			textures,
+			samplers,
+			sampler_bind_group_layout: Arc::new(sampler_bind_group_layout),
+			sampler_bind_group,
+			globals_buffer: Arc::new(globals_buffer),
+			globals_bind_group: Arc::new(globals_bind_group),
+			depth_view,
+			depth_resolve_pass,
+			msaa_view,
+			extra_targets,
+			background_pass: background_pass.map(Arc::new),
+			tonemap_pass,
+			bloom_pass,
+			profiler: Arc::new(Mutex::new(profiler)),
+			camera: settings.camera.clone(),
 			settings,
 		})
 	}
 }
+
+/// Check that the scene shader only binds what the app supplies, which is
+/// uniform buffers and float textures in group 0, [`FrameGlobals`] in
+/// [`FRAME_GLOBALS_GROUP`] and filtering samplers in [`SAMPLER_GROUP`],
+/// since anything else fails pipeline creation
+fn check_scene_bindings(bindings: &[BindingInfo]) -> Result<()> {
+	let globals_size = std::mem::size_of::<FrameGlobals>() as u64;
+
+	for info in bindings {
+		let is_uniform = matches!(
+			info.ty,
+			BindingType::Buffer {
+				ty: BufferBindingType::Uniform,
+				..
+			}
+		);
+
+		let supplied = match info.group {
+			0 => {
+				is_uniform
+					|| matches!(
+						info.ty,
+						BindingType::Texture {
+							sample_type: TextureSampleType::Float { .. },
+							multisampled: false,
+							view_dimension,
+						} if view_dimension != TextureViewDimension::CubeArray
+					)
+			}
+			FRAME_GLOBALS_GROUP => {
+				info.binding == 0
+					&& is_uniform && info.size <= Some(globals_size)
+			}
+			SAMPLER_GROUP => {
+				info.ty == BindingType::Sampler(SamplerBindingType::Filtering)
+			}
+			group => bail!(
+				"Binding {} is in group {group}, but the app only supplies \
+				 groups 0, {FRAME_GLOBALS_GROUP} and {SAMPLER_GROUP}",
+				info.binding
+			),
+		};
+		if !supplied {
+			bail!(
+				"The app can't supply the {:?} binding at group {}, binding {}",
+				info.ty,
+				info.group,
+				info.binding
+			);
+		}
+	}
+
+	Ok(())
+}
+
+/// Bind the group 0 uniforms and textures of the scene shader
+fn create_scene_bind_group(
+	device: &Device,
+	layout: &BindGroupLayout,
+	bindings: &[BindingInfo],
+	uniform_buffer: &Buffer,
+	uniform_buffers: &BTreeMap<u32, Arc<Buffer>>,
+	textures: &BTreeMap<u32, Arc<TextureView>>,
+) -> BindGroup {
+	let entries: Vec<_> = bindings
+		.iter()
+		.filter(|info| info.group == 0)
+		.map(|info| wgpu::BindGroupEntry {
+			binding: info.binding,
+			resource: match (info.ty, info.binding) {
+				(BindingType::Texture { .. }, binding) => {
+					BindingResource::TextureView(&textures[&binding])
+				}
+				(_, 0) => uniform_buffer.as_entire_binding(),
+				(_, binding) => uniform_buffers[&binding].as_entire_binding(),
+			},
+		})
+		.collect();
+
+	device.create_bind_group(&wgpu::BindGroupDescriptor {
+		label: None,
+		layout,
+		entries: &entries,
+	})
+}
+
+fn create_sampler_bind_group(
+	device: &Device,
+	layout: &BindGroupLayout,
+	samplers: &BTreeMap<u32, Arc<Sampler>>,
+) -> BindGroup {
+	let entries: Vec<_> = samplers
+		.iter()
+		.map(|(binding, sampler)| wgpu::BindGroupEntry {
+			binding: *binding,
+			resource: BindingResource::Sampler(sampler),
+		})
+		.collect();
+
+	device.create_bind_group(&wgpu::BindGroupDescriptor {
+		label: Some("Samplers"),
+		layout,
+		entries: &entries,
+	})
+}
+
+/// A 1x1 white texture bound until the app is given a real one
+fn create_placeholder_texture(
+	device: &Device,
+	queue: &Queue,
+	view_dimension: TextureViewDimension,
+) -> TextureView {
+	let (dimension, layers) = match view_dimension {
+		TextureViewDimension::D1 => (TextureDimension::D1, 1),
+		TextureViewDimension::D3 => (TextureDimension::D3, 1),
+		TextureViewDimension::Cube => (TextureDimension::D2, 6),
+		_ => (TextureDimension::D2, 1),
+	};
+
+	device
+		.create_texture_with_data(
+			queue,
+			&TextureDescriptor {
+				label: Some("Placeholder Texture"),
+				size: Extent3d {
+					width: 1,
+					height: 1,
+					depth_or_array_layers: layers,
+				},
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension,
+				format: TextureFormat::Rgba8Unorm,
+				usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+			},
+			&vec![u8::MAX; 4 * layers as usize],
+		)
+		.create_view(&TextureViewDescriptor {
+			dimension: Some(view_dimension),
+			..Default::default()
+		})
+}
+
+fn load_icon(path: &Path) -> Result<Icon> {
+	let image = image::open(path)
+		.with_context(|| format!("Failed to load window icon {path:?}"))?
+		.into_rgba8();
+	let (width, height) = image.dimensions();
+
+	Icon::from_rgba(image.into_raw(), width, height)
+		.with_context(|| format!("Invalid window icon {path:?}"))
+}
+
+fn grab_cursor(window: &Window, grab: bool) {
+	if let Err(error) = window.set_cursor_grab(grab) {
+		warn!("Failed to change the cursor grab: {error}");
+	}
+}
+
+fn create_extra_targets(
+	device: &Device,
+	formats: &[TextureFormat],
+	width: u32,
+	height: u32,
+) -> Vec<Arc<TextureView>> {
+	formats
+		.iter()
+		.map(|&format| {
+			Arc::new(create_color_target(
+				device,
+				"Extra Target",
+				format,
+				1,
+				width,
+				height,
+			))
+		})
+		.collect()
+}
+
+fn create_color_target(
+	device: &Device,
+	label: &str,
+	format: TextureFormat,
+	sample_count: u32,
+	width: u32,
+	height: u32,
+) -> TextureView {
+	device
+		.create_texture(&TextureDescriptor {
+			label: Some(label),
+			size: Extent3d {
+				width: width.max(1),
+				height: height.max(1),
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count,
+			dimension: TextureDimension::D2,
+			format,
+			usage: TextureUsages::RENDER_ATTACHMENT
+				| TextureUsages::TEXTURE_BINDING,
+		})
+		.create_view(&TextureViewDescriptor::default())
+}
+
+/// Pick the requested present mode, or mailbox where the surface has it and
+/// FIFO, which every surface supports, otherwise
+fn select_present_mode(
+	modes: &[PresentMode],
+	requested: Option<PresentMode>,
+) -> Result<PresentMode> {
+	if let Some(mode) = requested {
+		if !modes.contains(&mode) {
+			bail!(
+				"Present mode {mode:?} is not supported, expected one of \
+				 {modes:?}"
+			);
+		}
+
+		return Ok(mode);
+	}
+
+	Ok(if modes.contains(&PresentMode::Mailbox) {
+		PresentMode::Mailbox
+	} else {
+		PresentMode::Fifo
+	})
+}
+
+/// Pick the requested surface format, or the first sRGB one so gamma is
+/// consistent across GPUs
+fn select_surface_format(
+	formats: &[TextureFormat],
+	requested: Option<TextureFormat>,
+) -> Result<TextureFormat> {
+	if let Some(format) = requested {
+		if !formats.contains(&format) {
+			bail!(
+				"Surface format {format:?} is not supported, expected one of \
+				 {formats:?}"
+			);
+		}
+
+		return Ok(format);
+	}
+
+	let fallback = *formats
+		.first()
+		.context("Surface has no supported formats")?;
+
+	Ok(match formats.iter().find(|format| format.describe().srgb) {
+		Some(format) => *format,
+		None => {
+			warn!(
+				"No sRGB surface format available, falling back to \
+				 {fallback:?}"
+			);
+			fallback
+		}
+	})
+}
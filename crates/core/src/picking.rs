@@ -0,0 +1,327 @@
+use crate::{
+	buffer::read_buffer,
+	depth::{DepthSettings, DEPTH_FORMAT},
+	mesh::{vertex_buffer_layout, Mesh},
+	App,
+};
+use anyhow::Result;
+use std::{borrow::Cow, num::NonZeroU32};
+use wgpu::{
+	util::DeviceExt, BindGroup, BindGroupLayout, Buffer, BufferUsages, Color,
+	CommandEncoderDescriptor, Device, Extent3d, FragmentState, ImageCopyBuffer,
+	ImageCopyTexture, ImageDataLayout, LoadOp, MultisampleState, Operations,
+	Origin3d, PipelineLayoutDescriptor, RenderPassColorAttachment,
+	RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
+	RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, Texture,
+	TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+	TextureUsages, TextureView, TextureViewDescriptor, VertexState,
+};
+
+const PICKING_SHADER: &str = r#"
+@group(0)
+@binding(0)
+var<uniform> transform: mat4x4<f32>;
+
+@group(1)
+@binding(0)
+var<uniform> id: u32;
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+	return transform * vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) u32 {
+	return id;
+}
+"#;
+
+/// Mesh indices are rendered offset by one, leaving 0 for the background
+const ID_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+/// The readback buffer holds the single id under the cursor
+const READBACK_SIZE: wgpu::BufferAddress =
+	std::mem::size_of::<u32>() as wgpu::BufferAddress;
+
+struct Targets {
+	width: u32,
+	height: u32,
+	ids: Texture,
+	ids_view: TextureView,
+	depth_view: TextureView,
+}
+
+impl Targets {
+	fn new(device: &Device, width: u32, height: u32) -> Self {
+		let texture = |label, format, usage| {
+			device.create_texture(&TextureDescriptor {
+				label: Some(label),
+				size: Extent3d {
+					width,
+					height,
+					depth_or_array_layers: 1,
+				},
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: TextureDimension::D2,
+				format,
+				usage: TextureUsages::RENDER_ATTACHMENT | usage,
+			})
+		};
+
+		let ids = texture("Picking IDs", ID_FORMAT, TextureUsages::COPY_SRC);
+		let depth =
+			texture("Picking Depth", DEPTH_FORMAT, TextureUsages::empty());
+
+		Self {
+			width,
+			height,
+			ids_view: ids.create_view(&TextureViewDescriptor::default()),
+			ids,
+			depth_view: depth.create_view(&TextureViewDescriptor::default()),
+		}
+	}
+}
+
+/// Finds the mesh under a pixel by rendering mesh indices into an integer
+/// target and reading back that one pixel
+///
+/// Meshes are transformed by the app's uniform matrix, like the scene, and
+/// depth tested with [`crate::AppSettings::depth`] so the nearest one wins.
+/// Unlike ray casting against bounds, this is exact for any geometry.
+pub struct Picker {
+	pipeline: RenderPipeline,
+	transform_bind_group: BindGroup,
+	id_bind_group_layout: BindGroupLayout,
+	depth: DepthSettings,
+	targets: Option<Targets>,
+	readback: Buffer,
+}
+
+impl Picker {
+	pub fn new(app: &impl App) -> Self {
+		let device = app.get_device();
+		let depth = app.get_settings().depth.unwrap_or_default();
+
+		let uniform_layout_entry =
+			|visibility, has_dynamic_offset| wgpu::BindGroupLayoutEntry {
+				binding: 0,
+				visibility,
+				ty: wgpu::BindingType::Buffer {
+					ty: wgpu::BufferBindingType::Uniform,
+					has_dynamic_offset,
+					min_binding_size: None,
+				},
+				count: None,
+			};
+		let transform_bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Picking Transform"),
+				entries: &[uniform_layout_entry(
+					wgpu::ShaderStages::VERTEX,
+					false,
+				)],
+			});
+		let id_bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Picking IDs"),
+				entries: &[uniform_layout_entry(
+					wgpu::ShaderStages::FRAGMENT,
+					true,
+				)],
+			});
+
+		let transform_bind_group =
+			device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("Picking Transform"),
+				layout: &transform_bind_group_layout,
+				entries: &[wgpu::BindGroupEntry {
+					binding: 0,
+					resource: app.get_uniform_buffer().as_entire_binding(),
+				}],
+			});
+
+		let pipeline_layout =
+			device.create_pipeline_layout(&PipelineLayoutDescriptor {
+				label: Some("Picking"),
+				bind_group_layouts: &[
+					&transform_bind_group_layout,
+					&id_bind_group_layout,
+				],
+				push_constant_ranges: &[],
+			});
+
+		let shader = device.create_shader_module(ShaderModuleDescriptor {
+			label: Some("Picking"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(PICKING_SHADER)),
+		});
+
+		let pipeline =
+			device.create_render_pipeline(&RenderPipelineDescriptor {
+				label: Some("Picking"),
+				layout: Some(&pipeline_layout),
+				vertex: VertexState {
+					module: &shader,
+					entry_point: "vs_main",
+					buffers: &[vertex_buffer_layout()],
+				},
+				fragment: Some(FragmentState {
+					module: &shader,
+					entry_point: "fs_main",
+					targets: &[Some(ID_FORMAT.into())],
+				}),
+				primitive: app.get_settings().primitive_state,
+				depth_stencil: Some(depth.depth_stencil_state()),
+				multisample: MultisampleState::default(),
+				multiview: None,
+			});
+
+		let readback = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Picking Readback"),
+			size: READBACK_SIZE,
+			usage: BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		Self {
+			pipeline,
+			transform_bind_group,
+			id_bind_group_layout,
+			depth,
+			targets: None,
+			readback,
+		}
+	}
+
+	/// Index into `meshes` of the mesh covering pixel `(x, y)`, counted from
+	/// the top left of the window, or `None` if only the background is there
+	///
+	/// Pass the cursor position of a click, in physical pixels. The uniform
+	/// matrix has to be uploaded already, as it is when picking between
+	/// frames.
+	pub async fn pick(
+		&mut self,
+		app: &impl App,
+		meshes: &[Mesh],
+		x: u32,
+		y: u32,
+	) -> Result<Option<usize>> {
+		let (width, height) = app.get_window_size();
+		if x >= width || y >= height {
+			return Ok(None);
+		}
+
+		let device = app.get_device();
+		let targets = match self.targets.take() {
+			Some(targets)
+				if (targets.width, targets.height) == (width, height) =>
+			{
+				targets
+			}
+			_ => Targets::new(device, width, height),
+		};
+		let targets = self.targets.insert(targets);
+
+		// Every mesh gets its ID at its own dynamic offset
+		let stride = device.limits().min_uniform_buffer_offset_alignment;
+		let mut ids = vec![0; meshes.len().max(1) * stride as usize];
+		for (i, slot) in ids.chunks_exact_mut(stride as usize).enumerate() {
+			let id = i as u32 + 1;
+			slot[..4].copy_from_slice(bytemuck::bytes_of(&id));
+		}
+		let id_buffer =
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Picking IDs"),
+				contents: &ids,
+				usage: BufferUsages::UNIFORM,
+			});
+		let id_bind_group =
+			device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("Picking IDs"),
+				layout: &self.id_bind_group_layout,
+				entries: &[wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::Buffer(
+						wgpu::BufferBinding {
+							buffer: &id_buffer,
+							offset: 0,
+							size: wgpu::BufferSize::new(
+								std::mem::size_of::<u32>() as u64,
+							),
+						},
+					),
+				}],
+			});
+
+		let mut encoder = device
+			.create_command_encoder(&CommandEncoderDescriptor { label: None });
+		{
+			let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+				label: Some("Picking"),
+				color_attachments: &[Some(RenderPassColorAttachment {
+					view: &targets.ids_view,
+					resolve_target: None,
+					ops: Operations {
+						load: LoadOp::Clear(Color::TRANSPARENT),
+						store: true,
+					},
+				})],
+				depth_stencil_attachment: Some(
+					RenderPassDepthStencilAttachment {
+						view: &targets.depth_view,
+						depth_ops: Some(Operations {
+							load: LoadOp::Clear(self.depth.clear_depth),
+							store: false,
+						}),
+						stencil_ops: None,
+					},
+				),
+			});
+			// Only the picked pixel is needed
+			rpass.set_scissor_rect(x, y, 1, 1);
+			rpass.set_pipeline(&self.pipeline);
+			rpass.set_bind_group(0, &self.transform_bind_group, &[]);
+
+			for (i, mesh) in meshes.iter().enumerate() {
+				rpass.set_bind_group(1, &id_bind_group, &[i as u32 * stride]);
+				mesh.draw(&mut rpass);
+			}
+		}
+		encoder.copy_texture_to_buffer(
+			ImageCopyTexture {
+				texture: &targets.ids,
+				mip_level: 0,
+				origin: Origin3d { x, y, z: 0 },
+				aspect: TextureAspect::All,
+			},
+			ImageCopyBuffer {
+				buffer: &self.readback,
+				layout: ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(
+						wgpu::COPY_BYTES_PER_ROW_ALIGNMENT,
+					),
+					rows_per_image: None,
+				},
+			},
+			Extent3d {
+				width: 1,
+				height: 1,
+				depth_or_array_layers: 1,
+			},
+		);
+		app.get_queue().submit(Some(encoder.finish()));
+
+		let id = read_buffer::<u32>(
+			device,
+			app.get_queue(),
+			&self.readback,
+			READBACK_SIZE,
+			1,
+		)
+		.await?[0];
+
+		Ok(id.checked_sub(1).map(|index| index as usize))
+	}
+}
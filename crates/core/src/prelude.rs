@@ -0,0 +1,17 @@
+//! The types most apps need, for glob importing
+//!
+//! ```ignore
+//! use dyadikos_core::prelude::*;
+//! ```
+
+pub use crate::{
+	background::Background, mesh::Mesh, App, AppSettings, ArcRenderPass,
+};
+pub use dyadikos_math::{
+	camera::Camera,
+	transform::{ObjectTransform, RenderTransformation},
+	Matrix4, Vector3, Vertex,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::native::NativeApp;
@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use wgpu::{
+	Buffer, BufferAddress, CommandEncoder, Device, Features, Maintain, MapMode,
+	QuerySet, QuerySetDescriptor, QueryType, Queue, QUERY_SIZE,
+};
+
+/// GPU time spent on one labelled scope
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuTiming {
+	pub label: String,
+	pub milliseconds: f32,
+}
+
+struct Queries {
+	query_set: QuerySet,
+	/// Timestamps are resolved straight into this mappable buffer
+	buffer: Buffer,
+	/// Nanoseconds per timestamp tick
+	period: f32,
+}
+
+/// Measures how long the GPU spends on scopes of a command encoder using
+/// timestamp queries
+///
+/// Without [`Features::TIMESTAMP_QUERY`] on the device every method is a
+/// no-op, so the profiler can be left in place on any adapter.
+pub struct GpuProfiler {
+	queries: Option<Queries>,
+	capacity: u32,
+	labels: Vec<String>,
+	/// Whether the last scope is still waiting for its end timestamp
+	open: bool,
+	timings: Vec<GpuTiming>,
+}
+
+impl GpuProfiler {
+	/// Create a profiler that can time up to `capacity` scopes per frame
+	pub fn new(device: &Device, queue: &Queue, capacity: u32) -> Self {
+		let queries = device
+			.features()
+			.contains(Features::TIMESTAMP_QUERY)
+			.then(|| {
+				let size = (capacity * 2) as BufferAddress * QUERY_SIZE as u64;
+
+				Queries {
+					query_set: device.create_query_set(&QuerySetDescriptor {
+						label: Some("GPU Profiler"),
+						ty: QueryType::Timestamp,
+						count: capacity * 2,
+					}),
+					buffer: device.create_buffer(&wgpu::BufferDescriptor {
+						label: Some("GPU Profiler"),
+						size,
+						usage: wgpu::BufferUsages::MAP_READ
+							| wgpu::BufferUsages::COPY_DST,
+						mapped_at_creation: false,
+					}),
+					period: queue.get_timestamp_period(),
+				}
+			});
+
+		Self {
+			queries,
+			capacity,
+			labels: Vec::new(),
+			open: false,
+			timings: Vec::new(),
+		}
+	}
+
+	/// Whether the device supports timestamp queries
+	pub fn is_supported(&self) -> bool {
+		self.queries.is_some()
+	}
+
+	/// Start timing the commands recorded into `encoder` from here on
+	///
+	/// Scopes can't be nested, and ones past the capacity for this frame are
+	/// left untimed.
+	pub fn begin(&mut self, label: &str, encoder: &mut CommandEncoder) {
+		let index = self.labels.len() as u32;
+		if let Some(queries) = &self.queries {
+			if index < self.capacity {
+				encoder.write_timestamp(&queries.query_set, index * 2);
+				self.labels.push(label.to_string());
+				self.open = true;
+			}
+		}
+	}
+
+	/// Stop timing the scope started by [`GpuProfiler::begin`]
+	pub fn end(&mut self, encoder: &mut CommandEncoder) {
+		if let Some(queries) = &self.queries {
+			if std::mem::take(&mut self.open) {
+				let index = self.labels.len() as u32 - 1;
+				encoder.write_timestamp(&queries.query_set, index * 2 + 1);
+			}
+		}
+	}
+
+	/// Copy this frame's timestamps out of the query set, after every scope
+	/// has been recorded
+	pub fn resolve(&self, encoder: &mut CommandEncoder) {
+		let queries = match &self.queries {
+			Some(queries) if !self.labels.is_empty() => queries,
+			_ => return,
+		};
+
+		let count = self.labels.len() as u32 * 2;
+		encoder.resolve_query_set(
+			&queries.query_set,
+			0..count,
+			&queries.buffer,
+			0,
+		);
+	}
+
+	/// Wait for the submitted frame and read back its timings
+	///
+	/// This stalls until the GPU has finished the frame.
+	pub fn collect(&mut self, device: &Device) -> Result<()> {
+		let labels = std::mem::take(&mut self.labels);
+		let queries = match &self.queries {
+			Some(queries) => queries,
+			None => return Ok(()),
+		};
+		if labels.is_empty() {
+			self.timings.clear();
+			return Ok(());
+		}
+
+		let size = labels.len() as BufferAddress * 2 * QUERY_SIZE as u64;
+		let slice = queries.buffer.slice(..size);
+		let (sender, receiver) = std::sync::mpsc::channel();
+		slice.map_async(MapMode::Read, move |result| {
+			let _ = sender.send(result);
+		});
+		device.poll(Maintain::Wait);
+		receiver
+			.recv()
+			.context("Buffer mapping was never completed")?
+			.context("Failed to map the timestamp buffer")?;
+
+		let timestamps: Vec<u64> =
+			bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+		queries.buffer.unmap();
+
+		self.timings = labels
+			.into_iter()
+			.zip(timestamps.chunks_exact(2))
+			.map(|(label, pair)| GpuTiming {
+				label,
+				milliseconds: pair[1].wrapping_sub(pair[0]) as f32
+					* queries.period
+					/ 1_000_000.0,
+			})
+			.collect();
+
+		Ok(())
+	}
+
+	/// Timings of the last collected frame, in the order they were recorded
+	pub fn timings(&self) -> &[GpuTiming] {
+		&self.timings
+	}
+}
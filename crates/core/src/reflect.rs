@@ -0,0 +1,411 @@
+use anyhow::{bail, Context, Result};
+use naga::{
+	valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+	AddressSpace, Binding, ImageClass, ImageDimension, Module, ScalarKind,
+	StorageAccess, TypeInner,
+};
+use wgpu::{
+	BindGroupLayoutEntry, BindingType, BufferBindingType, BufferSize, Limits,
+	SamplerBindingType, ShaderStages, TextureSampleType, TextureViewDimension,
+	VertexBufferLayout, VertexFormat,
+};
+
+/// A resource binding declared by a shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindingInfo {
+	pub group: u32,
+	pub binding: u32,
+	/// Size in bytes of the bound buffer, if the binding is a buffer
+	pub size: Option<u64>,
+	pub ty: BindingType,
+	/// Shader stages whose entry points use the binding
+	pub visibility: ShaderStages,
+}
+
+impl BindingInfo {
+	/// Declare a storage buffer binding of `size` bytes
+	pub fn storage_buffer(
+		group: u32,
+		binding: u32,
+		size: u64,
+		read_only: bool,
+		visibility: ShaderStages,
+	) -> Self {
+		Self {
+			group,
+			binding,
+			size: Some(size),
+			ty: BindingType::Buffer {
+				ty: BufferBindingType::Storage { read_only },
+				has_dynamic_offset: false,
+				min_binding_size: BufferSize::new(size),
+			},
+			visibility,
+		}
+	}
+
+	pub fn layout_entry(&self) -> BindGroupLayoutEntry {
+		BindGroupLayoutEntry {
+			binding: self.binding,
+			visibility: self.visibility,
+			ty: self.ty,
+			count: None,
+		}
+	}
+}
+
+/// A vertex attribute read by a vertex shader entry point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexInput {
+	pub name: Option<String>,
+	pub location: u32,
+	pub kind: ScalarKind,
+	pub components: u32,
+}
+
+impl std::fmt::Display for VertexInput {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let scalar = match self.kind {
+			ScalarKind::Sint => "i32",
+			ScalarKind::Uint => "u32",
+			ScalarKind::Bool => "bool",
+			ScalarKind::Float => "f32",
+		};
+
+		match self.components {
+			1 => write!(f, "{scalar}"),
+			n => write!(f, "vec{n}<{scalar}>"),
+		}
+	}
+}
+
+/// A shader module parsed and validated by naga
+pub struct ShaderReflection {
+	pub module: Module,
+	pub info: ModuleInfo,
+}
+
+impl ShaderReflection {
+	pub fn from_wgsl(source: &str) -> Result<Self> {
+		let module = naga::front::wgsl::parse_str(source)
+			.map_err(|error| error.emit_to_string(source))
+			.map_err(anyhow::Error::msg)
+			.context("Failed to parse WGSL shader")?;
+
+		Self::from_module(module)
+	}
+
+	pub fn from_spirv(words: &[u32]) -> Result<Self> {
+		let module = naga::front::spv::parse_u8_slice(
+			bytemuck::cast_slice(words),
+			&Default::default(),
+		)
+		.context("Failed to parse SPIR-V shader")?;
+
+		Self::from_module(module)
+	}
+
+	pub fn from_module(module: Module) -> Result<Self> {
+		let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+			.validate(&module)
+			.context("Failed to validate shader")?;
+
+		Ok(Self { module, info })
+	}
+
+	/// List every resource binding, ordered by group and binding index
+	pub fn bindings(&self) -> Result<Vec<BindingInfo>> {
+		let mut bindings = Vec::new();
+
+		for (handle, global) in self.module.global_variables.iter() {
+			let Some(resource) = &global.binding else {
+				continue;
+			};
+
+			let mut visibility = ShaderStages::NONE;
+			for (index, entry_point) in
+				self.module.entry_points.iter().enumerate()
+			{
+				if !self.info.get_entry_point(index)[handle].is_empty() {
+					visibility |= match entry_point.stage {
+						naga::ShaderStage::Vertex => ShaderStages::VERTEX,
+						naga::ShaderStage::Fragment => ShaderStages::FRAGMENT,
+						naga::ShaderStage::Compute => ShaderStages::COMPUTE,
+					};
+				}
+			}
+
+			let inner = &self.module.types[global.ty].inner;
+			let size = match global.space {
+				AddressSpace::Uniform | AddressSpace::Storage { .. } => {
+					Some(inner.size(&self.module.constants) as u64)
+				}
+				_ => None,
+			};
+			let ty =
+				binding_type(global.space, inner, size).with_context(|| {
+					format!(
+						"Unsupported binding {:?} at group {}, binding {}",
+						global.name, resource.group, resource.binding
+					)
+				})?;
+
+			bindings.push(BindingInfo {
+				group: resource.group,
+				binding: resource.binding,
+				size,
+				ty,
+				visibility,
+			});
+		}
+
+		bindings.sort_by_key(|info| (info.group, info.binding));
+
+		Ok(bindings)
+	}
+
+	/// List the vertex attributes read by a vertex entry point
+	pub fn vertex_inputs(&self, entry_point: &str) -> Result<Vec<VertexInput>> {
+		let entry_point = self
+			.module
+			.entry_points
+			.iter()
+			.find(|ep| {
+				ep.stage == naga::ShaderStage::Vertex && ep.name == entry_point
+			})
+			.with_context(|| {
+				format!("Shader has no vertex entry point `{entry_point}`")
+			})?;
+
+		let mut inputs = Vec::new();
+		for argument in &entry_point.function.arguments {
+			match (&argument.binding, &self.module.types[argument.ty].inner) {
+				(Some(binding), inner) => {
+					inputs.extend(vertex_input(&argument.name, binding, inner))
+				}
+				(None, TypeInner::Struct { members, .. }) => {
+					for member in members {
+						let inner = &self.module.types[member.ty].inner;
+						if let Some(binding) = &member.binding {
+							inputs.extend(vertex_input(
+								&member.name,
+								binding,
+								inner,
+							));
+						}
+					}
+				}
+				_ => {}
+			}
+		}
+
+		inputs.sort_by_key(|input| input.location);
+
+		Ok(inputs)
+	}
+
+	/// Check that the vertex buffer layouts provide every attribute the
+	/// vertex entry point reads, with a matching scalar type
+	pub fn validate_vertex_layout(
+		&self,
+		entry_point: &str,
+		layouts: &[VertexBufferLayout],
+	) -> Result<()> {
+		for input in self.vertex_inputs(entry_point)? {
+			let name = input.name.as_deref().unwrap_or("<unnamed>");
+			let attribute = layouts
+				.iter()
+				.flat_map(|layout| layout.attributes)
+				.find(|attribute| attribute.shader_location == input.location)
+				.with_context(|| {
+					format!(
+						"Vertex shader input `{name}` ({input}) at location {} \
+						 has no matching attribute in the vertex buffer layout",
+						input.location
+					)
+				})?;
+
+			if format_kind(attribute.format) != input.kind {
+				bail!(
+					"Vertex shader input `{name}` at location {} is {input}, \
+					 but the vertex buffer layout provides {:?}",
+					input.location,
+					attribute.format
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn vertex_input(
+	name: &Option<String>,
+	binding: &Binding,
+	inner: &TypeInner,
+) -> Option<VertexInput> {
+	let Binding::Location { location, .. } = binding else {
+		return None;
+	};
+	let (kind, components) = match *inner {
+		TypeInner::Scalar { kind, .. } => (kind, 1),
+		TypeInner::Vector { kind, size, .. } => (kind, size as u32),
+		_ => return None,
+	};
+
+	Some(VertexInput {
+		name: name.clone(),
+		location: *location,
+		kind,
+		components,
+	})
+}
+
+fn format_kind(format: VertexFormat) -> ScalarKind {
+	use VertexFormat::*;
+
+	match format {
+		Uint8x2 | Uint8x4 | Uint16x2 | Uint16x4 | Uint32 | Uint32x2
+		| Uint32x3 | Uint32x4 => ScalarKind::Uint,
+		Sint8x2 | Sint8x4 | Sint16x2 | Sint16x4 | Sint32 | Sint32x2
+		| Sint32x3 | Sint32x4 => ScalarKind::Sint,
+		_ => ScalarKind::Float,
+	}
+}
+
+/// Check that the buffer bindings fit within the device limits
+pub fn validate_limits(
+	bindings: &[BindingInfo],
+	limits: &Limits,
+) -> Result<()> {
+	for stage in [
+		ShaderStages::VERTEX,
+		ShaderStages::FRAGMENT,
+		ShaderStages::COMPUTE,
+	] {
+		let storage_buffers = bindings
+			.iter()
+			.filter(|info| info.visibility.contains(stage))
+			.filter(|info| {
+				matches!(
+					info.ty,
+					BindingType::Buffer {
+						ty: BufferBindingType::Storage { .. },
+						..
+					}
+				)
+			})
+			.count() as u32;
+
+		if storage_buffers > limits.max_storage_buffers_per_shader_stage {
+			bail!(
+				"Shader uses {storage_buffers} storage buffers in the {stage:?} \
+				 stage, but the device allows {}",
+				limits.max_storage_buffers_per_shader_stage
+			);
+		}
+	}
+
+	for info in bindings {
+		let (max_size, kind) = match info.ty {
+			BindingType::Buffer {
+				ty: BufferBindingType::Uniform,
+				..
+			} => (limits.max_uniform_buffer_binding_size, "uniform"),
+			BindingType::Buffer {
+				ty: BufferBindingType::Storage { .. },
+				..
+			} => (limits.max_storage_buffer_binding_size, "storage"),
+			_ => continue,
+		};
+
+		if let Some(size) = info.size.filter(|size| *size > max_size as u64) {
+			bail!(
+				"The {kind} buffer at group {}, binding {} needs {size} bytes, \
+				 but the device allows {max_size}",
+				info.group,
+				info.binding
+			);
+		}
+	}
+
+	Ok(())
+}
+
+/// Reflect the resource bindings of a WGSL shader
+pub fn reflect_wgsl(source: &str) -> Result<Vec<BindingInfo>> {
+	ShaderReflection::from_wgsl(source)?.bindings()
+}
+
+/// Reflect the resource bindings of a SPIR-V shader
+pub fn reflect_spirv(words: &[u32]) -> Result<Vec<BindingInfo>> {
+	ShaderReflection::from_spirv(words)?.bindings()
+}
+
+fn binding_type(
+	space: AddressSpace,
+	inner: &TypeInner,
+	size: Option<u64>,
+) -> Result<BindingType> {
+	Ok(match (space, inner) {
+		(AddressSpace::Uniform, _) => BindingType::Buffer {
+			ty: BufferBindingType::Uniform,
+			has_dynamic_offset: false,
+			min_binding_size: size.and_then(BufferSize::new),
+		},
+		(AddressSpace::Storage { access }, _) => BindingType::Buffer {
+			ty: BufferBindingType::Storage {
+				read_only: !access.contains(StorageAccess::STORE),
+			},
+			has_dynamic_offset: false,
+			min_binding_size: size.and_then(BufferSize::new),
+		},
+		(_, TypeInner::Sampler { comparison: true }) => {
+			BindingType::Sampler(SamplerBindingType::Comparison)
+		}
+		(_, TypeInner::Sampler { comparison: false }) => {
+			BindingType::Sampler(SamplerBindingType::Filtering)
+		}
+		(
+			_,
+			TypeInner::Image {
+				dim,
+				arrayed,
+				class,
+			},
+		) => {
+			let view_dimension = match (dim, arrayed) {
+				(ImageDimension::D1, _) => TextureViewDimension::D1,
+				(ImageDimension::D2, false) => TextureViewDimension::D2,
+				(ImageDimension::D2, true) => TextureViewDimension::D2Array,
+				(ImageDimension::D3, _) => TextureViewDimension::D3,
+				(ImageDimension::Cube, false) => TextureViewDimension::Cube,
+				(ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+			};
+			let (sample_type, multisampled) = match class {
+				ImageClass::Sampled { kind, multi } => {
+					let sample_type = match kind {
+						ScalarKind::Sint => TextureSampleType::Sint,
+						ScalarKind::Uint => TextureSampleType::Uint,
+						_ => TextureSampleType::Float { filterable: true },
+					};
+
+					(sample_type, *multi)
+				}
+				ImageClass::Depth { multi } => {
+					(TextureSampleType::Depth, *multi)
+				}
+				ImageClass::Storage { .. } => {
+					bail!("Storage textures are not supported")
+				}
+			};
+
+			BindingType::Texture {
+				sample_type,
+				view_dimension,
+				multisampled,
+			}
+		}
+		_ => bail!("Unknown resource type {inner:?}"),
+	})
+}
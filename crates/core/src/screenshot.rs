@@ -0,0 +1,126 @@
+use crate::buffer::read_buffer_blocking;
+use anyhow::{bail, Context, Result};
+use std::{
+	num::NonZeroU32,
+	path::PathBuf,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use wgpu::{
+	Buffer, BufferUsages, CommandEncoder, Device, Extent3d, ImageCopyBuffer,
+	ImageCopyTexture, ImageDataLayout, Origin3d, Queue, Texture, TextureAspect,
+	TextureFormat,
+};
+
+/// A frame copied out of the swapchain, waiting to be read back and saved
+pub(crate) struct Screenshot {
+	buffer: Buffer,
+	width: u32,
+	height: u32,
+	padded_bytes_per_row: u32,
+	/// Whether the texels are stored as BGRA and have to be swizzled
+	bgra: bool,
+}
+
+impl Screenshot {
+	/// Record a copy of `texture`, which needs [`wgpu::TextureUsages::COPY_SRC`]
+	///
+	/// Only 8-bit RGBA and BGRA formats are supported. sRGB formats store the
+	/// encoded values, so both they and plain unorm formats hold exactly the
+	/// bytes the display shows, and are saved without any conversion.
+	pub(crate) fn encode(
+		device: &Device,
+		encoder: &mut CommandEncoder,
+		texture: &Texture,
+		format: TextureFormat,
+		width: u32,
+		height: u32,
+	) -> Result<Self> {
+		let bgra = match format {
+			TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+			TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+			format => {
+				bail!("Screenshots of {format:?} surfaces aren't supported")
+			}
+		};
+
+		// Rows of a texture copy have to start at aligned offsets
+		let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+		let padded_bytes_per_row = (width * 4).div_ceil(alignment) * alignment;
+		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Screenshot"),
+			size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+			usage: BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+
+		encoder.copy_texture_to_buffer(
+			ImageCopyTexture {
+				texture,
+				mip_level: 0,
+				origin: Origin3d::ZERO,
+				aspect: TextureAspect::All,
+			},
+			ImageCopyBuffer {
+				buffer: &buffer,
+				layout: ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+					rows_per_image: None,
+				},
+			},
+			Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+		);
+
+		Ok(Self {
+			buffer,
+			width,
+			height,
+			padded_bytes_per_row,
+			bgra,
+		})
+	}
+
+	/// Wait for the copy and write it to a timestamped PNG in the working
+	/// directory, returning its path
+	pub(crate) fn save(
+		self,
+		device: &Device,
+		queue: &Queue,
+	) -> Result<PathBuf> {
+		let data = read_buffer_blocking::<u8>(
+			device,
+			queue,
+			&self.buffer,
+			(self.padded_bytes_per_row * self.height) as wgpu::BufferAddress,
+			(self.padded_bytes_per_row * self.height) as usize,
+		)?;
+
+		let mut pixels =
+			Vec::with_capacity((self.width * self.height * 4) as usize);
+		for row in data.chunks_exact(self.padded_bytes_per_row as usize) {
+			for texel in row[..(self.width * 4) as usize].chunks_exact(4) {
+				if self.bgra {
+					pixels.extend([texel[2], texel[1], texel[0], texel[3]]);
+				} else {
+					pixels.extend_from_slice(texel);
+				}
+			}
+		}
+
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis();
+		let path = PathBuf::from(format!("screenshot-{timestamp}.png"));
+		image::RgbaImage::from_raw(self.width, self.height, pixels)
+			.context("Screenshot data doesn't match its size")?
+			.save(&path)
+			.with_context(|| format!("Failed to save screenshot {path:?}"))?;
+
+		Ok(path)
+	}
+}
@@ -0,0 +1,217 @@
+use crate::App;
+use anyhow::{bail, Result};
+use std::{
+	num::{NonZeroU32, NonZeroU8},
+	sync::Arc,
+};
+use wgpu::{
+	AddressMode, Device, Extent3d, FilterMode, ImageCopyTexture,
+	ImageDataLayout, Origin3d, Sampler, SamplerDescriptor, TextureAspect,
+	TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+	TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+/// Decoded pixels of an image, tightly packed row by row
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+	pub width: u32,
+	pub height: u32,
+	/// Must be uncompressed
+	pub format: TextureFormat,
+	pub data: &'a [u8],
+}
+
+impl Image<'_> {
+	fn bytes_per_row(&self) -> Result<u32> {
+		let info = self.format.describe();
+		if info.block_dimensions != (1, 1) {
+			bail!("compressed format {:?} is not supported", self.format);
+		}
+
+		let bytes_per_row = self.width * info.block_size as u32;
+		let expected = (bytes_per_row * self.height) as usize;
+		if self.data.len() != expected {
+			bail!(
+				"{}x{} {:?} image should hold {expected} bytes, got {}",
+				self.width,
+				self.height,
+				self.format,
+				self.data.len()
+			);
+		}
+
+		Ok(bytes_per_row)
+	}
+}
+
+/// A GPU texture along with the view shaders bind it through
+#[derive(Clone)]
+pub struct Texture {
+	texture: Arc<wgpu::Texture>,
+	view: Arc<TextureView>,
+}
+
+impl Texture {
+	/// Assemble six square faces into a cube texture with a cube view
+	///
+	/// The faces are ordered +X, -X, +Y, -Y, +Z, -Z, and must all share the
+	/// same size and format.
+	pub fn cubemap_from_images(
+		app: &impl App,
+		faces: [Image; 6],
+	) -> Result<Self> {
+		let first = faces[0];
+		if first.width != first.height {
+			bail!(
+				"cubemap faces must be square, got {}x{}",
+				first.width,
+				first.height
+			);
+		}
+		for (i, face) in faces.iter().enumerate() {
+			if (face.width, face.height, face.format)
+				!= (first.width, first.height, first.format)
+			{
+				bail!(
+					"cubemap face {i} is {}x{} {:?}, expected {}x{} {:?}",
+					face.width,
+					face.height,
+					face.format,
+					first.width,
+					first.height,
+					first.format
+				);
+			}
+			face.bytes_per_row()?;
+		}
+		let bytes_per_row = first.bytes_per_row()?;
+
+		let size = Extent3d {
+			width: first.width,
+			height: first.height,
+			depth_or_array_layers: 6,
+		};
+		let texture = app.get_device().create_texture(&TextureDescriptor {
+			label: Some("Cubemap"),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: first.format,
+			usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+		});
+
+		for (layer, face) in faces.iter().enumerate() {
+			app.get_queue().write_texture(
+				ImageCopyTexture {
+					texture: &texture,
+					mip_level: 0,
+					origin: Origin3d {
+						x: 0,
+						y: 0,
+						z: layer as u32,
+					},
+					aspect: TextureAspect::All,
+				},
+				face.data,
+				ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(bytes_per_row),
+					rows_per_image: None,
+				},
+				Extent3d {
+					depth_or_array_layers: 1,
+					..size
+				},
+			);
+		}
+
+		let view = texture.create_view(&TextureViewDescriptor {
+			label: Some("Cubemap"),
+			dimension: Some(TextureViewDimension::Cube),
+			..Default::default()
+		});
+
+		Ok(Self {
+			texture: Arc::new(texture),
+			view: Arc::new(view),
+		})
+	}
+
+	pub fn texture(&self) -> &wgpu::Texture {
+		&self.texture
+	}
+
+	pub fn view(&self) -> &TextureView {
+		&self.view
+	}
+}
+
+/// How a texture is filtered and wrapped when sampled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerSettings {
+	/// Nearest keeps pixel art crisp, linear smooths between texels
+	pub filter: FilterMode,
+	/// Applied along every axis
+	pub address_mode: AddressMode,
+	/// Number of anisotropic samples, one of 1, 2, 4, 8 or 16
+	///
+	/// Anything above 1 sharpens textures seen at grazing angles, like
+	/// terrain, and needs linear filtering. Adapters without
+	/// [`wgpu::DownlevelFlags::ANISOTROPIC_FILTERING`] silently ignore it.
+	pub anisotropy: u8,
+}
+
+impl Default for SamplerSettings {
+	fn default() -> Self {
+		Self {
+			filter: FilterMode::Linear,
+			address_mode: AddressMode::ClampToEdge,
+			anisotropy: 1,
+		}
+	}
+}
+
+impl SamplerSettings {
+	pub fn nearest() -> Self {
+		Self {
+			filter: FilterMode::Nearest,
+			..Self::default()
+		}
+	}
+
+	pub fn with_address_mode(mut self, address_mode: AddressMode) -> Self {
+		self.address_mode = address_mode;
+		self
+	}
+
+	pub fn with_anisotropy(mut self, anisotropy: u8) -> Self {
+		self.anisotropy = anisotropy;
+		self
+	}
+
+	pub fn create_sampler(&self, device: &Device) -> Result<Sampler> {
+		if !matches!(self.anisotropy, 1 | 2 | 4 | 8 | 16) {
+			bail!(
+				"anisotropy must be 1, 2, 4, 8 or 16, got {}",
+				self.anisotropy
+			);
+		}
+		if self.anisotropy > 1 && self.filter != FilterMode::Linear {
+			bail!("anisotropic filtering needs linear filtering");
+		}
+
+		Ok(device.create_sampler(&SamplerDescriptor {
+			label: None,
+			address_mode_u: self.address_mode,
+			address_mode_v: self.address_mode,
+			address_mode_w: self.address_mode,
+			mag_filter: self.filter,
+			min_filter: self.filter,
+			mipmap_filter: self.filter,
+			anisotropy_clamp: NonZeroU8::new(self.anisotropy)
+				.filter(|anisotropy| anisotropy.get() > 1),
+			..Default::default()
+		}))
+	}
+}
@@ -0,0 +1,147 @@
+use crate::fullscreen::fullscreen_pipeline;
+use std::sync::Arc;
+use wgpu::{
+	BindGroup, BindGroupLayout, Device, Extent3d, MultisampleState, RenderPass,
+	RenderPipeline, TextureDescriptor, TextureDimension, TextureFormat,
+	TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+/// Format of the offscreen target the scene is rendered into with HDR on
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+const TONEMAP_SHADER: &str = r#"
+@group(0)
+@binding(0)
+var scene: texture_2d<f32>;
+
+// Narkowicz's fit of the ACES filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+	let a = 2.51;
+	let b = 0.03;
+	let c = 2.43;
+	let d = 0.59;
+	let e = 0.14;
+	return clamp(
+		(x * (a * x + b)) / (x * (c * x + d) + e),
+		vec3<f32>(0.0),
+		vec3<f32>(1.0),
+	);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+	let color = textureLoad(scene, vec2<i32>(vertex.position.xy), 0);
+	return vec4<f32>(aces(color.rgb), 1.0);
+}
+"#;
+
+/// Offscreen HDR target and the full-screen pass tonemapping it onto the
+/// swapchain
+///
+/// The tonemapped output is linear, so the swapchain should be sRGB.
+#[derive(Clone)]
+pub struct TonemapPass {
+	pipeline: Arc<RenderPipeline>,
+	bind_group_layout: Arc<BindGroupLayout>,
+	view: Arc<TextureView>,
+	bind_group: Arc<BindGroup>,
+}
+
+impl TonemapPass {
+	pub fn new(
+		device: &Device,
+		format: TextureFormat,
+		width: u32,
+		height: u32,
+	) -> Self {
+		let bind_group_layout =
+			device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("Tonemap"),
+				entries: &[wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float {
+							filterable: false,
+						},
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				}],
+			});
+
+		let pipeline = fullscreen_pipeline(
+			device,
+			"Tonemap",
+			TONEMAP_SHADER,
+			&bind_group_layout,
+			&[Some(format.into())],
+			None,
+			MultisampleState::default(),
+		);
+		let (view, bind_group) =
+			create_target(device, &bind_group_layout, width, height);
+
+		Self {
+			pipeline: Arc::new(pipeline),
+			bind_group_layout: Arc::new(bind_group_layout),
+			view: Arc::new(view),
+			bind_group: Arc::new(bind_group),
+		}
+	}
+
+	/// Recreate the HDR target to match a new surface size
+	pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+		let (view, bind_group) =
+			create_target(device, &self.bind_group_layout, width, height);
+
+		self.view = Arc::new(view);
+		self.bind_group = Arc::new(bind_group);
+	}
+
+	/// The HDR target the scene should be rendered into
+	pub fn view(&self) -> &TextureView {
+		&self.view
+	}
+
+	pub fn draw<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+		rpass.set_pipeline(&self.pipeline);
+		rpass.set_bind_group(0, &self.bind_group, &[]);
+		rpass.draw(0..3, 0..1);
+	}
+}
+
+fn create_target(
+	device: &Device,
+	bind_group_layout: &BindGroupLayout,
+	width: u32,
+	height: u32,
+) -> (TextureView, BindGroup) {
+	let texture = device.create_texture(&TextureDescriptor {
+		label: Some("HDR Target"),
+		size: Extent3d {
+			width: width.max(1),
+			height: height.max(1),
+			depth_or_array_layers: 1,
+		},
+		mip_level_count: 1,
+		sample_count: 1,
+		dimension: TextureDimension::D2,
+		format: HDR_FORMAT,
+		usage: TextureUsages::RENDER_ATTACHMENT
+			| TextureUsages::TEXTURE_BINDING,
+	});
+	let view = texture.create_view(&TextureViewDescriptor::default());
+
+	let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+		label: Some("Tonemap"),
+		layout: bind_group_layout,
+		entries: &[wgpu::BindGroupEntry {
+			binding: 0,
+			resource: wgpu::BindingResource::TextureView(&view),
+		}],
+	});
+
+	(view, bind_group)
+}
@@ -0,0 +1,86 @@
+use crate::Vector3;
+
+/// Axis-aligned bounding box
+#[derive(PartialEq, Copy, Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb {
+	pub min: Vector3,
+	pub max: Vector3,
+}
+
+impl Default for Aabb {
+	fn default() -> Self {
+		Self::EMPTY
+	}
+}
+
+impl Aabb {
+	/// Box containing nothing, with its minimum above its maximum, which
+	/// [`Aabb::union`] treats as an identity. This is also the default box.
+	pub const EMPTY: Self = Self {
+		min: [f32::INFINITY; 3],
+		max: [f32::NEG_INFINITY; 3],
+	};
+
+	pub fn new(min: Vector3, max: Vector3) -> Self {
+		Self { min, max }
+	}
+
+	/// Whether the box contains no points, like [`Aabb::EMPTY`]
+	pub fn is_empty(&self) -> bool {
+		(0..3).any(|i| self.min[i] > self.max[i])
+	}
+
+	/// Smallest box containing every point, or `None` if there are none
+	pub fn from_points(
+		points: impl IntoIterator<Item = Vector3>,
+	) -> Option<Self> {
+		points.into_iter().fold(None, |aabb, point| {
+			Some(match aabb {
+				None => Self::new(point, point),
+				Some(Self { min, max }) => Self::new(
+					[0, 1, 2].map(|i| min[i].min(point[i])),
+					[0, 1, 2].map(|i| max[i].max(point[i])),
+				),
+			})
+		})
+	}
+
+	/// Smallest box containing both boxes
+	pub fn union(&self, other: &Aabb) -> Self {
+		Self::new(
+			[0, 1, 2].map(|i| self.min[i].min(other.min[i])),
+			[0, 1, 2].map(|i| self.max[i].max(other.max[i])),
+		)
+	}
+
+	/// Midpoint of the box, which is meaningless for empty boxes
+	pub fn center(&self) -> Vector3 {
+		[0, 1, 2].map(|i| (self.min[i] + self.max[i]) / 2.0)
+	}
+
+	/// Half the size of the box along each axis, so it spans `center -
+	/// extents` to `center + extents`
+	pub fn extents(&self) -> Vector3 {
+		[0, 1, 2].map(|i| (self.max[i] - self.min[i]) / 2.0)
+	}
+
+	/// Whether the point is inside the box or on its surface
+	pub fn contains(&self, point: Vector3) -> bool {
+		(0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+	}
+
+	/// The eight corners, where bit `n` of the index picks the maximum along
+	/// axis `n`
+	pub fn corners(&self) -> [Vector3; 8] {
+		std::array::from_fn(|i| {
+			[0, 1, 2].map(|axis| {
+				if i >> axis & 1 == 0 {
+					self.min[axis]
+				} else {
+					self.max[axis]
+				}
+			})
+		})
+	}
+}
@@ -0,0 +1,76 @@
+use crate::{transform::RenderTransformation, Matrix4};
+use glam::{EulerRot, Quat, Vec3};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3};
+
+/// Furthest [`Camera::pitch`] can turn up or down, just short of straight
+/// up or down, where the view would flip over
+pub const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// A perspective camera turned by yaw and pitch, with +Y up
+///
+/// At zero yaw and pitch it looks down -Z.
+#[derive(PartialEq, Copy, Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Camera {
+	pub position: Vec3,
+	/// Turn to the left around +Y, in radians
+	pub yaw: f32,
+	/// Turn upwards, in radians, kept within [`MAX_PITCH`] by
+	/// [`Camera::rotate`]
+	pub pitch: f32,
+	/// Vertical field of view, in radians
+	pub fov_y: f32,
+	/// Width divided by height of the viewport
+	pub aspect: f32,
+	pub near: f32,
+	pub far: f32,
+}
+
+impl Default for Camera {
+	fn default() -> Self {
+		Self {
+			position: Vec3::new(0.0, 0.0, 1.0),
+			yaw: 0.0,
+			pitch: 0.0,
+			fov_y: FRAC_PI_3,
+			aspect: 1.0,
+			near: 0.1,
+			far: 100.0,
+		}
+	}
+}
+
+impl Camera {
+	pub fn rotation(&self) -> Quat {
+		Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0)
+	}
+
+	/// Direction the camera looks in
+	pub fn forward(&self) -> Vec3 {
+		self.rotation() * Vec3::NEG_Z
+	}
+
+	/// Direction to the right of the view
+	pub fn right(&self) -> Vec3 {
+		self.rotation() * Vec3::X
+	}
+
+	/// Turn the camera by the given angles in radians, clamping the pitch
+	/// so it can't flip over the top
+	pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+		self.yaw += yaw;
+		self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+	}
+
+	/// View and projection of the camera, with an identity model matrix
+	pub fn transformation(&self) -> RenderTransformation {
+		RenderTransformation::default()
+			.perspective(self.fov_y, self.aspect, self.near, self.far)
+			.look_at(self.position, self.position + self.forward(), Vec3::Y)
+	}
+
+	/// The combined view-projection matrix, ready to upload
+	pub fn view_proj(&self) -> Matrix4 {
+		self.transformation().mvp()
+	}
+}
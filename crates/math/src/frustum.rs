@@ -0,0 +1,83 @@
+use crate::{bounds::Aabb, Matrix4};
+use glam::{Mat4, Vec3, Vec4};
+
+/// The six planes bounding what a camera can see
+///
+/// Each plane is stored as `(normal, distance)` with the normal pointing
+/// into the frustum.
+#[derive(PartialEq, Copy, Debug, Clone)]
+pub struct Frustum {
+	planes: [Vec4; 6],
+}
+
+impl Frustum {
+	/// Extract the planes of a view-projection matrix, which is expected to
+	/// map depth to `0..1` like wgpu does
+	pub fn from_matrix(view_proj: &Matrix4) -> Self {
+		let matrix = Mat4::from_cols_array(view_proj);
+		let [x, y, z, w] = [0, 1, 2, 3].map(|i| matrix.row(i));
+
+		Self {
+			planes: [w + x, w - x, w + y, w - y, z, w - z],
+		}
+	}
+
+	/// Whether any part of the box may be visible
+	///
+	/// This is conservative: boxes near a corner of the frustum can pass
+	/// without actually being inside it.
+	pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+		let (min, max) = (Vec3::from(aabb.min), Vec3::from(aabb.max));
+
+		self.planes.iter().all(|plane| {
+			// The corner furthest along the plane normal
+			let normal = plane.truncate();
+			let corner = Vec3::select(normal.cmpge(Vec3::ZERO), max, min);
+			normal.dot(corner) + plane.w >= 0.0
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::camera::Camera;
+	use std::f32::consts::PI;
+
+	/// Box around the origin, in front of the default camera
+	fn target() -> Aabb {
+		Aabb::new([-0.25; 3], [0.25; 3])
+	}
+
+	#[test]
+	fn camera_facing_box_sees_it() {
+		let frustum = Frustum::from_matrix(&Camera::default().view_proj());
+
+		assert!(frustum.intersects_aabb(&target()));
+	}
+
+	#[test]
+	fn camera_looking_away_culls_box() {
+		let camera = Camera {
+			yaw: PI,
+			..Default::default()
+		};
+		let frustum = Frustum::from_matrix(&camera.view_proj());
+
+		assert!(!frustum.intersects_aabb(&target()));
+	}
+
+	#[test]
+	fn box_before_near_plane_is_culled() {
+		let camera = Camera {
+			near: 0.5,
+			..Default::default()
+		};
+		let frustum = Frustum::from_matrix(&camera.view_proj());
+
+		// Between the camera at z = 1 and its near plane at z = 0.5, which
+		// a -1..1 depth range would wrongly keep
+		let close = Aabb::new([-0.01, -0.01, 0.7], [0.01, 0.01, 0.8]);
+		assert!(!frustum.intersects_aabb(&close));
+	}
+}
@@ -1,5 +1,14 @@
+//! Math types shared by the renderer, backed by [`glam`]
+//!
+//! GPU-facing data uses plain arrays, which are [`Pod`] and can be uploaded
+//! with `bytemuck` directly: [`Matrix4`] is column-major like
+//! [`glam::Mat4::to_cols_array`], and [`Vector3`] converts to and from
+//! [`glam::Vec3`] with `From`. Transforms in [`transform`] store glam types
+//! and should be converted before uploading.
+
 use bytemuck::{Pod, Zeroable};
 
+/// Column-major 4x4 matrix
 pub type Matrix4 = [f32; 16];
 pub type Vector3 = [f32; 3];
 
@@ -11,9 +20,58 @@ pub fn identity() -> Matrix4 {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct Vertex {
 	pub position: Vector3,
+	/// Linear RGBA color, white unless set
+	pub color: [f32; 4],
+	/// Facing +Z unless set
+	pub normal: Vector3,
+	pub uv: [f32; 2],
+	/// Tangent along increasing U, with the sign of the bitangent in w for
+	/// mirrored UVs, filled in by `Mesh::compute_tangents`
+	pub tangent: [f32; 4],
+}
+
+impl Vertex {
+	pub fn new(position: Vector3) -> Self {
+		Self::with_color(position, [1.0; 4])
+	}
+
+	pub fn with_color(position: Vector3, color: [f32; 4]) -> Self {
+		Self {
+			position,
+			color,
+			normal: [0.0, 0.0, 1.0],
+			uv: [0.0; 2],
+			tangent: [1.0, 0.0, 0.0, 1.0],
+		}
+	}
+
+	pub fn with_normal(position: Vector3, normal: Vector3) -> Self {
+		Self {
+			normal,
+			..Self::new(position)
+		}
+	}
+
+	/// A vertex of a textured surface
+	pub fn textured(position: Vector3, normal: Vector3, uv: [f32; 2]) -> Self {
+		Self {
+			uv,
+			..Self::with_normal(position, normal)
+		}
+	}
+}
+
+impl Default for Vertex {
+	fn default() -> Self {
+		Self::new([0.0; 3])
+	}
 }
 
+pub mod bounds;
+pub mod camera;
+pub mod frustum;
+pub mod ray;
 pub mod transform;
@@ -0,0 +1,124 @@
+use crate::{bounds::Aabb, Vector3};
+use glam::Vec3;
+
+/// Half-line starting at `origin` and going along `direction`
+///
+/// Hits are reported as the distance along the ray in multiples of
+/// `direction`, which is the actual distance when it is normalized.
+#[derive(PartialEq, Copy, Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ray {
+	pub origin: Vector3,
+	pub direction: Vector3,
+}
+
+impl Ray {
+	pub fn new(origin: Vector3, direction: Vector3) -> Self {
+		Self { origin, direction }
+	}
+
+	/// The point `distance` along the ray
+	pub fn at(&self, distance: f32) -> Vector3 {
+		(Vec3::from(self.origin) + Vec3::from(self.direction) * distance)
+			.to_array()
+	}
+
+	/// Where the ray enters the box, or 0 if it starts inside it
+	///
+	/// Empty boxes are never hit.
+	pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+		if aabb.is_empty() {
+			return None;
+		}
+
+		let (mut near, mut far) = (0.0_f32, f32::INFINITY);
+		for axis in 0..3 {
+			let (min, max) = (aabb.min[axis], aabb.max[axis]);
+			let origin = self.origin[axis];
+			// Parallel to the slab, so the ray is either always or never
+			// between its planes
+			if self.direction[axis] == 0.0 {
+				if origin < min || origin > max {
+					return None;
+				}
+				continue;
+			}
+
+			let inverse = 1.0 / self.direction[axis];
+			let (a, b) = ((min - origin) * inverse, (max - origin) * inverse);
+			near = near.max(a.min(b));
+			far = far.min(a.max(b));
+		}
+
+		(near <= far).then_some(near)
+	}
+
+	/// Where the ray hits the triangle from either side, using the
+	/// Möller–Trumbore algorithm
+	pub fn intersect_triangle(
+		&self,
+		a: Vector3,
+		b: Vector3,
+		c: Vector3,
+	) -> Option<f32> {
+		let (a, b, c) = (Vec3::from(a), Vec3::from(b), Vec3::from(c));
+		let (origin, direction) =
+			(Vec3::from(self.origin), Vec3::from(self.direction));
+		let (edge1, edge2) = (b - a, c - a);
+
+		let p = direction.cross(edge2);
+		let determinant = edge1.dot(p);
+		// The ray is parallel to the triangle
+		if determinant.abs() < f32::EPSILON {
+			return None;
+		}
+		let inverse = 1.0 / determinant;
+
+		let offset = origin - a;
+		let u = offset.dot(p) * inverse;
+		if !(0.0..=1.0).contains(&u) {
+			return None;
+		}
+		let q = offset.cross(edge1);
+		let v = direction.dot(q) * inverse;
+		if v < 0.0 || u + v > 1.0 {
+			return None;
+		}
+
+		let distance = edge2.dot(q) * inverse;
+		(distance >= 0.0).then_some(distance)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn unit_box() -> Aabb {
+		Aabb::new([-0.5; 3], [0.5; 3])
+	}
+
+	#[test]
+	fn ray_hits_unit_box() {
+		let ray = Ray::new([0.0, 0.0, -2.0], [0.0, 0.0, 1.0]);
+
+		assert_eq!(ray.intersect_aabb(&unit_box()), Some(1.5));
+		assert_eq!(ray.intersect_aabb(&Aabb::EMPTY), None);
+	}
+
+	#[test]
+	fn ray_misses_unit_box() {
+		let beside = Ray::new([1.0, 0.0, -2.0], [0.0, 0.0, 1.0]);
+		let away = Ray::new([0.0, 0.0, -2.0], [0.0, 0.0, -1.0]);
+
+		assert_eq!(beside.intersect_aabb(&unit_box()), None);
+		assert_eq!(away.intersect_aabb(&unit_box()), None);
+	}
+
+	#[test]
+	fn ray_inside_unit_box() {
+		let ray = Ray::new([0.0; 3], [1.0, 1.0, 0.0]);
+
+		assert_eq!(ray.intersect_aabb(&unit_box()), Some(0.0));
+	}
+}
@@ -1,4 +1,5 @@
-use glam::{Mat4, Quat, Vec3};
+use crate::Matrix4;
+use glam::{Mat3, Mat4, Quat, Vec3};
 
 #[derive(PartialEq, Copy, Debug, Clone, Default)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
@@ -17,6 +18,62 @@ impl RenderTransformation {
 			proj: Mat4::IDENTITY,
 		}
 	}
+
+	/// Replace the projection with a perspective projection looking down -Z
+	///
+	/// `fov_y` is the vertical field of view in radians. Depth is mapped to
+	/// `0..1` as wgpu expects, rather than OpenGL's `-1..1`, with `near` at
+	/// 0 and `far` at 1.
+	pub fn perspective(
+		self,
+		fov_y: f32,
+		aspect: f32,
+		near: f32,
+		far: f32,
+	) -> Self {
+		Self {
+			proj: Mat4::perspective_rh(fov_y, aspect, near, far),
+			..self
+		}
+	}
+
+	/// Replace the projection with an orthographic projection of the given
+	/// view-space box, mapping depth to `0..1` like
+	/// [`RenderTransformation::perspective`]
+	pub fn orthographic(
+		self,
+		left: f32,
+		right: f32,
+		bottom: f32,
+		top: f32,
+		near: f32,
+		far: f32,
+	) -> Self {
+		Self {
+			proj: Mat4::orthographic_rh(left, right, bottom, top, near, far),
+			..self
+		}
+	}
+
+	/// Replace the view with a camera at `eye` facing `target`, with `up`
+	/// pointing towards the top of the screen
+	pub fn look_at(self, eye: Vec3, target: Vec3, up: Vec3) -> Self {
+		Self {
+			view: Mat4::look_at_rh(eye, target, up),
+			..self
+		}
+	}
+
+	/// The combined model-view-projection matrix, ready to upload
+	pub fn mvp(&self) -> Matrix4 {
+		(self.proj * self.view * self.model).to_cols_array()
+	}
+
+	/// Matrix transforming normals by the model matrix, keeping them
+	/// perpendicular to surfaces under non-uniform scaling
+	pub fn normal_matrix(&self) -> Mat3 {
+		Mat3::from_mat4(self.model).inverse().transpose()
+	}
 }
 
 #[derive(PartialEq, Copy, Debug, Clone, Default)]
@@ -29,6 +86,17 @@ pub struct ObjectTransform {
 }
 
 impl ObjectTransform {
+	/// Blend towards `other`, interpolating position and scale linearly
+	/// and rotation spherically, so `t` of 0 gives `self` and 1 gives
+	/// `other`
+	pub fn lerp(&self, other: &ObjectTransform, t: f32) -> ObjectTransform {
+		Self {
+			position: self.position.lerp(other.position, t),
+			rotation: self.rotation.slerp(other.rotation, t),
+			scale: self.scale.lerp(other.scale, t),
+		}
+	}
+
 	pub fn get_matrix(&self) -> Mat4 {
 		Mat4::from_scale_rotation_translation(
 			self.scale,
@@ -37,3 +105,31 @@ impl ObjectTransform {
 		)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f32::consts::FRAC_PI_2;
+
+	#[test]
+	fn lerp_halfway_rotation() {
+		let from = ObjectTransform {
+			position: Vec3::ZERO,
+			rotation: Quat::IDENTITY,
+			scale: Vec3::ONE,
+		};
+		let to = ObjectTransform {
+			position: Vec3::new(2.0, 0.0, 0.0),
+			rotation: Quat::from_rotation_y(FRAC_PI_2),
+			scale: Vec3::splat(3.0),
+		};
+
+		let halfway = from.lerp(&to, 0.5);
+
+		let (axis, angle) = halfway.rotation.to_axis_angle();
+		assert!(axis.abs_diff_eq(Vec3::Y, 1e-5), "{axis}");
+		assert!((angle.to_degrees() - 45.0).abs() < 1e-3, "{angle}");
+		assert_eq!(halfway.position, Vec3::new(1.0, 0.0, 0.0));
+		assert_eq!(halfway.scale, Vec3::splat(2.0));
+	}
+}
@@ -0,0 +1,158 @@
+use crate::{
+	graph::{Graph, Node, TypeName, TypedValue},
+	subgraph::SubgraphId,
+};
+use anyhow::Result;
+use petgraph::graph::NodeIndex;
+
+/// Builds a [`Graph`] without tracking argument indices by hand
+///
+/// Every method adds one node, connects the given operands as its
+/// arguments in the order they are passed, and returns the new node.
+#[derive(Debug, Clone, Default)]
+pub struct GraphBuilder {
+	graph: Graph,
+}
+
+impl GraphBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add any node, connecting `arguments` at indices 0, 1, 2...
+	pub fn node(
+		&mut self,
+		node: Node,
+		arguments: &[NodeIndex<u32>],
+	) -> NodeIndex<u32> {
+		let index = self.graph.add_node(node);
+		for (argument, operand) in arguments.iter().enumerate() {
+			self.graph.add_edge(*operand, index, argument as u32);
+		}
+
+		index
+	}
+
+	pub fn constant(&mut self, value: f64) -> NodeIndex<u32> {
+		self.value(TypedValue::Float(value))
+	}
+
+	pub fn value(&mut self, value: TypedValue) -> NodeIndex<u32> {
+		self.node(Node::Constant(value), &[])
+	}
+
+	pub fn input(&mut self, location: u32, ty: TypeName) -> NodeIndex<u32> {
+		self.node(Node::Input(location, Box::new(ty)), &[])
+	}
+
+	pub fn uniform(&mut self, binding: u32, ty: TypeName) -> NodeIndex<u32> {
+		self.node(Node::Uniform(binding, Box::new(ty)), &[])
+	}
+
+	/// Write `value` to the output at `location`
+	pub fn output(
+		&mut self,
+		location: u32,
+		ty: TypeName,
+		value: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::Output(location, Box::new(ty)), &[value])
+	}
+
+	pub fn construct(
+		&mut self,
+		ty: TypeName,
+		components: &[NodeIndex<u32>],
+	) -> NodeIndex<u32> {
+		self.node(Node::Construct(Box::new(ty)), components)
+	}
+
+	pub fn extract(
+		&mut self,
+		vector: NodeIndex<u32>,
+		component: u32,
+	) -> NodeIndex<u32> {
+		self.node(Node::Extract(component), &[vector])
+	}
+
+	pub fn swizzle(
+		&mut self,
+		vector: NodeIndex<u32>,
+		components: &[u32],
+	) -> NodeIndex<u32> {
+		self.node(Node::Swizzle(components.to_vec()), &[vector])
+	}
+
+	pub fn add(
+		&mut self,
+		a: NodeIndex<u32>,
+		b: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::Add, &[a, b])
+	}
+
+	pub fn subtract(
+		&mut self,
+		a: NodeIndex<u32>,
+		b: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::Subtract, &[a, b])
+	}
+
+	pub fn multiply(
+		&mut self,
+		a: NodeIndex<u32>,
+		b: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::Multiply, &[a, b])
+	}
+
+	pub fn divide(
+		&mut self,
+		a: NodeIndex<u32>,
+		b: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::Divide, &[a, b])
+	}
+
+	/// Multiply a matrix by a vector or another matrix
+	pub fn mat_mul(
+		&mut self,
+		a: NodeIndex<u32>,
+		b: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::MatMul, &[a, b])
+	}
+
+	/// Sample the sampler uniform `texture` at `coordinate`
+	pub fn sample(
+		&mut self,
+		texture: NodeIndex<u32>,
+		coordinate: NodeIndex<u32>,
+	) -> NodeIndex<u32> {
+		self.node(Node::Sample, &[texture, coordinate])
+	}
+
+	/// Register a subgraph for [`GraphBuilder::call`], see
+	/// [`Graph::register_subgraph`]
+	pub fn register_subgraph(
+		&mut self,
+		name: impl Into<String>,
+		graph: Graph,
+	) -> Result<SubgraphId> {
+		self.graph.register_subgraph(name, graph)
+	}
+
+	/// Call a registered subgraph, passing `arguments` as its inputs
+	pub fn call(
+		&mut self,
+		subgraph: SubgraphId,
+		arguments: &[NodeIndex<u32>],
+	) -> NodeIndex<u32> {
+		self.node(Node::Call(subgraph), arguments)
+	}
+
+	pub fn build(self) -> Graph {
+		self.graph
+	}
+}
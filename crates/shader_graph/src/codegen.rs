@@ -0,0 +1,1084 @@
+use crate::{
+	graph::{Dim, Graph, Node, TypeName, TypedValue},
+	infer::Types,
+};
+use anyhow::{bail, Context, Result};
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const COMPONENTS: [char; 4] = ['x', 'y', 'z', 'w'];
+
+/// Shading language targeted by the code generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+	Wgsl,
+	Glsl,
+}
+
+impl Language {
+	/// Spell a graph type in the target language
+	pub fn type_name(self, ty: &TypeName) -> Result<String> {
+		Ok(match (self, ty) {
+			(_, TypeName::Bool) => "bool".to_string(),
+			(Language::Wgsl, TypeName::Int(true)) => "i32".to_string(),
+			(Language::Wgsl, TypeName::Int(false)) => "u32".to_string(),
+			(Language::Wgsl, TypeName::Float(false)) => "f32".to_string(),
+			(Language::Wgsl, TypeName::Float(true)) => {
+				bail!("WGSL has no 64-bit floats")
+			}
+			(Language::Wgsl, TypeName::Vec(n)) => format!("vec{n}<f32>"),
+			(Language::Wgsl, TypeName::Mat(n, component)) => {
+				format!("mat{n}x{n}<{}>", self.type_name(component)?)
+			}
+			(Language::Glsl, TypeName::Int(true)) => "int".to_string(),
+			(Language::Glsl, TypeName::Int(false)) => "uint".to_string(),
+			(Language::Glsl, TypeName::Float(false)) => "float".to_string(),
+			(Language::Glsl, TypeName::Float(true)) => "double".to_string(),
+			(Language::Glsl, TypeName::Vec(n)) => format!("vec{n}"),
+			(Language::Glsl, TypeName::Mat(n, component)) => {
+				match component.as_ref() {
+					TypeName::Float(true) => format!("dmat{n}"),
+					_ => format!("mat{n}"),
+				}
+			}
+			(_, TypeName::Sampler(component, dim)) => {
+				self.sampler_type_name(component, *dim)?
+			}
+		})
+	}
+
+	fn sampler_type_name(
+		self,
+		component: &TypeName,
+		dim: Dim,
+	) -> Result<String> {
+		match self {
+			Language::Wgsl => {
+				let dim = match dim {
+					Dim::Dim1D => "1d",
+					Dim::Dim2D | Dim::DimRect => "2d",
+					Dim::Dim3D => "3d",
+					Dim::DimCube => "cube",
+					Dim::DimBuffer | Dim::DimSubpassData => {
+						bail!("{dim:?} samplers are not supported in WGSL")
+					}
+				};
+
+				Ok(format!("texture_{dim}<{}>", self.type_name(component)?))
+			}
+			Language::Glsl => {
+				let prefix = match component {
+					TypeName::Int(true) => "i",
+					TypeName::Int(false) => "u",
+					_ => "",
+				};
+				let dim = match dim {
+					Dim::Dim1D => "1D",
+					Dim::Dim2D => "2D",
+					Dim::Dim3D => "3D",
+					Dim::DimCube => "Cube",
+					Dim::DimRect => "2DRect",
+					Dim::DimBuffer => "Buffer",
+					Dim::DimSubpassData => {
+						bail!("subpass inputs cannot be sampled")
+					}
+				};
+
+				Ok(format!("{prefix}sampler{dim}"))
+			}
+		}
+	}
+
+	/// Spell a constant value in the target language
+	///
+	/// Matrices are passed to their constructor column by column, which both
+	/// languages read as column-major.
+	pub fn literal(self, value: &TypedValue) -> String {
+		let components = match value {
+			TypedValue::Bool(value) => return value.to_string(),
+			TypedValue::Float(x) => return float_literal(*x),
+			TypedValue::Vec2(x, y) => vec![*x, *y],
+			TypedValue::Vec3(x, y, z) => vec![*x, *y, *z],
+			TypedValue::Vec4(x, y, z, w) => vec![*x, *y, *z, *w],
+			TypedValue::Mat2(columns) => columns.to_vec(),
+			TypedValue::Mat3(columns) => columns.to_vec(),
+			TypedValue::Mat4(columns) => columns.to_vec(),
+		};
+		let ty = self
+			.type_name(&value.type_name())
+			.expect("vector and matrix types are always representable");
+		let components: Vec<_> =
+			components.into_iter().map(float_literal).collect();
+
+		format!("{ty}({})", components.join(", "))
+	}
+}
+
+/// Pipeline stage a generated shader runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+	Vertex,
+	Fragment,
+}
+
+/// Stage and function name of a generated shader's entry point
+///
+/// GLSL entry points are always called `main`, so the name only affects
+/// WGSL output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryPoint {
+	pub stage: ShaderStage,
+	pub name: String,
+}
+
+impl EntryPoint {
+	pub fn new(stage: ShaderStage, name: impl Into<String>) -> Self {
+		Self {
+			stage,
+			name: name.into(),
+		}
+	}
+
+	/// The `vs_main` vertex entry point used by the core render pipeline
+	pub fn vertex() -> Self {
+		Self::new(ShaderStage::Vertex, "vs_main")
+	}
+
+	/// The `fs_main` fragment entry point used by the core render pipeline
+	pub fn fragment() -> Self {
+		Self::new(ShaderStage::Fragment, "fs_main")
+	}
+}
+
+fn float_literal(value: f64) -> String {
+	// Debug formatting always keeps a decimal point or exponent
+	format!("{value:?}")
+}
+
+/// Bind group holding the per-frame globals read by `TimeUniform` and
+/// `ResolutionUniform`, matching `dyadikos_core::FRAME_GLOBALS_GROUP`
+pub const FRAME_GLOBALS_GROUP: u32 = 1;
+
+/// Bind group holding the samplers WGSL output pairs with sampler uniforms,
+/// matching `dyadikos_core::SAMPLER_GROUP`
+pub const SAMPLER_GROUP: u32 = 2;
+
+/// Identifier of the temporary holding the value of a node used more than
+/// once
+pub fn temporary_name(index: NodeIndex<u32>) -> String {
+	format!("node_{}", index.index())
+}
+
+/// Identifier used for the uniform bound at `binding`
+pub fn uniform_name(binding: u32) -> String {
+	format!("uniform_{binding}")
+}
+
+/// Identifier used for the sampler paired with the texture at `binding`
+///
+/// WGSL splits combined image samplers into a texture and a sampler, so
+/// the sampler is declared in [`SAMPLER_GROUP`] with the same binding index
+/// as its texture in group 0.
+pub fn sampler_name(binding: u32) -> String {
+	format!("uniform_{binding}_sampler")
+}
+
+/// A uniform a generated shader expects to be bound
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformBinding {
+	pub group: u32,
+	pub binding: u32,
+	pub ty: TypeName,
+}
+
+impl UniformBinding {
+	/// Size in bytes of the buffer backing the uniform, or `None` for
+	/// samplers
+	///
+	/// Matrix columns are padded the same way in WGSL and std140 GLSL, so
+	/// the size holds for both languages.
+	pub fn size(&self) -> Option<u64> {
+		let scalar = |ty: &TypeName| match ty {
+			TypeName::Float(true) => 8,
+			_ => 4,
+		};
+
+		match &self.ty {
+			TypeName::Sampler(_, _) => None,
+			TypeName::Vec(n) => Some(*n as u64 * 4),
+			TypeName::Mat(n, component) => {
+				let padded = if *n == 3 { 4 } else { *n as u64 };
+				Some(*n as u64 * padded * scalar(component))
+			}
+			ty => Some(scalar(ty)),
+		}
+	}
+}
+
+/// Placement of a uniform in a std140 block, see
+/// [`Graph::uniform_block_layout`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformMember {
+	pub binding: u32,
+	pub ty: TypeName,
+	/// Offset in bytes from the start of the block
+	pub offset: u64,
+	/// Size in bytes, not counting padding before the next member
+	pub size: u64,
+}
+
+/// Alignment and size of a type in a std140 block, or `None` for types
+/// that can't be stored in one
+fn std140(ty: &TypeName) -> Option<(u64, u64)> {
+	let scalar = |ty: &TypeName| match ty {
+		TypeName::Float(true) => 8,
+		_ => 4,
+	};
+	// A vec3 is aligned like a vec4 but only takes the space of three
+	// components, so a scalar can follow it in the padding
+	let vector = |n: u32, component: u64| match n {
+		2 => (component * 2, component * 2),
+		n => (component * 4, component * n as u64),
+	};
+
+	match ty {
+		TypeName::Sampler(_, _) => None,
+		TypeName::Vec(n) => Some(vector(*n, 4)),
+		// Matrices are arrays of column vectors, whose stride is rounded up
+		// to 16 bytes like every array element
+		TypeName::Mat(n, component) => {
+			let (align, _) = vector(*n, scalar(component));
+			let stride = align.max(16);
+			Some((stride, stride * *n as u64))
+		}
+		ty => Some((scalar(ty), scalar(ty))),
+	}
+}
+
+impl Graph {
+	/// Every `Uniform` node of the graph, ordered by binding
+	///
+	/// WGSL output also declares a sampler in [`SAMPLER_GROUP`] for each
+	/// sampler uniform, named by [`sampler_name`].
+	pub fn uniform_layout(&self) -> Vec<UniformBinding> {
+		let mut layout: Vec<_> = self
+			.nodes()
+			.filter_map(|(_, node)| match node {
+				Node::Uniform(binding, ty) => Some(UniformBinding {
+					group: 0,
+					binding: *binding,
+					ty: ty.as_ref().clone(),
+				}),
+				_ => None,
+			})
+			.collect();
+		layout.sort_by_key(|uniform| uniform.binding);
+
+		layout
+	}
+
+	/// Pack every uniform besides samplers into a single std140 block, in
+	/// binding order
+	///
+	/// Generated shaders read each uniform from its own buffer, so this is
+	/// for staging their values in one CPU-side buffer, uploading it once
+	/// and copying each member's range into the buffer of its binding.
+	/// Uniforms bound more than once are only packed once.
+	pub fn uniform_block_layout(&self) -> Vec<UniformMember> {
+		let mut layout = Vec::new();
+		let mut offset = 0u64;
+		for uniform in self.uniform_layout() {
+			let (align, size) = match std140(&uniform.ty) {
+				Some(layout) => layout,
+				None => continue,
+			};
+			let last = layout.last().map(|last: &UniformMember| last.binding);
+			if last == Some(uniform.binding) {
+				continue;
+			}
+
+			offset = offset.div_ceil(align) * align;
+			layout.push(UniformMember {
+				binding: uniform.binding,
+				ty: uniform.ty,
+				offset,
+				size,
+			});
+			offset += size;
+		}
+
+		layout
+	}
+
+	/// Declare every uniform of the graph in the target language, once per
+	/// binding and in binding order
+	///
+	/// Uniform nodes sharing a binding have to agree on its type.
+	pub fn uniform_declarations(&self, language: Language) -> Result<String> {
+		uniform_declarations(language, &[self])
+	}
+
+	/// Generate a complete shader module with a single entry point
+	///
+	/// In the vertex stage `Output(0)` must be a `Vec(4)` and is written to
+	/// the clip-space position builtin; every other output is passed on at
+	/// its own location.
+	pub fn generate(
+		&self,
+		language: Language,
+		entry_point: &EntryPoint,
+	) -> Result<String> {
+		let inputs = self.stage_inputs(language)?;
+		let (temporaries, mut outputs) = self.stage_outputs(language)?;
+		let position = match entry_point.stage {
+			ShaderStage::Vertex => Some(take_position(&mut outputs)?),
+			ShaderStage::Fragment => None,
+		};
+
+		let uniforms = self.uniform_declarations(language)?;
+		let functions: String = self
+			.functions(language)?
+			.into_iter()
+			.map(|(_, function)| function)
+			.collect();
+
+		match language {
+			Language::Wgsl => {
+				let (attribute, struct_name) = match entry_point.stage {
+					ShaderStage::Vertex => ("@vertex", "VertexOutput"),
+					ShaderStage::Fragment => ("@fragment", "FragmentOutput"),
+				};
+
+				let mut members = String::new();
+				let mut body =
+					format!("{temporaries}\tvar output: {struct_name};\n");
+				if let Some(position) = &position {
+					members += "\t@builtin(position) position: vec4<f32>,\n";
+					body += &format!("\toutput.position = {position};\n");
+				}
+				for (location, ty, value) in &outputs {
+					let name = self.output_name(*location);
+					members += &format!(
+						"\t@location({location}) {name}: {},\n",
+						language.type_name(ty)?
+					);
+					body += &format!("\toutput.{name} = {value};\n");
+				}
+				body += "\treturn output;\n";
+
+				let params: String = inputs
+					.iter()
+					.map(|(location, (name, ty))| {
+						format!("\t@location({location}) {name}: {ty},\n")
+					})
+					.collect();
+
+				Ok(format!(
+					"{uniforms}\n{functions}struct {struct_name} {{\n{members}}};\n\n\
+					 {attribute}\nfn {}(\n{params}) -> {struct_name} {{\n{body}}}\n",
+					entry_point.name
+				))
+			}
+			Language::Glsl => {
+				let mut declarations = String::new();
+				for (location, (name, ty)) in &inputs {
+					declarations += &format!(
+						"layout(location = {location}) in {ty} {name};\n"
+					);
+				}
+
+				let mut body = temporaries;
+				if let Some(position) = &position {
+					body += &format!("\tgl_Position = {position};\n");
+				}
+				for (location, ty, value) in &outputs {
+					let name = self.output_name(*location);
+					declarations += &format!(
+						"layout(location = {location}) out {} {name};\n",
+						language.type_name(ty)?
+					);
+					body += &format!("\t{name} = {value};\n");
+				}
+
+				Ok(format!(
+					"#version 450\n\n{declarations}{uniforms}\n{functions}\
+					 void main() {{\n{body}}}\n"
+				))
+			}
+		}
+	}
+
+	/// Generate a WGSL module for the stage, with the `vs_main` or `fs_main`
+	/// entry point the core render pipeline looks for
+	///
+	/// A graph doesn't know which stage it is written for, so the stage has
+	/// to be given, as for `Graph::to_spirv`. `AppSettings::shader` needs
+	/// both entry points in one module, which [`Graph::generate_module`]
+	/// builds from a vertex and a fragment graph.
+	pub fn to_wgsl(&self, stage: ShaderStage) -> Result<String> {
+		let entry_point = match stage {
+			ShaderStage::Vertex => EntryPoint::vertex(),
+			ShaderStage::Fragment => EntryPoint::fragment(),
+		};
+
+		self.generate(Language::Wgsl, &entry_point)
+	}
+
+	/// Generate `#version 450` GLSL for the stage, with a `main` entry point
+	///
+	/// Both languages are lowered by [`Graph::lower`], so they only
+	/// differ in how builtins, types and declarations are spelled.
+	pub fn to_glsl(&self, stage: ShaderStage) -> Result<String> {
+		self.generate(Language::Glsl, &EntryPoint::new(stage, "main"))
+	}
+
+	/// Generate one WGSL module with a `vs_main` entry point from this graph
+	/// and an `fs_main` entry point from `fragment`
+	///
+	/// Every output of this graph besides the `Output(0)` position is a
+	/// varying: `Output(n)` is written to `@location(n)` of the
+	/// `VertexOutput` struct shared by both stages, and read back by the
+	/// fragment graph's `Input(n)`. Each fragment input needs a vertex
+	/// output of the same type at its location. Uniforms used by both
+	/// graphs are declared once, so they have to agree on their type, and
+	/// so are subgraph functions, which have to agree on their body.
+	///
+	/// GLSL has no modules with several stages, so generate each stage on
+	/// its own with [`Graph::generate`] instead.
+	pub fn generate_module(&self, fragment: &Graph) -> Result<String> {
+		let language = Language::Wgsl;
+
+		let declarations = uniform_declarations(language, &[self, fragment])?;
+
+		let mut functions = BTreeMap::new();
+		let mut function_order = Vec::new();
+		for graph in [self, fragment] {
+			for (name, function) in graph.functions(language)? {
+				match functions.get(&name) {
+					Some(other) if *other != function => bail!(
+						"Subgraph function {name} differs between the stages"
+					),
+					Some(_) => {}
+					None => {
+						functions.insert(name.clone(), function);
+						function_order.push(name);
+					}
+				}
+			}
+		}
+		let functions: String = function_order
+			.iter()
+			.map(|name| functions[name].as_str())
+			.collect();
+
+		let inputs = self.stage_inputs(language)?;
+		let (temporaries, mut varyings) = self.stage_outputs(language)?;
+		let position = take_position(&mut varyings)?;
+
+		let mut members =
+			"\t@builtin(position) position: vec4<f32>,\n".to_string();
+		let mut vertex_body = format!(
+			"{temporaries}\tvar output: VertexOutput;\n\
+			 \toutput.position = {position};\n"
+		);
+		for (location, ty, value) in &varyings {
+			let name = self.output_name(*location);
+			members += &format!(
+				"\t@location({location}) {name}: {},\n",
+				language.type_name(ty)?
+			);
+			vertex_body += &format!("\toutput.{name} = {value};\n");
+		}
+		vertex_body += "\treturn output;\n";
+
+		let params: String = inputs
+			.iter()
+			.map(|(location, (name, ty))| {
+				format!("\t@location({location}) {name}: {ty},\n")
+			})
+			.collect();
+
+		let mut fragment_body = String::new();
+		for (location, (name, ty)) in fragment.stage_inputs(language)? {
+			let (_, varying, _) = varyings
+				.iter()
+				.find(|(other, _, _)| *other == location)
+				.with_context(|| {
+					format!("No vertex output feeds fragment input {location}")
+				})?;
+			let varying = language.type_name(varying)?;
+			if varying != ty {
+				bail!(
+					"Fragment input {location} is a {ty}, but the vertex stage \
+					 outputs a {varying}"
+				);
+			}
+
+			fragment_body += &format!(
+				"\tlet {name} = vertex.{};\n",
+				self.output_name(location)
+			);
+		}
+
+		let mut fragment_members = String::new();
+		let (temporaries, outputs) = fragment.stage_outputs(language)?;
+		fragment_body += &temporaries;
+		fragment_body += "\tvar output: FragmentOutput;\n";
+		for (location, ty, value) in outputs {
+			let name = fragment.output_name(location);
+			fragment_members += &format!(
+				"\t@location({location}) {name}: {},\n",
+				language.type_name(ty)?
+			);
+			fragment_body += &format!("\toutput.{name} = {value};\n");
+		}
+		fragment_body += "\treturn output;\n";
+
+		Ok(format!(
+			"{declarations}\n{functions}\
+			 struct VertexOutput {{\n{members}}};\n\n\
+			 @vertex\nfn {}(\n{params}) -> VertexOutput {{\n{vertex_body}}}\n\n\
+			 struct FragmentOutput {{\n{fragment_members}}};\n\n\
+			 @fragment\nfn {}(vertex: VertexOutput) -> FragmentOutput {{\n\
+			 {fragment_body}}}\n",
+			EntryPoint::vertex().name,
+			EntryPoint::fragment().name
+		))
+	}
+
+	/// Name and source of a function for every subgraph the graph calls,
+	/// ordered so functions are declared before they are called
+	fn functions(&self, language: Language) -> Result<Vec<(String, String)>> {
+		let mut functions = Vec::new();
+		for id in self.called_subgraphs()? {
+			let name = self
+				.subgraph(id)
+				.map(|subgraph| subgraph.function_name())
+				.with_context(|| format!("{id:?} isn't registered"))?;
+			let body = self.subgraph_body(id)?;
+			let (_, output) = body.signature()?;
+			let output = language.type_name(&output)?;
+			let (temporaries, value) = match body.outputs().next() {
+				Some(index) => {
+					let (temporaries, mut values) =
+						body.lower(language, &[index])?;
+					(temporaries, values.remove(0))
+				}
+				None => bail!("Subgraph function {name} has no output"),
+			};
+
+			let params = body
+				.stage_inputs(language)?
+				.into_values()
+				.map(|(param, ty)| match language {
+					Language::Wgsl => format!("{param}: {ty}"),
+					Language::Glsl => format!("{ty} {param}"),
+				})
+				.collect::<Vec<_>>()
+				.join(", ");
+			let function = match language {
+				Language::Wgsl => format!(
+					"fn {name}({params}) -> {output} {{\n\
+					 {temporaries}\treturn {value};\n}}\n\n"
+				),
+				Language::Glsl => format!(
+					"{output} {name}({params}) {{\n\
+					 {temporaries}\treturn {value};\n}}\n\n"
+				),
+			};
+			functions.push((name, function));
+		}
+
+		Ok(functions)
+	}
+
+	/// Type and identifier of every input, keyed by location
+	fn stage_inputs(
+		&self,
+		language: Language,
+	) -> Result<BTreeMap<u32, (String, String)>> {
+		let mut inputs = BTreeMap::new();
+		for (_, node) in self.nodes() {
+			if let Node::Input(location, ty) = node {
+				inputs.insert(
+					*location,
+					(self.input_name(*location), language.type_name(ty)?),
+				);
+			}
+		}
+
+		Ok(inputs)
+	}
+
+	/// Declarations of the temporaries the outputs use, followed by the
+	/// location, type and value of every output, ordered by location
+	fn stage_outputs(
+		&self,
+		language: Language,
+	) -> Result<(String, Vec<StageOutput<'_>>)> {
+		let indices: Vec<_> = self.outputs().collect();
+		let (temporaries, values) = self.lower(language, &indices)?;
+
+		let mut outputs = Vec::new();
+		for (index, value) in indices.into_iter().zip(values) {
+			if let Node::Output(location, ty) = &self[index] {
+				outputs.push((*location, ty.as_ref(), value));
+			}
+		}
+		outputs.sort_by_key(|(location, _, _)| *location);
+
+		Ok((temporaries, outputs))
+	}
+
+	/// Lower the values produced by `roots` into expressions
+	///
+	/// Every node besides inputs, uniforms and constants that is used more
+	/// than once is bound to a temporary named by [`temporary_name`], so its
+	/// expression is only written once. The statements declaring them are
+	/// returned first, one per line, to be placed before the expressions.
+	/// Graphs with cycles can't be lowered.
+	pub fn lower(
+		&self,
+		language: Language,
+		roots: &[NodeIndex<u32>],
+	) -> Result<(String, Vec<String>)> {
+		let order = self.toposort()?;
+
+		let mut needed = HashSet::new();
+		let mut stack = roots.to_vec();
+		while let Some(index) = stack.pop() {
+			if needed.insert(index) {
+				stack.extend(self.arguments(index));
+			}
+		}
+		let mut uses = HashMap::new();
+		for index in &needed {
+			// GLSL's truncating modulus spells out each operand twice
+			let count = match (language, &self[*index]) {
+				(Language::Glsl, Node::Modulus) => 2,
+				_ => 1,
+			};
+			for arg in self.arguments(*index) {
+				*uses.entry(arg).or_insert(0) += count;
+			}
+		}
+
+		let mut expressions = HashMap::new();
+		let mut types = Types::new();
+		let mut temporaries = String::new();
+		for index in order.into_iter().filter(|index| needed.contains(index)) {
+			let mut value =
+				self.expression(language, index, &expressions, &mut types)?;
+			let inline = matches!(
+				self[index],
+				Node::Input(_, _)
+					| Node::Uniform(_, _)
+					| Node::TimeUniform
+					| Node::ResolutionUniform
+					| Node::Constant(_)
+			);
+			if !inline && uses.get(&index).copied().unwrap_or(0) > 1 {
+				let name = temporary_name(index);
+				temporaries += &match language {
+					Language::Wgsl => format!("\tlet {name} = {value};\n"),
+					Language::Glsl => format!(
+						"\t{} {name} = {value};\n",
+						language
+							.type_name(&self.infer_with(index, &mut types)?)?
+					),
+				};
+				value = name;
+			}
+			expressions.insert(index, value);
+		}
+
+		let values =
+			roots.iter().map(|root| expressions[root].clone()).collect();
+
+		Ok((temporaries, values))
+	}
+
+	/// Lower the value produced by a node into an expression, given the
+	/// expressions of its arguments
+	fn expression(
+		&self,
+		language: Language,
+		index: NodeIndex<u32>,
+		expressions: &HashMap<NodeIndex<u32>, String>,
+		types: &mut Types,
+	) -> Result<String> {
+		let args = self.arguments(index).collect::<Vec<_>>();
+		let operand = |i: usize| -> Result<String> {
+			let arg = args.get(i).with_context(|| {
+				format!("{:?} is missing argument {i}", self[index])
+			})?;
+
+			Ok(expressions[arg].clone())
+		};
+		let call = |name: &str, arity: usize| -> Result<String> {
+			let operands =
+				(0..arity).map(operand).collect::<Result<Vec<_>>>()?;
+
+			Ok(format!("{name}({})", operands.join(", ")))
+		};
+		// Splat scalar operands to the vector type of the result, which both
+		// languages need for clamp and min/max
+		let mut per_component = |name: &str, arity: usize| -> Result<String> {
+			let ty = self.infer_with(index, types)?;
+			let operands = (0..arity)
+				.map(|i| {
+					let value = operand(i)?;
+
+					Ok(match (self.infer_with(args[i], types)?, &ty) {
+						(TypeName::Float(_), TypeName::Vec(_)) => {
+							format!("{}({value})", language.type_name(&ty)?)
+						}
+						_ => value,
+					})
+				})
+				.collect::<Result<Vec<_>>>()?;
+
+			Ok(format!("{name}({})", operands.join(", ")))
+		};
+		let binary = |op: &str| -> Result<String> {
+			Ok(format!("({} {op} {})", operand(0)?, operand(1)?))
+		};
+
+		Ok(match &self[index] {
+			Node::Input(location, _) => self.input_name(*location),
+			Node::Uniform(binding, ty) => match (language, ty.as_ref()) {
+				(Language::Glsl, TypeName::Sampler(_, _)) => {
+					uniform_name(*binding)
+				}
+				(Language::Glsl, _) => {
+					format!("{}.value", uniform_name(*binding))
+				}
+				(Language::Wgsl, _) => uniform_name(*binding),
+			},
+			Node::TimeUniform => "frame.time".to_string(),
+			Node::ResolutionUniform => "frame.resolution".to_string(),
+			Node::Output(_, _) => operand(0)?,
+			Node::Constant(value) => language.literal(value),
+			Node::Construct(ty) => {
+				let operands =
+					(0..args.len()).map(operand).collect::<Result<Vec<_>>>()?;
+
+				format!("{}({})", language.type_name(ty)?, operands.join(", "))
+			}
+			Node::Extract(component) => {
+				let component =
+					COMPONENTS.get(*component as usize).with_context(|| {
+						format!("cannot extract component {component}")
+					})?;
+
+				format!("{}.{component}", operand(0)?)
+			}
+			Node::Swizzle(mask) => {
+				let components = mask
+					.iter()
+					.map(|component| {
+						COMPONENTS.get(*component as usize).with_context(|| {
+							format!("cannot swizzle component {component}")
+						})
+					})
+					.collect::<Result<String>>()?;
+
+				format!("{}.{components}", operand(0)?)
+			}
+			Node::Add => binary("+")?,
+			Node::Subtract => binary("-")?,
+			Node::Multiply => binary("*")?,
+			Node::Divide => binary("/")?,
+			// GLSL's `mod` floors, so truncate like WGSL's `%` and the CPU
+			// evaluator instead
+			Node::Modulus => match language {
+				Language::Wgsl => binary("%")?,
+				Language::Glsl => {
+					let (a, b) = (operand(0)?, operand(1)?);
+					format!("({a} - {b} * trunc({a} / {b}))")
+				}
+			},
+			Node::Normalize => call("normalize", 1)?,
+			Node::Clamp => per_component("clamp", 3)?,
+			Node::Dot => call("dot", 2)?,
+			Node::Cross => call("cross", 2)?,
+			Node::Floor => call("floor", 1)?,
+			Node::Ceil => call("ceil", 1)?,
+			Node::Round => call("round", 1)?,
+			Node::Sin => call("sin", 1)?,
+			Node::Cos => call("cos", 1)?,
+			Node::Tan => call("tan", 1)?,
+			Node::Abs => call("abs", 1)?,
+			Node::Sign => call("sign", 1)?,
+			Node::Fract => call("fract", 1)?,
+			Node::Sqrt => call("sqrt", 1)?,
+			Node::InverseSqrt => match language {
+				Language::Wgsl => call("inverseSqrt", 1)?,
+				Language::Glsl => call("inversesqrt", 1)?,
+			},
+			Node::Exp => call("exp", 1)?,
+			Node::Log => call("log", 1)?,
+			Node::Pow => call("pow", 2)?,
+			Node::Min => per_component("min", 2)?,
+			Node::Max => per_component("max", 2)?,
+			Node::Length => call("length", 1)?,
+			Node::Distance => call("distance", 2)?,
+			Node::Reflect => call("reflect", 2)?,
+			Node::Refract => call("refract", 3)?,
+			Node::Mix => per_component("mix", 3)?,
+			Node::Step => per_component("step", 2)?,
+			Node::Smoothstep => per_component("smoothstep", 3)?,
+			Node::LessThan => binary("<")?,
+			Node::GreaterThan => binary(">")?,
+			Node::Equal => binary("==")?,
+			Node::Select => {
+				let (condition, accept) = (operand(0)?, operand(1)?);
+				let reject = operand(2)?;
+
+				match language {
+					// WGSL's select takes the rejected value first
+					Language::Wgsl => {
+						format!("select({reject}, {accept}, {condition})")
+					}
+					Language::Glsl => {
+						format!("({condition} ? {accept} : {reject})")
+					}
+				}
+			}
+			Node::Call(id) => {
+				let subgraph = self
+					.subgraph(*id)
+					.with_context(|| format!("{id:?} isn't registered"))?;
+
+				call(&subgraph.function_name(), args.len())?
+			}
+			Node::Sample => {
+				self.sample_expression(language, index, &args, expressions)?
+			}
+			Node::MatMul => binary("*")?,
+			Node::Transpose => call("transpose", 1)?,
+			Node::Inverse => match language {
+				Language::Glsl => call("inverse", 1)?,
+				Language::Wgsl => bail!("WGSL has no matrix inverse builtin"),
+			},
+		})
+	}
+
+	fn sample_expression(
+		&self,
+		language: Language,
+		index: NodeIndex<u32>,
+		args: &[NodeIndex<u32>],
+		expressions: &HashMap<NodeIndex<u32>, String>,
+	) -> Result<String> {
+		let (texture, coordinate) = match args {
+			[texture, coordinate] => (*texture, *coordinate),
+			_ => bail!(
+				"Sample node {} expects a sampler and a coordinate, got {} arguments",
+				index.index(),
+				args.len()
+			),
+		};
+		let binding = match &self[texture] {
+			Node::Uniform(binding, ty)
+				if matches!(ty.as_ref(), TypeName::Sampler(_, _)) =>
+			{
+				*binding
+			}
+			node => bail!(
+				"Sample node {} must read from a sampler uniform, not {node:?}",
+				index.index()
+			),
+		};
+		let coordinate = &expressions[&coordinate];
+
+		Ok(match language {
+			Language::Wgsl => format!(
+				"textureSample({}, {}, {coordinate})",
+				uniform_name(binding),
+				sampler_name(binding)
+			),
+			Language::Glsl => {
+				format!("texture({}, {coordinate})", uniform_name(binding))
+			}
+		})
+	}
+}
+
+/// Location, type and value of an output of a generated stage
+type StageOutput<'a> = (u32, &'a TypeName, String);
+
+/// Remove the `Output(0)` clip-space position from a vertex stage's outputs
+fn take_position(outputs: &mut Vec<StageOutput<'_>>) -> Result<String> {
+	let index = outputs
+		.iter()
+		.position(|(location, _, _)| *location == 0)
+		.context("Vertex graphs need an Output(0) position")?;
+	let (_, ty, value) = outputs.remove(index);
+	if *ty != TypeName::Vec(4) {
+		bail!("The vertex position must be a Vec(4), not {ty:?}");
+	}
+
+	Ok(value)
+}
+
+/// Declare the uniforms read by any of the graphs, once per binding and in
+/// binding order, followed by the frame globals if any graph reads them
+fn uniform_declarations(
+	language: Language,
+	graphs: &[&Graph],
+) -> Result<String> {
+	let mut uniforms = BTreeMap::new();
+	let mut uses_frame_globals = false;
+	for graph in graphs {
+		for (_, node) in graph.nodes() {
+			match node {
+				Node::Uniform(binding, ty) => {
+					match uniforms.insert(*binding, ty.as_ref()) {
+						Some(other) if other != ty.as_ref() => bail!(
+							"Uniform {binding} is declared as both a {other:?} \
+							 and a {ty:?}"
+						),
+						_ => {}
+					}
+				}
+				Node::TimeUniform | Node::ResolutionUniform => {
+					uses_frame_globals = true;
+				}
+				_ => {}
+			}
+		}
+	}
+
+	let mut declarations = String::new();
+	for (binding, ty) in uniforms {
+		declarations += &uniform_declaration(language, binding, ty)?;
+	}
+	if uses_frame_globals {
+		declarations += &frame_globals_declaration(language);
+	}
+
+	Ok(declarations)
+}
+
+fn frame_globals_declaration(language: Language) -> String {
+	let group = FRAME_GLOBALS_GROUP;
+
+	match language {
+		Language::Wgsl => format!(
+			"struct FrameGlobals {{\n\
+			 \ttime: f32,\n\
+			 \tresolution: vec2<f32>,\n\
+			 }};\n\
+			 @group({group}) @binding(0) var<uniform> frame: FrameGlobals;\n"
+		),
+		Language::Glsl => format!(
+			"layout(set = {group}, binding = 0) uniform FrameGlobals {{\n\
+			 \tfloat time;\n\
+			 \tvec2 resolution;\n\
+			 }} frame;\n"
+		),
+	}
+}
+
+fn uniform_declaration(
+	language: Language,
+	binding: u32,
+	ty: &TypeName,
+) -> Result<String> {
+	let name = uniform_name(binding);
+	let type_name = language.type_name(ty)?;
+	let is_sampler = matches!(ty, TypeName::Sampler(_, _));
+	let sampler_group = SAMPLER_GROUP;
+
+	Ok(match (language, is_sampler) {
+		(Language::Wgsl, true) => format!(
+			"@group(0) @binding({binding}) var {name}: {type_name};\n\
+			 @group({sampler_group}) @binding({binding}) var {}: sampler;\n",
+			sampler_name(binding)
+		),
+		(Language::Wgsl, false) => format!(
+			"@group(0) @binding({binding}) var<uniform> {name}: {type_name};\n"
+		),
+		(Language::Glsl, true) => format!(
+			"layout(set = 0, binding = {binding}) uniform {type_name} {name};\n"
+		),
+		(Language::Glsl, false) => format!(
+			"layout(set = 0, binding = {binding}) uniform Uniform{binding} {{\n\
+			 \t{type_name} value;\n\
+			 }} {name};\n"
+		),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn uniform_block_pads_vec3() {
+		let mut graph = Graph::default();
+		graph.add_node(Node::Uniform(1, Box::new(TypeName::Vec(3))));
+		graph.add_node(Node::Uniform(0, Box::new(TypeName::Float(false))));
+
+		let layout = graph.uniform_block_layout();
+		let offsets: Vec<_> = layout
+			.iter()
+			.map(|member| (member.binding, member.offset, member.size))
+			.collect();
+		assert_eq!(offsets, [(0, 0, 4), (1, 16, 12)]);
+	}
+	#[test]
+	fn reflect_expression() {
+		let mut graph = Graph::default();
+		let incident =
+			graph.add_node(Node::Input(0, Box::new(TypeName::Vec(3))));
+		let normal = graph.add_node(Node::Input(1, Box::new(TypeName::Vec(3))));
+		let reflect = graph.add_node(Node::Reflect);
+		graph.add_edge(incident, reflect, 0);
+		graph.add_edge(normal, reflect, 1);
+		graph.name_input(1, "normal").unwrap();
+
+		assert_eq!(graph.infer(reflect).unwrap(), TypeName::Vec(3));
+		for language in [Language::Wgsl, Language::Glsl] {
+			let (temporaries, values) =
+				graph.lower(language, &[reflect]).unwrap();
+			assert_eq!(temporaries, "");
+			assert_eq!(values, ["reflect(input_0, normal)"]);
+		}
+	}
+	/// `Mix(Input(0), Input(1), Input(2))` with a float `t`
+	fn mix_graph(ty: TypeName) -> (Graph, NodeIndex<u32>) {
+		let mut graph = Graph::default();
+		let a = graph.add_node(Node::Input(0, Box::new(ty.clone())));
+		let b = graph.add_node(Node::Input(1, Box::new(ty)));
+		let t =
+			graph.add_node(Node::Input(2, Box::new(TypeName::Float(false))));
+		let mix = graph.add_node(Node::Mix);
+		graph.add_edge(a, mix, 0);
+		graph.add_edge(b, mix, 1);
+		graph.add_edge(t, mix, 2);
+
+		(graph, mix)
+	}
+
+	#[test]
+	fn scalar_mix() {
+		let (graph, mix) = mix_graph(TypeName::Float(false));
+
+		assert_eq!(graph.infer(mix).unwrap(), TypeName::Float(false));
+		let (_, values) = graph.lower(Language::Wgsl, &[mix]).unwrap();
+		assert_eq!(values, ["mix(input_0, input_1, input_2)"]);
+	}
+
+	#[test]
+	fn vector_mix_splats_t() {
+		let (graph, mix) = mix_graph(TypeName::Vec(3));
+
+		assert_eq!(graph.infer(mix).unwrap(), TypeName::Vec(3));
+		let (_, values) = graph.lower(Language::Wgsl, &[mix]).unwrap();
+		assert_eq!(values, ["mix(input_0, input_1, vec3<f32>(input_2))"]);
+		let (_, values) = graph.lower(Language::Glsl, &[mix]).unwrap();
+		assert_eq!(values, ["mix(input_0, input_1, vec3(input_2))"]);
+	}
+}
@@ -0,0 +1,27 @@
+use crate::{codegen::ShaderStage, graph::Graph};
+use anyhow::{Context, Result};
+use shaderc::{Compiler, ShaderKind};
+
+impl Graph {
+	/// Generate GLSL for the stage and compile it into SPIR-V words
+	///
+	/// Compile errors include the generated source, since it is not written
+	/// anywhere else.
+	pub fn to_spirv(&self, stage: ShaderStage) -> Result<Vec<u32>> {
+		let source = self.to_glsl(stage)?;
+		let (kind, file_name) = match stage {
+			ShaderStage::Vertex => (ShaderKind::Vertex, "graph.vert"),
+			ShaderStage::Fragment => (ShaderKind::Fragment, "graph.frag"),
+		};
+
+		let compiler =
+			Compiler::new().context("Failed to initialize shaderc")?;
+		let artifact = compiler
+			.compile_into_spirv(&source, kind, file_name, "main", None)
+			.with_context(|| {
+				format!("Failed to compile the generated GLSL:\n{source}")
+			})?;
+
+		Ok(artifact.as_binary().to_vec())
+	}
+}
@@ -0,0 +1,421 @@
+use crate::graph::{Graph, Node, TypeName, TypedValue};
+use anyhow::{bail, Context, Result};
+use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+
+/// Components of a value, matrices column by column
+///
+/// Booleans count as 0 or 1, but results can't be booleans, so they can
+/// only be compared and selected on.
+fn components(value: &TypedValue) -> Vec<f64> {
+	match value {
+		TypedValue::Bool(value) => vec![if *value { 1.0 } else { 0.0 }],
+		TypedValue::Float(x) => vec![*x],
+		TypedValue::Vec2(x, y) => vec![*x, *y],
+		TypedValue::Vec3(x, y, z) => vec![*x, *y, *z],
+		TypedValue::Vec4(x, y, z, w) => vec![*x, *y, *z, *w],
+		TypedValue::Mat2(columns) => columns.to_vec(),
+		TypedValue::Mat3(columns) => columns.to_vec(),
+		TypedValue::Mat4(columns) => columns.to_vec(),
+	}
+}
+
+/// A float or vector with the given components
+fn vector(components: &[f64]) -> Result<TypedValue> {
+	Ok(match *components {
+		[x] => TypedValue::Float(x),
+		[x, y] => TypedValue::Vec2(x, y),
+		[x, y, z] => TypedValue::Vec3(x, y, z),
+		[x, y, z, w] => TypedValue::Vec4(x, y, z, w),
+		_ => bail!("vectors can't have {} components", components.len()),
+	})
+}
+
+/// A value of the given type with the given components
+fn value(ty: &TypeName, components: &[f64]) -> Result<TypedValue> {
+	let expected = match ty {
+		TypeName::Float(_) => 1,
+		TypeName::Vec(n) => *n as usize,
+		TypeName::Mat(n, _) => (n * n) as usize,
+		ty => bail!("{ty:?} values can't be evaluated on the CPU"),
+	};
+	if components.len() != expected {
+		bail!(
+			"{ty:?} needs {expected} components, not {}",
+			components.len()
+		);
+	}
+
+	Ok(match (ty, components) {
+		(TypeName::Mat(2, _), columns) => {
+			TypedValue::Mat2(columns.try_into().unwrap())
+		}
+		(TypeName::Mat(3, _), columns) => {
+			TypedValue::Mat3(columns.try_into().unwrap())
+		}
+		(TypeName::Mat(4, _), columns) => {
+			TypedValue::Mat4(columns.try_into().unwrap())
+		}
+		(TypeName::Mat(n, _), _) => bail!("{n}x{n} matrices aren't supported"),
+		_ => vector(components)?,
+	})
+}
+
+/// Apply `f` to every pair of components, applying a float to every
+/// component of the other operand as the GPU does
+fn zip(
+	lhs: &TypedValue,
+	rhs: &TypedValue,
+	f: impl Fn(f64, f64) -> f64,
+) -> Result<TypedValue> {
+	let (a, b) = (components(lhs), components(rhs));
+	let ty = match (lhs.type_name(), rhs.type_name()) {
+		(lhs, rhs) if lhs == rhs => lhs,
+		(TypeName::Float(_), ty) | (ty, TypeName::Float(_)) => ty,
+		(lhs, rhs) => bail!("{lhs:?} and {rhs:?} can't be combined"),
+	};
+	let component = |values: &[f64], i: usize| match values {
+		[x] => *x,
+		values => values[i],
+	};
+	let len = a.len().max(b.len());
+	let result: Vec<_> = (0..len)
+		.map(|i| f(component(&a, i), component(&b, i)))
+		.collect();
+
+	value(&ty, &result)
+}
+
+fn map(value: &TypedValue, f: impl Fn(f64) -> f64) -> Result<TypedValue> {
+	let result: Vec<_> = components(value).into_iter().map(f).collect();
+
+	self::value(&value.type_name(), &result)
+}
+
+fn dot(lhs: &TypedValue, rhs: &TypedValue) -> Result<f64> {
+	let (a, b) = (components(lhs), components(rhs));
+	if lhs.type_name() != rhs.type_name() {
+		bail!("can't take the dot product of {lhs:?} and {rhs:?}");
+	}
+
+	Ok(a.iter().zip(&b).map(|(a, b)| a * b).sum())
+}
+
+/// Round halfway cases to the nearest even number, like WGSL's `round`
+fn round_even(x: f64) -> f64 {
+	if (x - x.trunc()).abs() == 0.5 {
+		2.0 * (x / 2.0).round()
+	} else {
+		x.round()
+	}
+}
+
+/// Product of two column-major matrices of size `n`, or of a matrix and a
+/// vector on either side
+fn matmul(lhs: &TypedValue, rhs: &TypedValue) -> Result<TypedValue> {
+	let (a, b) = (components(lhs), components(rhs));
+
+	match (lhs.type_name(), rhs.type_name()) {
+		(TypeName::Mat(n, _), TypeName::Vec(m)) if n == m => {
+			let n = n as usize;
+			let result: Vec<_> = (0..n)
+				.map(|row| (0..n).map(|k| a[k * n + row] * b[k]).sum())
+				.collect();
+			vector(&result)
+		}
+		(TypeName::Vec(m), TypeName::Mat(n, _)) if n == m => {
+			let n = n as usize;
+			let result: Vec<_> = (0..n)
+				.map(|column| (0..n).map(|k| a[k] * b[column * n + k]).sum())
+				.collect();
+			vector(&result)
+		}
+		(ty @ TypeName::Mat(n, _), TypeName::Mat(m, _)) if n == m => {
+			let n = n as usize;
+			let result: Vec<_> = (0..n * n)
+				.map(|i| {
+					let (column, row) = (i / n, i % n);
+					(0..n).map(|k| a[k * n + row] * b[column * n + k]).sum()
+				})
+				.collect();
+			value(&ty, &result)
+		}
+		(lhs, rhs) => bail!("can't multiply {lhs:?} by {rhs:?}"),
+	}
+}
+
+impl Graph {
+	/// Compute every output on the CPU, keyed by location, for checking a
+	/// graph without a GPU
+	///
+	/// `inputs` and `uniforms` give the value of each input location and
+	/// uniform binding that the outputs depend on, and have to match the
+	/// declared types. Floats are applied to every component of vectors
+	/// where the GPU would do the same, and `Modulus` truncates in both
+	/// languages, keeping the sign of its first operand. Samples, the frame
+	/// uniforms and matrix inverses can't be evaluated.
+	pub fn evaluate(
+		&self,
+		inputs: &HashMap<u32, TypedValue>,
+		uniforms: &HashMap<u32, TypedValue>,
+	) -> Result<HashMap<u32, TypedValue>> {
+		// Recursive subgraphs would never finish evaluating
+		self.called_subgraphs()?;
+
+		// Only nodes feeding an output are evaluated, so unused nodes can't
+		// cause errors
+		let reachable = self.reachable_from_outputs();
+
+		let mut values: HashMap<NodeIndex<u32>, TypedValue> = HashMap::new();
+		let mut outputs = HashMap::new();
+		for index in self.toposort()? {
+			if !reachable.contains(&index) {
+				continue;
+			}
+
+			let args: Vec<_> =
+				self.arguments(index).map(|arg| &values[&arg]).collect();
+			let value = self
+				.evaluate_node(index, &args, inputs, uniforms)
+				.with_context(|| {
+					format!(
+						"Failed to evaluate node {} ({:?})",
+						index.index(),
+						self[index]
+					)
+				})?;
+
+			if let Node::Output(location, _) = &self[index] {
+				outputs.insert(*location, value.clone());
+			}
+			values.insert(index, value);
+		}
+
+		Ok(outputs)
+	}
+
+	/// Value of a node given the values of its arguments
+	pub(crate) fn evaluate_node(
+		&self,
+		index: NodeIndex<u32>,
+		args: &[&TypedValue],
+		inputs: &HashMap<u32, TypedValue>,
+		uniforms: &HashMap<u32, TypedValue>,
+	) -> Result<TypedValue> {
+		let node = &self[index];
+		if let Some(arity) = node.arity() {
+			if args.len() != arity {
+				bail!("expected {arity} arguments, found {}", args.len());
+			}
+		}
+		let typed = |value: Option<&TypedValue>, ty: &TypeName| {
+			let value = value.context("no value was given")?;
+			if value.type_name() != *ty {
+				bail!("expected a {ty:?}, found {value:?}");
+			}
+
+			Ok(value.clone())
+		};
+
+		Ok(match node {
+			Node::Input(location, ty) => typed(inputs.get(location), ty)
+				.with_context(|| format!("Invalid input {location}"))?,
+			Node::Uniform(binding, ty) => typed(uniforms.get(binding), ty)
+				.with_context(|| format!("Invalid uniform {binding}"))?,
+			Node::Output(_, ty) => typed(Some(args[0]), ty)?,
+			Node::Constant(value) => value.clone(),
+			Node::Construct(ty) => {
+				let components: Vec<_> =
+					args.iter().flat_map(|arg| components(arg)).collect();
+
+				match (ty.as_ref(), components.as_slice()) {
+					(TypeName::Vec(n), [x]) => vector(&vec![*x; *n as usize])?,
+					(ty, components) => value(ty, components)?,
+				}
+			}
+			Node::Extract(component) => {
+				match components(args[0]).get(*component as usize) {
+					Some(x) => TypedValue::Float(*x),
+					None => bail!("{:?} has no component {component}", args[0]),
+				}
+			}
+			Node::Swizzle(mask) => {
+				let source = components(args[0]);
+				let components = mask
+					.iter()
+					.map(|component| {
+						source.get(*component as usize).copied().with_context(
+							|| {
+								format!(
+									"{:?} has no component {component}",
+									args[0]
+								)
+							},
+						)
+					})
+					.collect::<Result<Vec<_>>>()?;
+
+				vector(&components)?
+			}
+			Node::Add => zip(args[0], args[1], |a, b| a + b)?,
+			Node::Subtract => zip(args[0], args[1], |a, b| a - b)?,
+			Node::Multiply => zip(args[0], args[1], |a, b| a * b)?,
+			Node::Divide => zip(args[0], args[1], |a, b| a / b)?,
+			Node::Modulus => zip(args[0], args[1], |a, b| a % b)?,
+			Node::Pow => zip(args[0], args[1], f64::powf)?,
+			Node::Min => zip(args[0], args[1], f64::min)?,
+			Node::Max => zip(args[0], args[1], f64::max)?,
+			Node::Clamp => {
+				let lower = zip(args[0], args[1], f64::max)?;
+				zip(&lower, args[2], f64::min)?
+			}
+			Node::Mix => {
+				let (a, b) = (args[0], args[1]);
+				if a.type_name() != b.type_name() {
+					bail!("can't mix {a:?} and {b:?}");
+				}
+				let offset = zip(b, a, |b, a| b - a)?;
+				let scaled = zip(&offset, args[2], |offset, t| offset * t)?;
+				zip(a, &scaled, |a, scaled| a + scaled)?
+			}
+			Node::Floor => map(args[0], f64::floor)?,
+			Node::Ceil => map(args[0], f64::ceil)?,
+			Node::Round => map(args[0], round_even)?,
+			Node::Sin => map(args[0], f64::sin)?,
+			Node::Cos => map(args[0], f64::cos)?,
+			Node::Tan => map(args[0], f64::tan)?,
+			Node::Abs => map(args[0], f64::abs)?,
+			// signum gives 1 or -1 for zeros, where the GPU gives 0
+			Node::Sign => {
+				map(args[0], |x| if x == 0.0 { 0.0 } else { x.signum() })?
+			}
+			Node::Fract => map(args[0], |x| x - x.floor())?,
+			Node::Sqrt => map(args[0], f64::sqrt)?,
+			Node::InverseSqrt => map(args[0], |x| 1.0 / x.sqrt())?,
+			Node::Exp => map(args[0], f64::exp)?,
+			Node::Log => map(args[0], f64::ln)?,
+			Node::Step => {
+				zip(
+					args[0],
+					args[1],
+					|edge, x| {
+						if x < edge {
+							0.0
+						} else {
+							1.0
+						}
+					},
+				)?
+			}
+			Node::Smoothstep => {
+				let (low, high, value) = (args[0], args[1], args[2]);
+				let offset = zip(value, low, |x, low| x - low)?;
+				let range = zip(high, low, |high, low| high - low)?;
+				let t = zip(&offset, &range, |offset, range| {
+					(offset / range).clamp(0.0, 1.0)
+				})?;
+				map(&t, |t| t * t * (3.0 - 2.0 * t))?
+			}
+			Node::LessThan | Node::GreaterThan | Node::Equal => {
+				let (lhs, rhs) = (args[0], args[1]);
+				if lhs.type_name() != rhs.type_name() {
+					bail!("can't compare {lhs:?} and {rhs:?}");
+				}
+				let (a, b) = match (
+					components(lhs).as_slice(),
+					components(rhs).as_slice(),
+				) {
+					(&[a], &[b]) => (a, b),
+					_ => bail!("only scalars can be compared"),
+				};
+
+				TypedValue::Bool(match node {
+					Node::LessThan => a < b,
+					Node::GreaterThan => a > b,
+					_ => a == b,
+				})
+			}
+			Node::Call(id) => {
+				let inputs = args
+					.iter()
+					.enumerate()
+					.map(|(location, arg)| (location as u32, (*arg).clone()))
+					.collect();
+
+				self.subgraph_body(*id)?
+					.evaluate(&inputs, &HashMap::new())?
+					.remove(&0)
+					.context("the subgraph has no output")?
+			}
+			Node::Select => match args[0] {
+				TypedValue::Bool(true) => args[1].clone(),
+				TypedValue::Bool(false) => args[2].clone(),
+				condition => {
+					bail!("the condition has to be a Bool, not {condition:?}")
+				}
+			},
+			Node::Dot => TypedValue::Float(dot(args[0], args[1])?),
+			Node::Length => TypedValue::Float(dot(args[0], args[0])?.sqrt()),
+			Node::Distance => {
+				let offset = zip(args[0], args[1], |a, b| a - b)?;
+				TypedValue::Float(dot(&offset, &offset)?.sqrt())
+			}
+			Node::Normalize => {
+				let length = dot(args[0], args[0])?.sqrt();
+				map(args[0], |x| x / length)?
+			}
+			Node::Cross => match (args[0], args[1]) {
+				(
+					TypedValue::Vec3(ax, ay, az),
+					TypedValue::Vec3(bx, by, bz),
+				) => TypedValue::Vec3(
+					ay * bz - az * by,
+					az * bx - ax * bz,
+					ax * by - ay * bx,
+				),
+				(a, b) => {
+					bail!("can't take the cross product of {a:?} and {b:?}")
+				}
+			},
+			Node::Reflect => {
+				let (incident, normal) = (args[0], args[1]);
+				let d = dot(normal, incident)?;
+				zip(incident, normal, |i, n| i - 2.0 * d * n)?
+			}
+			Node::Refract => {
+				let (incident, normal) = (args[0], args[1]);
+				let eta = match args[2] {
+					TypedValue::Float(eta) => *eta,
+					eta => bail!(
+						"the ratio of indices has to be a float, not {eta:?}"
+					),
+				};
+				let d = dot(normal, incident)?;
+				let k = 1.0 - eta * eta * (1.0 - d * d);
+				if k < 0.0 {
+					map(incident, |_| 0.0)?
+				} else {
+					zip(incident, normal, |i, n| {
+						eta * i - (eta * d + k.sqrt()) * n
+					})?
+				}
+			}
+			Node::MatMul => matmul(args[0], args[1])?,
+			Node::Transpose => {
+				let ty = args[0].type_name();
+				let n = match ty {
+					TypeName::Mat(n, _) => n as usize,
+					_ => bail!("can't transpose {:?}", args[0]),
+				};
+				let a = components(args[0]);
+				let result: Vec<_> =
+					(0..n * n).map(|i| a[(i % n) * n + i / n]).collect();
+				value(&ty, &result)?
+			}
+			Node::TimeUniform
+			| Node::ResolutionUniform
+			| Node::Sample
+			| Node::Inverse => bail!("{node:?} can't be evaluated on the CPU"),
+		})
+	}
+}
@@ -1,8 +1,17 @@
+use crate::subgraph::{Subgraph, SubgraphId};
+use anyhow::{anyhow, bail, Result};
 use petgraph::{
-	algo, graph::NodeIndex, visit::EdgeRef, EdgeDirection, Graph as PetGraph,
-	Incoming, Outgoing,
+	algo,
+	dot::{Config, Dot},
+	graph::{EdgeIndex, NodeIndex},
+	stable_graph::{Neighbors, StableGraph},
+	visit::{EdgeRef, IntoEdgeReferences},
+	EdgeDirection, Incoming, Outgoing,
+};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	ops::Index,
 };
-use std::ops::Index;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
@@ -20,10 +29,17 @@ pub enum Dim {
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypedValue {
+	Bool(bool),
 	Float(f64),
 	Vec2(f64, f64),
 	Vec3(f64, f64, f64),
 	Vec4(f64, f64, f64, f64),
+	/// 2x2 matrix, column by column
+	Mat2([f64; 4]),
+	/// 3x3 matrix, column by column
+	Mat3([f64; 9]),
+	/// 4x4 matrix, column by column
+	Mat4([f64; 16]),
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -43,10 +59,17 @@ pub enum TypeName {
 pub enum Node {
 	Input(u32, Box<TypeName>),
 	Uniform(u32, Box<TypeName>),
+	/// Seconds elapsed since the app started rendering
+	TimeUniform,
+	/// Size of the render surface in pixels
+	ResolutionUniform,
 	Output(u32, Box<TypeName>),
 	Constant(TypedValue),
 	Construct(Box<TypeName>),
 	Extract(u32),
+	/// Pick components of a vector by index, in any order and with repeats,
+	/// such as `[0, 0, 2]` for `v.xxz`
+	Swizzle(Vec<u32>),
 	Normalize,
 	Add,
 	Subtract,
@@ -62,29 +85,146 @@ pub enum Node {
 	Sin,
 	Cos,
 	Tan,
+	Abs,
+	/// -1, 0 or 1 depending on the sign of the argument
+	Sign,
+	/// The argument minus its floor
+	Fract,
+	Sqrt,
+	/// One over the square root of the argument
+	InverseSqrt,
+	Exp,
+	/// Natural logarithm
+	Log,
 	Pow,
 	Min,
 	Max,
 	Length,
 	Distance,
+	/// Reflect the incident vector off the plane with the given normal
 	Reflect,
+	/// Refract the incident vector through the surface with the given normal
+	/// and ratio of indices of refraction, a scalar
 	Refract,
 	Mix,
+	/// 0 where the value is below the edge and 1 elsewhere, taking the edge
+	/// and then the value
+	Step,
+	/// Smooth Hermite interpolation from 0 to 1 as the value goes from the
+	/// lower to the upper edge, taking both edges and then the value
+	Smoothstep,
+	/// Whether the first scalar is less than the second, as a `Bool`
+	LessThan,
+	/// Whether the first scalar is greater than the second, as a `Bool`
+	GreaterThan,
+	/// Whether two scalars or booleans are equal, as a `Bool`
+	Equal,
+	/// The second argument if the `Bool` condition passed first holds,
+	/// otherwise the third
+	Select,
+	/// Evaluate a subgraph registered with [`Graph::register_subgraph`],
+	/// passing argument `n` as its input `n`
+	Call(SubgraphId),
 	Sample,
+	/// Matrix-vector or matrix-matrix product
+	MatMul,
+	Transpose,
+	Inverse,
+}
+
+impl Node {
+	/// Number of arguments the node takes, or `None` if it takes any number
+	pub fn arity(&self) -> Option<usize> {
+		Some(match self {
+			Node::Input(_, _)
+			| Node::Uniform(_, _)
+			| Node::TimeUniform
+			| Node::ResolutionUniform
+			| Node::Constant(_) => 0,
+			// Calls take as many arguments as the subgraph has inputs
+			Node::Construct(_) | Node::Call(_) => return None,
+			Node::Output(_, _)
+			| Node::Extract(_)
+			| Node::Swizzle(_)
+			| Node::Normalize
+			| Node::Floor
+			| Node::Ceil
+			| Node::Round
+			| Node::Sin
+			| Node::Cos
+			| Node::Tan
+			| Node::Abs
+			| Node::Sign
+			| Node::Fract
+			| Node::Sqrt
+			| Node::InverseSqrt
+			| Node::Exp
+			| Node::Log
+			| Node::Length
+			| Node::Transpose
+			| Node::Inverse => 1,
+			Node::Add
+			| Node::Subtract
+			| Node::Multiply
+			| Node::Divide
+			| Node::Modulus
+			| Node::Dot
+			| Node::Cross
+			| Node::Pow
+			| Node::Min
+			| Node::Max
+			| Node::Distance
+			| Node::Reflect
+			| Node::Sample
+			| Node::MatMul
+			| Node::Step
+			| Node::LessThan
+			| Node::GreaterThan
+			| Node::Equal => 2,
+			// Clamp takes the value, minimum and maximum, Mix both ends and
+			// the factor
+			Node::Clamp
+			| Node::Refract
+			| Node::Mix
+			| Node::Smoothstep
+			| Node::Select => 3,
+		})
+	}
 }
 
-/// Convenience wrapper for [`petgraph::Graph`](petgraph::graph::Graph)
+/// Convenience wrapper for a [`StableGraph`]
+///
+/// Removing nodes and edges leaves the indices of every other node and edge
+/// unchanged, so they can be kept across edits.
+///
+/// The serde representation mirrors petgraph's internals, so files meant to
+/// be kept should be saved with `Graph::to_json` instead.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Graph {
-	graph: PetGraph<Node, u32>,
+	graph: StableGraph<Node, u32>,
+	/// Identifiers given to inputs and outputs in generated code, keyed by
+	/// location
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub(crate) input_names: BTreeMap<u32, String>,
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub(crate) output_names: BTreeMap<u32, String>,
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub(crate) subgraphs: Vec<Subgraph>,
+	/// Names given to nodes by editors, which codegen ignores
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub(crate) labels: BTreeMap<NodeIndex<u32>, String>,
 }
 
 impl Default for Graph {
 	/// Create a new empty graph
 	fn default() -> Self {
 		Self {
-			graph: PetGraph::new(),
+			graph: StableGraph::new(),
+			input_names: BTreeMap::new(),
+			output_names: BTreeMap::new(),
+			subgraphs: Vec::new(),
+			labels: BTreeMap::new(),
 		}
 	}
 }
@@ -95,18 +235,345 @@ impl Graph {
 		self.graph.add_node(node)
 	}
 
-	/// Add an edge between two nodes in the graph, infering the result type of the origin node
+	/// Add an edge between two nodes in the graph, passing `from` as argument
+	/// `index` of `to` without any type checking
+	///
+	/// Use [`Graph::try_add_edge`] to reject edges whose types don't fit.
 	pub fn add_edge(
 		&mut self,
 		from: NodeIndex<u32>,
 		to: NodeIndex<u32>,
 		index: u32,
+	) -> EdgeIndex<u32> {
+		self.graph.add_edge(from, to, index)
+	}
+
+	pub(crate) fn remove_edge(&mut self, edge: EdgeIndex<u32>) {
+		self.graph.remove_edge(edge);
+	}
+
+	/// Remove a node along with every edge to and from it, returning it if
+	/// it was in the graph
+	///
+	/// Other nodes keep their indices, while the removed index may be reused
+	/// by a later [`Graph::add_node`], so the node's label is removed too.
+	pub fn remove_node(&mut self, index: NodeIndex<u32>) -> Option<Node> {
+		self.labels.remove(&index);
+		self.graph.remove_node(index)
+	}
+
+	/// Attach a label to a node, such as the name a user gave it in an
+	/// editor, replacing any previous label
+	pub fn set_label(
+		&mut self,
+		index: NodeIndex<u32>,
+		label: impl Into<String>,
+	) -> Result<()> {
+		if !self.graph.contains_node(index) {
+			bail!("There is no node {}", index.index());
+		}
+		self.labels.insert(index, label.into());
+
+		Ok(())
+	}
+
+	pub fn get_label(&self, index: NodeIndex<u32>) -> Option<&str> {
+		self.labels.get(&index).map(String::as_str)
+	}
+
+	/// Remove a node's label, returning it if it had one
+	pub fn remove_label(&mut self, index: NodeIndex<u32>) -> Option<String> {
+		self.labels.remove(&index)
+	}
+
+	/// Remove every edge passing `from` as an argument of `to`, returning
+	/// how many were removed
+	pub fn remove_edge_between(
+		&mut self,
+		from: NodeIndex<u32>,
+		to: NodeIndex<u32>,
+	) -> usize {
+		let mut removed = 0;
+		while let Some(edge) = self.graph.find_edge(from, to) {
+			self.graph.remove_edge(edge);
+			removed += 1;
+		}
+
+		removed
+	}
+
+	/// Move every outgoing edge of `old` over to `new`, keeping targets and
+	/// argument indices
+	pub(crate) fn replace_uses(
+		&mut self,
+		old: NodeIndex<u32>,
+		new: NodeIndex<u32>,
 	) {
-		self.graph.add_edge(from, to, index);
+		let uses: Vec<_> = self
+			.graph
+			.edges_directed(old, Outgoing)
+			.map(|edge| (edge.id(), edge.target(), *edge.weight()))
+			.collect();
+
+		for (edge, target, index) in uses.into_iter().rev() {
+			self.graph.remove_edge(edge);
+			self.graph.add_edge(new, target, index);
+		}
+	}
+
+	/// Iterate over every node in the graph along with its index
+	pub fn nodes(
+		&'_ self,
+	) -> impl Iterator<Item = (NodeIndex<u32>, &Node)> + '_ {
+		self.graph
+			.node_indices()
+			.map(move |index| (index, &self.graph[index]))
+	}
+
+	/// Number of nodes in the graph
+	pub fn node_count(&self) -> usize {
+		self.graph.node_count()
+	}
+
+	/// Number of edges in the graph
+	pub fn edge_count(&self) -> usize {
+		self.graph.edge_count()
+	}
+
+	/// Source, target and argument index of every edge
+	pub(crate) fn edges(
+		&'_ self,
+	) -> impl Iterator<Item = (NodeIndex<u32>, NodeIndex<u32>, u32)> + '_ {
+		(&self.graph)
+			.edge_references()
+			.map(|edge| (edge.source(), edge.target(), *edge.weight()))
+	}
+
+	/// Splice another graph into this one as a reusable function
+	///
+	/// Every `Input(location)` of `other` is replaced by the host node in
+	/// `input_map`, and the value of every `Output(location)` listed in
+	/// `output_map` is connected to the given host node and argument index.
+	/// The remaining nodes of `other` are copied over with fresh indices.
+	pub fn inline(
+		&mut self,
+		other: &Graph,
+		input_map: &HashMap<u32, NodeIndex<u32>>,
+		output_map: &HashMap<u32, (NodeIndex<u32>, u32)>,
+	) -> Result<()> {
+		for (index, node) in other.nodes() {
+			match node {
+				Node::Input(location, _)
+					if !input_map.contains_key(location) =>
+				{
+					bail!("Inlined graph input {location} is not mapped")
+				}
+				Node::Output(location, _)
+					if other.neighbors(index, Some(Outgoing)).count() > 0 =>
+				{
+					bail!("Inlined graph output {location} feeds another node")
+				}
+				// Its calls refer to its own subgraphs
+				Node::Call(_) => bail!("Inlined graphs can't call subgraphs"),
+				_ => {}
+			}
+		}
+
+		for location in output_map.keys() {
+			let found = other.nodes().any(
+				|(_, node)| matches!(node, Node::Output(l, _) if l == location),
+			);
+			if !found {
+				bail!("Inlined graph has no output {location}");
+			}
+		}
+
+		let mut remap = HashMap::new();
+		for (index, node) in other.nodes() {
+			let host = match node {
+				Node::Input(location, _) => input_map[location],
+				Node::Output(_, _) => continue,
+				node => {
+					let host = self.add_node(node.clone());
+					if let Some(label) = other.labels.get(&index) {
+						self.labels.insert(host, label.clone());
+					}
+
+					host
+				}
+			};
+
+			remap.insert(index, host);
+		}
+
+		for (source, target, index) in other.edges() {
+			let source = remap[&source];
+
+			match &other[target] {
+				Node::Output(location, _) => {
+					if let Some((target, index)) = output_map.get(location) {
+						self.add_edge(source, *target, *index);
+					}
+				}
+				_ => {
+					self.add_edge(source, remap[&target], index);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Name the input at `location` in generated code, instead of
+	/// `input_{location}`
+	///
+	/// The location is still what the input is bound by, so a graph drawing
+	/// `dyadikos_core` meshes reads `position` from 0, `color` from 1,
+	/// `normal` from 2, `uv` from 3 and `tangent` from 4.
+	pub fn name_input(
+		&mut self,
+		location: u32,
+		name: impl Into<String>,
+	) -> Result<()> {
+		let name = name.into();
+		self.check_name(&name, &self.input_names, location)?;
+		self.input_names.insert(location, name);
+
+		Ok(())
+	}
+
+	/// Name the output at `location` in generated code, instead of
+	/// `output_{location}`
+	pub fn name_output(
+		&mut self,
+		location: u32,
+		name: impl Into<String>,
+	) -> Result<()> {
+		let name = name.into();
+		if name == "position" {
+			bail!("\"position\" is reserved for the vertex position builtin");
+		}
+		self.check_name(&name, &self.output_names, location)?;
+		self.output_names.insert(location, name);
+
+		Ok(())
+	}
+
+	/// Identifier of the input at `location` in generated code
+	pub fn input_name(&self, location: u32) -> String {
+		match self.input_names.get(&location) {
+			Some(name) => name.clone(),
+			None => format!("input_{location}"),
+		}
+	}
+
+	/// Identifier of the output at `location` in generated code
+	pub fn output_name(&self, location: u32) -> String {
+		match self.output_names.get(&location) {
+			Some(name) => name.clone(),
+			None => format!("output_{location}"),
+		}
+	}
+
+	/// Reject names that aren't identifiers, are already taken, or could
+	/// clash with identifiers the code generator makes up
+	fn check_name(
+		&self,
+		name: &str,
+		names: &BTreeMap<u32, String>,
+		location: u32,
+	) -> Result<()> {
+		check_identifier(name)?;
+
+		// Renaming a slot to its current name is fine
+		let taken = self
+			.input_names
+			.values()
+			.chain(self.output_names.values())
+			.any(|other| other == name)
+			&& names.get(&location).map(String::as_str) != Some(name);
+		if taken {
+			bail!("{name:?} already names another input or output");
+		}
+
+		Ok(())
 	}
 
 	pub fn has_cycle(&self) -> bool {
-		algo::is_cyclic_directed(&self.graph)
+		self.find_cycle().is_some()
+	}
+
+	/// Nodes along one cycle of the graph, in edge order, so each node feeds
+	/// the next and the last feeds the first
+	///
+	/// The search visits nodes and their users by ascending index, so the
+	/// same graph always gives the same cycle. A self-loop is returned as a
+	/// single node.
+	pub fn find_cycle(&self) -> Option<Vec<NodeIndex<u32>>> {
+		let successors = |index: NodeIndex<u32>| {
+			let mut successors: Vec<_> =
+				self.graph.neighbors_directed(index, Outgoing).collect();
+			successors.sort();
+			successors.dedup();
+			successors
+		};
+
+		let mut visited = HashSet::new();
+		for root in self.graph.node_indices() {
+			if !visited.insert(root) {
+				continue;
+			}
+
+			// The current path, with each node's successors and how many of
+			// them have been followed
+			let mut path = vec![(root, successors(root), 0)];
+			while let Some((_, following, next)) = path.last_mut() {
+				let successor = match following.get(*next) {
+					Some(successor) => *successor,
+					None => {
+						path.pop();
+						continue;
+					}
+				};
+				*next += 1;
+
+				// An edge back into the path closes a cycle
+				if let Some(start) =
+					path.iter().position(|(node, _, _)| *node == successor)
+				{
+					return Some(
+						path[start..]
+							.iter()
+							.map(|(node, _, _)| *node)
+							.collect(),
+					);
+				}
+				if visited.insert(successor) {
+					path.push((successor, successors(successor), 0));
+				}
+			}
+		}
+
+		None
+	}
+
+	/// Every node in dependency order, so each node comes after all of its
+	/// arguments, such as inputs and constants before the outputs they feed
+	pub fn toposort(&self) -> Result<Vec<NodeIndex<u32>>> {
+		algo::toposort(&self.graph, None).map_err(|cycle| {
+			anyhow!("node {} is part of a cycle", cycle.node_id().index())
+		})
+	}
+
+	/// Groups of nodes that each form a cycle, including self-loops
+	pub(crate) fn cycles(&self) -> Vec<Vec<NodeIndex<u32>>> {
+		algo::tarjan_scc(&self.graph)
+			.into_iter()
+			.filter(|nodes| match nodes.as_slice() {
+				[node] => self.graph.contains_edge(*node, *node),
+				_ => true,
+			})
+			.collect()
 	}
 
 	/// List all the outputs of the graph
@@ -116,6 +583,56 @@ impl Graph {
 		})
 	}
 
+	/// Describe the graph in Graphviz DOT, with nodes labeled by their
+	/// variant, after their [`Graph::set_label`] label if they have one,
+	/// and edges by the argument index they pass to
+	///
+	/// Render it with `dot -Tpng graph.dot -o graph.png`.
+	pub fn to_dot(&self) -> String {
+		let label = |node: &Node| match node {
+			Node::Input(location, ty) => format!("Input({location}): {ty:?}"),
+			Node::Uniform(binding, ty) => {
+				format!("Uniform({binding}): {ty:?}")
+			}
+			Node::Output(location, ty) => format!("Output({location}): {ty:?}"),
+			Node::Constant(value) => format!("{value:?}"),
+			Node::Call(id) => match self.subgraph(*id) {
+				Some(subgraph) => format!("Call({})", subgraph.name),
+				None => format!("{node:?}"),
+			},
+			node => format!("{node:?}"),
+		};
+
+		format!(
+			"{:?}",
+			Dot::with_attr_getters(
+				&self.graph,
+				&[Config::NodeNoLabel, Config::EdgeNoLabel],
+				&|_, edge| format!("label = \"{}\"", edge.weight()),
+				&|_, (index, node)| match self.labels.get(&index) {
+					Some(name) => {
+						let name = name.escape_debug();
+						format!("label = \"{name}: {}\"", label(node))
+					}
+					None => format!("label = \"{}\"", label(node)),
+				},
+			)
+		)
+	}
+
+	/// Every node an output depends on, including the outputs themselves
+	pub(crate) fn reachable_from_outputs(&self) -> HashSet<NodeIndex<u32>> {
+		let mut reachable = HashSet::new();
+		let mut stack: Vec<_> = self.outputs().collect();
+		while let Some(index) = stack.pop() {
+			if reachable.insert(index) {
+				stack.extend(self.neighbors(index, Some(Incoming)));
+			}
+		}
+
+		reachable
+	}
+
 	pub fn arguments(
 		&'_ self,
 		index: NodeIndex<u32>,
@@ -132,12 +649,151 @@ impl Graph {
 		&self,
 		index: NodeIndex<u32>,
 		dir: Option<EdgeDirection>,
-	) -> petgraph::graph::Neighbors<u32> {
+	) -> Neighbors<u32> {
 		self.graph
 			.neighbors_directed(index, dir.unwrap_or(EdgeDirection::Incoming))
 	}
 }
 
+/// Reject names that aren't identifiers, are reserved by WGSL or GLSL, or
+/// could clash with identifiers the code generator makes up
+pub(crate) fn check_identifier(name: &str) -> Result<()> {
+	let mut chars = name.chars();
+	let identifier = matches!(
+		chars.next(),
+		Some(c) if c.is_ascii_alphabetic() || c == '_'
+	) && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+	if !identifier {
+		bail!("{name:?} is not an identifier");
+	}
+
+	let reserved = [
+		"input_",
+		"output_",
+		"uniform_",
+		"subgraph_",
+		"node_",
+		"gl_",
+		"__",
+	];
+	let listed = |words: &str| words.split_whitespace().any(|w| w == name);
+	if listed(GENERATED_WORDS) || reserved.iter().any(|p| name.starts_with(p)) {
+		bail!("{name:?} is reserved for generated identifiers");
+	}
+
+	if listed(RESERVED_WORDS) || listed(BUILTIN_FUNCTIONS) {
+		bail!("{name:?} is reserved in WGSL or GLSL");
+	}
+	if is_builtin_type(name) {
+		bail!("{name:?} names a WGSL or GLSL type");
+	}
+
+	Ok(())
+}
+
+/// Identifiers the code generator declares in every shader
+const GENERATED_WORDS: &str =
+	"output vertex frame main vs_main fs_main VertexOutput \
+	FragmentOutput FrameGlobals";
+
+/// Keywords and reserved words of WGSL and GLSL 4.50, separated by
+/// whitespace, excluding the types [`is_builtin_type`] recognizes
+const RESERVED_WORDS: &str =
+	"bool break case const continue default discard do else enum false \
+	for if in return struct switch true while \
+	alias array atomic const_assert continuing diagnostic enable f16 \
+	f32 fn i32 let loop override ptr requires sampler \
+	sampler_comparison u32 var \
+	NULL Self abstract active alignas alignof as asm asm_fragment \
+	async attribute auto await become binding_array cast catch class \
+	co_await co_return co_yield coherent column_major common compile \
+	compile_fragment concept const_cast consteval constexpr constinit \
+	crate debugger decltype delete demote demote_to_helper \
+	dynamic_cast explicit export extends extern external fallthrough \
+	filter final finally friend from fxgroup get goto groupshared \
+	highp impl implements import inline instanceof interface layout \
+	lowp macro macro_rules match mediump meta mod module move mut \
+	mutable namespace new nil noexcept noinline nointerpolation \
+	noperspective null nullptr of operator package packoffset \
+	partition pass patch pixelfragment precise precision premerge priv \
+	protected pub public readonly ref regardless register \
+	reinterpret_cast require resource restrict self set shared sizeof \
+	smooth snorm static static_assert static_cast std subroutine super \
+	target template this thread_local throw trait try type typedef \
+	typeid typename typeof union unless unorm unsafe unsized use using \
+	varying virtual volatile wgsl where with writeonly yield \
+	atomic_uint buffer centroid double flat float inout int invariant \
+	out sample uint uniform void \
+	input long short half fixed unsigned superp";
+
+/// Builtin functions the generated code calls, which a parameter or
+/// function with the same name would shadow
+const BUILTIN_FUNCTIONS: &str =
+	"abs ceil clamp cos cross distance dot exp floor fract inverse \
+	inverseSqrt inversesqrt length log max min mix normalize pow \
+	reflect refract round select sign sin smoothstep sqrt step tan \
+	texture textureSample transpose";
+
+/// Whether the name is one of the vector, matrix, sampler, image or texture
+/// types of either language, like `vec3f`, `dmat4x3`, `isampler2DArray` or
+/// `texture_depth_2d`
+fn is_builtin_type(name: &str) -> bool {
+	fn digit(rest: &str) -> Option<&str> {
+		let mut chars = rest.chars();
+		matches!(chars.next(), Some('2'..='4')).then(|| chars.as_str())
+	}
+	// A vector or matrix size, then an optional WGSL component suffix
+	let sized = |rest: &str, matrix: bool| {
+		let Some(mut rest) = digit(rest) else {
+			return false;
+		};
+		if matrix {
+			if let Some(columns) = rest.strip_prefix('x') {
+				match digit(columns) {
+					Some(after) => rest = after,
+					None => return false,
+				}
+			}
+		}
+		matches!(rest, "" | "f" | "h" | "i" | "u")
+	};
+
+	let unprefixed = name
+		.strip_prefix(|c| matches!(c, 'b' | 'i' | 'u' | 'd'))
+		.unwrap_or(name);
+	if let Some(rest) = unprefixed.strip_prefix("vec") {
+		return sized(rest, false);
+	}
+	if let Some(rest) = unprefixed.strip_prefix("mat") {
+		return sized(rest, true);
+	}
+
+	// GLSL samplers, images and separate textures, like `sampler2D` or
+	// `usamplerCubeArray`
+	let unprefixed = name.strip_prefix(|c| matches!(c, 'i' | 'u'));
+	for kind in ["sampler", "image", "texture"] {
+		let rest = name
+			.strip_prefix(kind)
+			.or_else(|| unprefixed?.strip_prefix(kind));
+		if let Some(rest) = rest {
+			if rest.starts_with(|c: char| c.is_ascii_digit())
+				|| rest.starts_with("Cube")
+				|| rest.starts_with("Buffer")
+			{
+				return true;
+			}
+		}
+	}
+
+	// WGSL textures, like `texture_2d` or `texture_storage_2d_array`
+	name.strip_prefix("texture_").is_some_and(|rest| {
+		rest.starts_with(|c: char| c.is_ascii_digit())
+			|| ["cube", "depth_", "storage_", "multisampled_", "external"]
+				.iter()
+				.any(|p| rest.starts_with(p))
+	})
+}
+
 impl Index<NodeIndex<u32>> for Graph {
 	type Output = Node;
 
@@ -146,3 +802,64 @@ impl Index<NodeIndex<u32>> for Graph {
 		&self.graph[index]
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn toposort_orders_arguments_first() {
+		let mut graph = Graph::default();
+		let output =
+			graph.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		let multiply = graph.add_node(Node::Multiply);
+		let input =
+			graph.add_node(Node::Input(0, Box::new(TypeName::Float(false))));
+		graph.add_edge(input, multiply, 0);
+		graph.add_edge(input, multiply, 1);
+		graph.add_edge(multiply, output, 0);
+
+		assert_eq!(graph.toposort().unwrap(), [input, multiply, output]);
+	}
+	#[test]
+	fn inline_double() {
+		// Output(0) = Input(0) + Input(0)
+		let mut double = Graph::default();
+		let input =
+			double.add_node(Node::Input(0, Box::new(TypeName::Float(false))));
+		let add = double.add_node(Node::Add);
+		let output =
+			double.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		double.add_edge(input, add, 0);
+		double.add_edge(input, add, 1);
+		double.add_edge(add, output, 0);
+
+		// Output(0) = double(Input(0)) + 1
+		let mut host = Graph::default();
+		let input =
+			host.add_node(Node::Input(0, Box::new(TypeName::Float(false))));
+		let one = host.add_node(Node::Constant(TypedValue::Float(1.0)));
+		let add = host.add_node(Node::Add);
+		let output =
+			host.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		host.add_edge(one, add, 1);
+		host.add_edge(add, output, 0);
+
+		host.inline(
+			&double,
+			&HashMap::from([(0, input)]),
+			&HashMap::from([(0, (add, 0))]),
+		)
+		.unwrap();
+
+		// Only the inlined addition is new
+		assert_eq!(host.node_count(), 5);
+		let outputs = host
+			.evaluate(
+				&HashMap::from([(0, TypedValue::Float(3.0))]),
+				&HashMap::new(),
+			)
+			.unwrap();
+		assert_eq!(outputs[&0], TypedValue::Float(7.0));
+	}
+}
@@ -0,0 +1,653 @@
+use crate::{
+	graph::{Dim, Graph, Node, TypeName, TypedValue},
+	subgraph::SubgraphId,
+};
+use petgraph::graph::NodeIndex;
+use std::{cmp::Ordering, collections::HashMap, fmt};
+
+/// Types inferred so far, so nodes shared by several others are only
+/// inferred once
+pub(crate) type Types = HashMap<NodeIndex<u32>, Result<TypeName, TypeError>>;
+
+/// Reason a node's result type could not be inferred
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+	/// The graph contains a cycle passing through this node
+	Cycle { node: NodeIndex<u32> },
+	/// The node has no edge for one of its arguments
+	MissingArgument {
+		node: NodeIndex<u32>,
+		argument: usize,
+	},
+	/// The node cannot operate on the given argument types
+	Unsupported {
+		node: NodeIndex<u32>,
+		arguments: Vec<TypeName>,
+	},
+	/// The node extracts a component the source vector does not have
+	ComponentOutOfBounds {
+		node: NodeIndex<u32>,
+		component: u32,
+		components: u32,
+	},
+	/// The node swizzles into a vector with no or more than 4 components
+	SwizzleLength { node: NodeIndex<u32>, length: usize },
+	/// The node calls a subgraph that isn't registered, or isn't valid
+	UnknownSubgraph {
+		node: NodeIndex<u32>,
+		id: SubgraphId,
+	},
+	/// Connecting `from` as argument `argument` of `to` gives `to` argument
+	/// types it cannot operate on, returned by [`Graph::try_add_edge`]
+	Mismatch {
+		from: NodeIndex<u32>,
+		to: NodeIndex<u32>,
+		argument: u32,
+		found: TypeName,
+		arguments: Vec<TypeName>,
+	},
+}
+
+impl fmt::Display for TypeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TypeError::Cycle { node } => {
+				write!(f, "node {} is part of a cycle", node.index())
+			}
+			TypeError::MissingArgument { node, argument } => write!(
+				f,
+				"node {} is missing argument {argument}",
+				node.index()
+			),
+			TypeError::Unsupported { node, arguments } => write!(
+				f,
+				"node {} does not accept arguments of type {arguments:?}",
+				node.index()
+			),
+			TypeError::ComponentOutOfBounds {
+				node,
+				component,
+				components,
+			} => write!(
+				f,
+				"node {} extracts component {component} from a vector with \
+				 {components} components",
+				node.index()
+			),
+			TypeError::SwizzleLength { node, length } => write!(
+				f,
+				"node {} swizzles {length} components, but vectors have 2 \
+				 to 4",
+				node.index()
+			),
+			TypeError::UnknownSubgraph { node, id } => write!(
+				f,
+				"node {} calls subgraph {}, which is not registered or not a \
+				 valid subgraph",
+				node.index(),
+				id.0
+			),
+			TypeError::Mismatch {
+				from,
+				to,
+				argument,
+				found,
+				arguments,
+			} => write!(
+				f,
+				"node {} of type {found:?} cannot be argument {argument} of \
+				 node {}, which does not accept arguments of type \
+				 {arguments:?}",
+				from.index(),
+				to.index()
+			),
+		}
+	}
+}
+
+impl std::error::Error for TypeError {}
+
+impl TypeError {
+	/// The node the error was found at
+	pub fn node(&self) -> NodeIndex<u32> {
+		match self {
+			TypeError::Cycle { node }
+			| TypeError::MissingArgument { node, .. }
+			| TypeError::Unsupported { node, .. }
+			| TypeError::ComponentOutOfBounds { node, .. }
+			| TypeError::SwizzleLength { node, .. }
+			| TypeError::UnknownSubgraph { node, .. }
+			| TypeError::Mismatch { to: node, .. } => *node,
+		}
+	}
+}
+
+fn float() -> TypeName {
+	TypeName::Float(false)
+}
+
+fn is_numeric(ty: &TypeName) -> bool {
+	matches!(ty, TypeName::Vec(_) | TypeName::Float(_))
+}
+
+/// Number of scalars held by a vector or scalar
+fn scalar_count(ty: &TypeName) -> Option<u32> {
+	match ty {
+		TypeName::Vec(n) => Some(*n),
+		TypeName::Float(_) | TypeName::Int(_) | TypeName::Bool => Some(1),
+		_ => None,
+	}
+}
+
+/// Whether `operand` can be combined with `ty` per component, either by
+/// matching it or as a scalar applied to every component
+fn applies_to(operand: &TypeName, ty: &TypeName) -> bool {
+	operand == ty
+		|| matches!((operand, ty), (TypeName::Float(_), TypeName::Vec(_)))
+}
+
+impl TypedValue {
+	/// Type of the constant value
+	pub fn type_name(&self) -> TypeName {
+		match self {
+			TypedValue::Bool(_) => TypeName::Bool,
+			TypedValue::Float(_) => float(),
+			TypedValue::Vec2(_, _) => TypeName::Vec(2),
+			TypedValue::Vec3(_, _, _) => TypeName::Vec(3),
+			TypedValue::Vec4(_, _, _, _) => TypeName::Vec(4),
+			TypedValue::Mat2(_) => TypeName::Mat(2, Box::new(float())),
+			TypedValue::Mat3(_) => TypeName::Mat(3, Box::new(float())),
+			TypedValue::Mat4(_) => TypeName::Mat(4, Box::new(float())),
+		}
+	}
+}
+
+impl Graph {
+	/// Infer the type of the value produced by a node
+	pub fn infer_type(
+		&self,
+		index: NodeIndex<u32>,
+	) -> Result<TypeName, TypeError> {
+		if let Some(cycle) = self.find_cycle() {
+			return Err(TypeError::Cycle { node: cycle[0] });
+		}
+
+		self.infer(index)
+	}
+
+	/// Add an edge, rejecting it if the target node can no longer be typed
+	///
+	/// Nodes that are still missing arguments are not treated as errors, so
+	/// operands can be connected one at a time.
+	pub fn try_add_edge(
+		&mut self,
+		from: NodeIndex<u32>,
+		to: NodeIndex<u32>,
+		index: u32,
+	) -> Result<(), TypeError> {
+		let edge = self.add_edge(from, to, index);
+
+		let result = match self.infer_type(to) {
+			Ok(_) | Err(TypeError::MissingArgument { .. }) => Ok(()),
+			Err(TypeError::Unsupported { node, arguments }) if node == to => {
+				match self.infer(from) {
+					Ok(found) => Err(TypeError::Mismatch {
+						from,
+						to,
+						argument: index,
+						found,
+						arguments,
+					}),
+					Err(error) => Err(error),
+				}
+			}
+			Err(error) => Err(error),
+		};
+
+		if result.is_err() {
+			self.remove_edge(edge);
+		}
+
+		result
+	}
+
+	pub(crate) fn infer(
+		&self,
+		index: NodeIndex<u32>,
+	) -> Result<TypeName, TypeError> {
+		self.infer_with(index, &mut Types::new())
+	}
+
+	/// Infer the type of a node, reusing and adding to the types already
+	/// inferred
+	///
+	/// A node reached again while its own type is being inferred is part
+	/// of a cycle.
+	pub(crate) fn infer_with(
+		&self,
+		index: NodeIndex<u32>,
+		types: &mut Types,
+	) -> Result<TypeName, TypeError> {
+		if let Some(ty) = types.get(&index) {
+			return ty.clone();
+		}
+
+		types.insert(index, Err(TypeError::Cycle { node: index }));
+		let ty = self.infer_node(index, types);
+		types.insert(index, ty.clone());
+
+		ty
+	}
+
+	fn infer_node(
+		&self,
+		index: NodeIndex<u32>,
+		types: &mut Types,
+	) -> Result<TypeName, TypeError> {
+		let args = self.arguments(index).collect::<Vec<_>>();
+		let mut operand = |i: usize| match args.get(i) {
+			Some(arg) => self.infer_with(*arg, types),
+			None => Err(TypeError::MissingArgument {
+				node: index,
+				argument: i,
+			}),
+		};
+		let unsupported = |arguments: Vec<TypeName>| TypeError::Unsupported {
+			node: index,
+			arguments,
+		};
+
+		Ok(match &self[index] {
+			Node::Input(_, ty) | Node::Uniform(_, ty) => ty.as_ref().clone(),
+			// Outputs without a value are reported by validation instead
+			Node::Output(_, ty) => match args.first() {
+				Some(_) if operand(0)? != **ty => {
+					return Err(unsupported(vec![operand(0)?]))
+				}
+				_ => ty.as_ref().clone(),
+			},
+			Node::Constant(value) => value.type_name(),
+			// Vectors are built from scalars and vectors adding up to their
+			// size, or splatted from a single float, matrices from columns or
+			// from every component, and scalars converted from a scalar
+			Node::Construct(ty) => {
+				let arguments = (0..args.len())
+					.map(&mut operand)
+					.collect::<Result<Vec<_>, _>>()?;
+				let all = |ty: &TypeName| {
+					arguments.iter().all(|argument| argument == ty)
+				};
+				let (found, expected) = match ty.as_ref() {
+					TypeName::Vec(_) if arguments == [float()] => (1, 1),
+					TypeName::Vec(n)
+						if arguments.iter().all(|argument| {
+							*argument == float()
+								|| matches!(argument, TypeName::Vec(_))
+						}) =>
+					{
+						let found = arguments.iter().filter_map(scalar_count);
+
+						(found.sum(), *n)
+					}
+					TypeName::Mat(n, component)
+						if **component == float()
+							&& all(&TypeName::Vec(*n)) =>
+					{
+						(arguments.len() as u32, *n)
+					}
+					TypeName::Mat(n, component)
+						if **component == float() && all(&float()) =>
+					{
+						(arguments.len() as u32, n * n)
+					}
+					TypeName::Float(_) | TypeName::Int(_) | TypeName::Bool
+						if arguments.iter().all(|argument| {
+							scalar_count(argument) == Some(1)
+						}) =>
+					{
+						(arguments.len() as u32, 1)
+					}
+					_ => return Err(unsupported(arguments)),
+				};
+
+				match found.cmp(&expected) {
+					Ordering::Less => {
+						return Err(TypeError::MissingArgument {
+							node: index,
+							argument: arguments.len(),
+						})
+					}
+					Ordering::Greater => return Err(unsupported(arguments)),
+					Ordering::Equal => ty.as_ref().clone(),
+				}
+			}
+			Node::Extract(component) => match operand(0)? {
+				TypeName::Vec(components) if *component < components => float(),
+				TypeName::Vec(components) => {
+					return Err(TypeError::ComponentOutOfBounds {
+						node: index,
+						component: *component,
+						components,
+					})
+				}
+				ty => return Err(unsupported(vec![ty])),
+			},
+			// A single component gives a scalar, like Extract
+			Node::Swizzle(mask) => match operand(0)? {
+				TypeName::Vec(components) => {
+					if mask.is_empty() || mask.len() > 4 {
+						return Err(TypeError::SwizzleLength {
+							node: index,
+							length: mask.len(),
+						});
+					}
+					if let Some(component) =
+						mask.iter().find(|component| **component >= components)
+					{
+						return Err(TypeError::ComponentOutOfBounds {
+							node: index,
+							component: *component,
+							components,
+						});
+					}
+
+					match mask.len() {
+						1 => float(),
+						length => TypeName::Vec(length as u32),
+					}
+				}
+				ty => return Err(unsupported(vec![ty])),
+			},
+			Node::Dot => match (operand(0)?, operand(1)?) {
+				(lhs, rhs) if lhs == rhs && matches!(lhs, TypeName::Vec(_)) => {
+					float()
+				}
+				(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+			},
+			Node::Cross => match (operand(0)?, operand(1)?) {
+				(TypeName::Vec(3), TypeName::Vec(3)) => TypeName::Vec(3),
+				(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+			},
+			// Either side can be a float applied to every component, and
+			// matrices can be added and subtracted, but products of matrices
+			// go through MatMul
+			Node::Add
+			| Node::Subtract
+			| Node::Multiply
+			| Node::Divide
+			| Node::Modulus => {
+				let (lhs, rhs) = (operand(0)?, operand(1)?);
+				let matrices =
+					matches!(self[index], Node::Add | Node::Subtract)
+						&& matches!(lhs, TypeName::Mat(_, _));
+				let same =
+					lhs == rhs && (matrices || matches!(lhs, TypeName::Int(_)));
+				if same || (is_numeric(&lhs) && applies_to(&rhs, &lhs)) {
+					lhs
+				} else if is_numeric(&rhs) && applies_to(&lhs, &rhs) {
+					rhs
+				} else {
+					return Err(unsupported(vec![lhs, rhs]));
+				}
+			}
+			Node::Pow => match (operand(0)?, operand(1)?) {
+				(lhs, rhs) if lhs == rhs && is_numeric(&lhs) => lhs,
+				(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+			},
+			Node::Floor
+			| Node::Ceil
+			| Node::Round
+			| Node::Sin
+			| Node::Cos
+			| Node::Tan
+			| Node::Abs
+			| Node::Sign
+			| Node::Fract
+			| Node::Sqrt
+			| Node::InverseSqrt
+			| Node::Exp
+			| Node::Log => match operand(0)? {
+				ty if is_numeric(&ty) => ty,
+				ty => return Err(unsupported(vec![ty])),
+			},
+			// Scalars are treated as one-component vectors
+			Node::Length => match operand(0)? {
+				TypeName::Vec(_) | TypeName::Float(_) => float(),
+				ty => return Err(unsupported(vec![ty])),
+			},
+			Node::Distance => match (operand(0)?, operand(1)?) {
+				(lhs, rhs)
+					if lhs == rhs
+						&& matches!(
+							lhs,
+							TypeName::Vec(_) | TypeName::Float(_)
+						) =>
+				{
+					float()
+				}
+				(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+			},
+			// The bounds of Clamp, the factor of Mix and either side of Min
+			// and Max can also be scalars, applied to every component
+			Node::Clamp => {
+				let value = operand(0)?;
+				let (min, max) = (operand(1)?, operand(2)?);
+				if !is_numeric(&value)
+					|| !applies_to(&min, &value)
+					|| !applies_to(&max, &value)
+				{
+					return Err(unsupported(vec![value, min, max]));
+				}
+
+				value
+			}
+			Node::Mix => {
+				let (a, b, t) = (operand(0)?, operand(1)?, operand(2)?);
+				if !is_numeric(&a) || a != b || !applies_to(&t, &a) {
+					return Err(unsupported(vec![a, b, t]));
+				}
+
+				a
+			}
+			// Edges can be scalars applied to every component of the value
+			Node::Step => {
+				let (edge, value) = (operand(0)?, operand(1)?);
+				if !is_numeric(&value) || !applies_to(&edge, &value) {
+					return Err(unsupported(vec![edge, value]));
+				}
+
+				value
+			}
+			Node::Smoothstep => {
+				let (low, high) = (operand(0)?, operand(1)?);
+				let value = operand(2)?;
+				if !is_numeric(&value)
+					|| !applies_to(&low, &value)
+					|| !applies_to(&high, &value)
+				{
+					return Err(unsupported(vec![low, high, value]));
+				}
+
+				value
+			}
+			// Booleans only come in scalars, so vectors can't be compared
+			Node::LessThan | Node::GreaterThan | Node::Equal => {
+				match (operand(0)?, operand(1)?) {
+					(lhs, rhs)
+						if lhs == rhs
+							&& (matches!(
+								lhs,
+								TypeName::Float(_) | TypeName::Int(_)
+							) || matches!(
+								(&self[index], &lhs),
+								(Node::Equal, TypeName::Bool)
+							)) =>
+					{
+						TypeName::Bool
+					}
+					(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+				}
+			}
+			Node::Call(id) => {
+				let (parameters, output) = match self
+					.subgraph(*id)
+					.map(|subgraph| subgraph.graph.signature())
+				{
+					Some(Ok(signature)) => signature,
+					_ => {
+						return Err(TypeError::UnknownSubgraph {
+							node: index,
+							id: *id,
+						})
+					}
+				};
+				let arguments = (0..args.len())
+					.map(operand)
+					.collect::<Result<Vec<_>, _>>()?;
+				if arguments.len() < parameters.len() {
+					return Err(TypeError::MissingArgument {
+						node: index,
+						argument: arguments.len(),
+					});
+				}
+				if arguments != parameters {
+					return Err(unsupported(arguments));
+				}
+
+				output
+			}
+			Node::Select => {
+				let condition = operand(0)?;
+				let (accept, reject) = (operand(1)?, operand(2)?);
+				if condition != TypeName::Bool || accept != reject {
+					return Err(unsupported(vec![condition, accept, reject]));
+				}
+
+				accept
+			}
+			Node::Min | Node::Max => match (operand(0)?, operand(1)?) {
+				(lhs, rhs) if is_numeric(&lhs) && applies_to(&rhs, &lhs) => lhs,
+				(lhs, rhs) if is_numeric(&rhs) && applies_to(&lhs, &rhs) => rhs,
+				(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+			},
+			Node::Reflect => match (operand(0)?, operand(1)?) {
+				(incident, normal)
+					if incident == normal
+						&& matches!(incident, TypeName::Vec(_)) =>
+				{
+					incident
+				}
+				(incident, normal) => {
+					return Err(unsupported(vec![incident, normal]))
+				}
+			},
+			Node::Refract => {
+				let (incident, normal) = (operand(0)?, operand(1)?);
+				let eta = operand(2)?;
+				match (&incident, &eta) {
+					(TypeName::Vec(_), TypeName::Float(_))
+						if incident == normal =>
+					{
+						incident
+					}
+					_ => return Err(unsupported(vec![incident, normal, eta])),
+				}
+			}
+			Node::Normalize => match operand(0)? {
+				ty @ TypeName::Vec(_) => ty,
+				ty => return Err(unsupported(vec![ty])),
+			},
+			// The coordinate has one component per dimension of the texture
+			Node::Sample => {
+				let sampler = operand(0)?;
+				let size = match &sampler {
+					TypeName::Sampler(_, Dim::Dim1D) => 1,
+					TypeName::Sampler(_, Dim::Dim2D | Dim::DimRect) => 2,
+					TypeName::Sampler(_, Dim::Dim3D | Dim::DimCube) => 3,
+					_ => return Err(unsupported(vec![sampler])),
+				};
+				let coordinate = operand(1)?;
+				let matches = match coordinate {
+					TypeName::Float(false) => size == 1,
+					TypeName::Vec(n) => size == n,
+					_ => false,
+				};
+				if !matches {
+					return Err(unsupported(vec![sampler, coordinate]));
+				}
+
+				TypeName::Vec(4)
+			}
+			Node::TimeUniform => float(),
+			Node::ResolutionUniform => TypeName::Vec(2),
+			// Vectors only hold single precision floats, so they can't be
+			// multiplied by double precision matrices
+			Node::MatMul => match (operand(0)?, operand(1)?) {
+				(TypeName::Mat(n, component), TypeName::Vec(m))
+				| (TypeName::Vec(m), TypeName::Mat(n, component))
+					if n == m && *component == float() =>
+				{
+					TypeName::Vec(n)
+				}
+				(TypeName::Mat(n, a), TypeName::Mat(m, b))
+					if n == m && a == b =>
+				{
+					TypeName::Mat(n, a)
+				}
+				(lhs, rhs) => return Err(unsupported(vec![lhs, rhs])),
+			},
+			Node::Transpose | Node::Inverse => match operand(0)? {
+				ty @ TypeName::Mat(_, _) => ty,
+				ty => return Err(unsupported(vec![ty])),
+			},
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::validate::ValidationIssue;
+
+	#[test]
+	fn extract_out_of_bounds() {
+		let mut graph = Graph::default();
+		let vector = graph.add_node(Node::Constant(TypedValue::Vec2(1.0, 2.0)));
+		let extract = graph.add_node(Node::Extract(3));
+		let output =
+			graph.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		graph.add_edge(vector, extract, 0);
+		graph.add_edge(extract, output, 0);
+
+		let error = TypeError::ComponentOutOfBounds {
+			node: extract,
+			component: 3,
+			components: 2,
+		};
+		assert_eq!(graph.infer(extract), Err(error.clone()));
+		assert_eq!(graph.validate(), [ValidationIssue::Type(error)]);
+	}
+	#[test]
+	fn length_of_vec3_is_float() {
+		let mut graph = Graph::default();
+		let vector = graph.add_node(Node::Input(0, Box::new(TypeName::Vec(3))));
+		let length = graph.add_node(Node::Length);
+		graph.add_edge(vector, length, 0);
+
+		assert_eq!(graph.infer(length), Ok(TypeName::Float(false)));
+	}
+	#[test]
+	fn mat4_times_vec4_is_vec4() {
+		let mut graph = Graph::default();
+		let matrix = graph.add_node(Node::Uniform(
+			0,
+			Box::new(TypeName::Mat(4, Box::new(TypeName::Float(false)))),
+		));
+		let vector = graph.add_node(Node::Input(0, Box::new(TypeName::Vec(4))));
+		let product = graph.add_node(Node::MatMul);
+		graph.add_edge(matrix, product, 0);
+		graph.add_edge(vector, product, 1);
+
+		assert_eq!(graph.infer(product), Ok(TypeName::Vec(4)));
+	}
+}
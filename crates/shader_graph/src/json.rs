@@ -0,0 +1,170 @@
+use crate::graph::{Graph, Node};
+use anyhow::{bail, Context, Result};
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Version of the format written by [`Graph::to_json`]
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Upgrades from each older version to the next, where the upgrade from
+/// version `n` is at index `n - 1`
+///
+/// Changing the format means bumping [`FORMAT_VERSION`] and adding a
+/// function here that rewrites files of the previous version, so saved
+/// graphs keep loading.
+const MIGRATIONS: &[fn(Value) -> Result<Value>] = &[];
+
+/// On-disk form of a graph, which only changes along with
+/// [`FORMAT_VERSION`]
+#[derive(Serialize, Deserialize)]
+struct GraphFile {
+	version: u32,
+	#[serde(flatten)]
+	body: GraphBody,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GraphBody {
+	nodes: Vec<Node>,
+	/// Source and target positions in `nodes` and the argument index
+	edges: Vec<(u32, u32, u32)>,
+	/// Locations and names of named inputs and outputs
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	input_names: Vec<(u32, String)>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	output_names: Vec<(u32, String)>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	subgraphs: Vec<SubgraphBody>,
+	/// Positions in `nodes` and labels of labeled nodes
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	labels: Vec<(u32, String)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SubgraphBody {
+	name: String,
+	#[serde(flatten)]
+	graph: GraphBody,
+}
+
+impl GraphBody {
+	fn new(graph: &Graph) -> Self {
+		let positions: HashMap<NodeIndex<u32>, u32> = graph
+			.nodes()
+			.enumerate()
+			.map(|(position, (index, _))| (index, position as u32))
+			.collect();
+
+		Self {
+			nodes: graph.nodes().map(|(_, node)| node.clone()).collect(),
+			edges: graph
+				.edges()
+				.map(|(from, to, index)| {
+					(positions[&from], positions[&to], index)
+				})
+				.collect(),
+			input_names: graph.input_names.clone().into_iter().collect(),
+			output_names: graph.output_names.clone().into_iter().collect(),
+			subgraphs: graph
+				.subgraphs()
+				.map(|(_, subgraph)| SubgraphBody {
+					name: subgraph.name.clone(),
+					graph: GraphBody::new(&subgraph.graph),
+				})
+				.collect(),
+			labels: graph
+				.labels
+				.iter()
+				.map(|(index, label)| (positions[index], label.clone()))
+				.collect(),
+		}
+	}
+
+	fn into_graph(self) -> Result<Graph> {
+		let mut graph = Graph::default();
+		let indices: Vec<_> = self
+			.nodes
+			.into_iter()
+			.map(|node| graph.add_node(node))
+			.collect();
+		let node = |position: u32| {
+			indices
+				.get(position as usize)
+				.copied()
+				.with_context(|| format!("There is no node {position}"))
+		};
+		for (from, to, index) in self.edges {
+			graph.add_edge(node(from)?, node(to)?, index);
+		}
+		for (position, label) in self.labels {
+			graph.set_label(node(position)?, label)?;
+		}
+
+		for (location, name) in self.input_names {
+			graph.name_input(location, name)?;
+		}
+		for (location, name) in self.output_names {
+			graph.name_output(location, name)?;
+		}
+		for subgraph in self.subgraphs {
+			let body = subgraph.graph.into_graph()?;
+			graph.register_subgraph(subgraph.name, body)?;
+		}
+
+		Ok(graph)
+	}
+}
+
+impl Graph {
+	/// Save the graph in a versioned JSON format that doesn't depend on how
+	/// petgraph stores graphs
+	///
+	/// Nodes are listed in index order, so a graph without removed nodes
+	/// keeps its indices when loaded with [`Graph::from_json`], while the
+	/// gaps left by removed nodes are closed up.
+	pub fn to_json(&self) -> String {
+		let file = GraphFile {
+			version: FORMAT_VERSION,
+			body: GraphBody::new(self),
+		};
+
+		serde_json::to_string_pretty(&file)
+			.expect("graphs always serialize to JSON")
+	}
+
+	/// Load a graph saved by [`Graph::to_json`], upgrading files written by
+	/// older versions
+	///
+	/// Names and subgraphs are checked the same way as when they are added
+	/// through [`Graph::name_input`] and [`Graph::register_subgraph`].
+	pub fn from_json(json: &str) -> Result<Graph> {
+		let mut value: Value =
+			serde_json::from_str(json).context("Invalid JSON")?;
+
+		let mut version = match value.get("version").and_then(Value::as_u64) {
+			Some(version) => version as u32,
+			None => bail!("Graph file has no version"),
+		};
+		if version == 0 || version > FORMAT_VERSION {
+			bail!(
+				"Unsupported graph file version {version}, the newest \
+				 supported is {FORMAT_VERSION}"
+			);
+		}
+		while version < FORMAT_VERSION {
+			value =
+				MIGRATIONS[version as usize - 1](value).with_context(|| {
+					format!("Failed to upgrade graph file version {version}")
+				})?;
+			version += 1;
+			value["version"] = version.into();
+		}
+
+		let file: GraphFile =
+			serde_json::from_value(value).context("Invalid graph file")?;
+
+		file.body.into_graph()
+	}
+}
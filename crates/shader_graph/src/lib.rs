@@ -1 +1,13 @@
+pub mod builder;
+pub mod codegen;
+#[cfg(feature = "compilation")]
+pub mod compile;
+pub mod eval;
 pub mod graph;
+pub mod infer;
+#[cfg(feature = "serialize")]
+pub mod json;
+pub mod optimize;
+pub mod stats;
+pub mod subgraph;
+pub mod validate;
@@ -0,0 +1,223 @@
+use crate::graph::{Graph, Node, TypedValue};
+use petgraph::{graph::NodeIndex, Outgoing};
+use std::collections::HashMap;
+
+/// The value with every component rounded to `f32`, or `None` when a
+/// component isn't finite at that precision
+fn single_precision(value: &TypedValue) -> Option<TypedValue> {
+	let round = |x: f64| {
+		let x = x as f32;
+		x.is_finite().then_some(x as f64)
+	};
+	let columns = |columns: &[f64]| {
+		columns
+			.iter()
+			.map(|x| round(*x))
+			.collect::<Option<Vec<_>>>()
+	};
+
+	Some(match value {
+		TypedValue::Bool(value) => TypedValue::Bool(*value),
+		TypedValue::Float(x) => TypedValue::Float(round(*x)?),
+		TypedValue::Vec2(x, y) => TypedValue::Vec2(round(*x)?, round(*y)?),
+		TypedValue::Vec3(x, y, z) => {
+			TypedValue::Vec3(round(*x)?, round(*y)?, round(*z)?)
+		}
+		TypedValue::Vec4(x, y, z, w) => {
+			TypedValue::Vec4(round(*x)?, round(*y)?, round(*z)?, round(*w)?)
+		}
+		TypedValue::Mat2(m) => TypedValue::Mat2(columns(m)?.try_into().ok()?),
+		TypedValue::Mat3(m) => TypedValue::Mat3(columns(m)?.try_into().ok()?),
+		TypedValue::Mat4(m) => TypedValue::Mat4(columns(m)?.try_into().ok()?),
+	})
+}
+
+impl Graph {
+	/// Merge constant nodes holding equal values into a single node
+	///
+	/// Values are compared with `TypedValue`'s `PartialEq`, which compares
+	/// every component with IEEE 754 equality: `NaN` constants are never
+	/// merged, while `0.0` and `-0.0` are. Returns the number of removed
+	/// nodes.
+	pub fn dedup_constants(&mut self) -> usize {
+		let mut canonical: Vec<(NodeIndex<u32>, &TypedValue)> = Vec::new();
+		let mut duplicates = Vec::new();
+
+		for (index, node) in self.nodes() {
+			if let Node::Constant(value) = node {
+				match canonical.iter().find(|(_, other)| *other == value) {
+					Some((original, _)) => duplicates.push((index, *original)),
+					None => canonical.push((index, value)),
+				}
+			}
+		}
+
+		for (duplicate, original) in &duplicates {
+			self.replace_uses(*duplicate, *original);
+		}
+
+		for (duplicate, _) in &duplicates {
+			self.remove_node(*duplicate);
+		}
+
+		duplicates.len()
+	}
+
+	/// Replace every operation whose arguments are all constants with a
+	/// constant holding its result, computed by the CPU evaluator of
+	/// [`Graph::evaluate`]
+	///
+	/// Folding repeats until no operation is left with only constant
+	/// arguments, so whole constant subtrees collapse into one node. Inputs,
+	/// uniforms and samples are never folded, and neither are operations the
+	/// evaluator can't compute, such as inverses. Constants left without
+	/// uses are removed. Returns the number of folded operations.
+	///
+	/// Arguments and results are rounded to single precision, as the GPU
+	/// computes in `f32`. Operations whose result isn't finite, such as
+	/// `1.0 / 0.0` or `sqrt(-1.0)`, are left for the GPU, since shading
+	/// languages have no literals for infinities or `NaN`. Functions like
+	/// `sin` are still computed in double precision before rounding, so they
+	/// can differ from the GPU's in the last bits.
+	pub fn fold_constants(&mut self) -> usize {
+		let mut folded = 0;
+
+		loop {
+			let foldable =
+				self.nodes().find_map(|(index, node)| {
+					if matches!(
+						node,
+						Node::Input(_, _)
+							| Node::Uniform(_, _) | Node::TimeUniform
+							| Node::ResolutionUniform
+							| Node::Output(_, _) | Node::Constant(_)
+							| Node::Sample
+					) {
+						return None;
+					}
+
+					let args: Vec<_> = self.arguments(index).collect();
+					let values = args
+						.iter()
+						.map(|arg| match &self[*arg] {
+							Node::Constant(value) => single_precision(value),
+							_ => None,
+						})
+						.collect::<Option<Vec<_>>>()?;
+					if values.is_empty() {
+						return None;
+					}
+
+					let value = self
+						.evaluate_node(
+							index,
+							&values.iter().collect::<Vec<_>>(),
+							&HashMap::new(),
+							&HashMap::new(),
+						)
+						.ok()?;
+					let value = single_precision(&value)?;
+
+					Some((index, args, value))
+				});
+
+			let (index, mut args, value) = match foldable {
+				Some(foldable) => foldable,
+				None => return folded,
+			};
+
+			let constant = self.add_node(Node::Constant(value));
+			self.replace_uses(index, constant);
+			self.remove_node(index);
+
+			args.sort();
+			args.dedup();
+			for arg in args {
+				if self.neighbors(arg, Some(Outgoing)).next().is_none() {
+					self.remove_node(arg);
+				}
+			}
+
+			folded += 1;
+		}
+	}
+
+	/// Remove every node that no output depends on, returning how many were
+	/// removed
+	///
+	/// Indices of the remaining nodes stay valid.
+	pub fn prune(&mut self) -> usize {
+		let reachable = self.reachable_from_outputs();
+		let unused: Vec<_> = self
+			.nodes()
+			.map(|(index, _)| index)
+			.filter(|index| !reachable.contains(index))
+			.collect();
+
+		for index in &unused {
+			self.remove_node(*index);
+		}
+
+		unused.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::graph::TypeName;
+
+	#[test]
+	fn fold_constants_collapses_add() {
+		let mut graph = Graph::default();
+		let a = graph.add_node(Node::Constant(TypedValue::Float(1.0)));
+		let b = graph.add_node(Node::Constant(TypedValue::Float(2.0)));
+		let add = graph.add_node(Node::Add);
+		let output =
+			graph.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		graph.add_edge(a, add, 0);
+		graph.add_edge(b, add, 1);
+		graph.add_edge(add, output, 0);
+
+		assert_eq!(graph.fold_constants(), 1);
+		assert_eq!(graph.node_count(), 2);
+		let folded = graph.arguments(output).next().unwrap();
+		assert_eq!(graph[folded], Node::Constant(TypedValue::Float(3.0)));
+	}
+
+	/// `Input -> Sin -> Output`, every node of which is reachable
+	fn chain() -> (Graph, [NodeIndex<u32>; 3]) {
+		let mut graph = Graph::default();
+		let input =
+			graph.add_node(Node::Input(0, Box::new(TypeName::Float(false))));
+		let sin = graph.add_node(Node::Sin);
+		let output =
+			graph.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		graph.add_edge(input, sin, 0);
+		graph.add_edge(sin, output, 0);
+
+		(graph, [input, sin, output])
+	}
+
+	#[test]
+	fn prune_keeps_reachable_graph() {
+		let (mut graph, _) = chain();
+
+		assert_eq!(graph.prune(), 0);
+		assert_eq!(graph.node_count(), 3);
+	}
+
+	#[test]
+	fn prune_removes_dangling_add() {
+		let (mut graph, [input, sin, output]) = chain();
+		let add = graph.add_node(Node::Add);
+		graph.add_edge(input, add, 0);
+		graph.add_edge(sin, add, 1);
+
+		assert_eq!(graph.prune(), 1);
+		assert_eq!(graph.node_count(), 3);
+		assert!(graph.nodes().all(|(index, _)| index != add));
+		assert_eq!(graph.arguments(output).collect::<Vec<_>>(), [sin]);
+		assert_eq!(graph.arguments(sin).collect::<Vec<_>>(), [input]);
+	}
+}
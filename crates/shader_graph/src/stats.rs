@@ -0,0 +1,189 @@
+use crate::graph::{Graph, Node};
+use petgraph::{graph::NodeIndex, Incoming};
+use std::collections::{HashMap, HashSet};
+
+/// Size and cost of a graph, see [`Graph::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphStats {
+	pub inputs: usize,
+	/// Uniforms, including samplers and the time and resolution uniforms
+	pub uniforms: usize,
+	pub constants: usize,
+	pub operations: usize,
+	pub outputs: usize,
+	/// Number of edges on the longest path from an output to a node
+	/// without arguments, such as an input or constant, or `None` if the
+	/// graph has a cycle
+	///
+	/// An output connected straight to an input has a depth of 1.
+	pub max_depth: Option<usize>,
+	/// Rough number of instructions the outputs compile to
+	///
+	/// Every node reachable from an output is counted once, the way a
+	/// shader compiler shares repeated subexpressions. Arithmetic costs 1,
+	/// while transcendental functions, texture samples and matrix
+	/// operations cost more. Constructing and extracting vector components
+	/// is free, and subgraph calls cost as much as the subgraph's body each
+	/// time they're made.
+	pub instructions: usize,
+}
+
+/// Relative cost of evaluating a node, not counting its arguments
+fn cost(node: &Node) -> usize {
+	match node {
+		Node::Input(_, _)
+		| Node::Uniform(_, _)
+		| Node::TimeUniform
+		| Node::ResolutionUniform
+		| Node::Output(_, _)
+		| Node::Constant(_)
+		| Node::Construct(_)
+		| Node::Extract(_)
+		| Node::Swizzle(_)
+		| Node::Transpose => 0,
+		// Counted by `Graph::stats`, which can look into the subgraph
+		Node::Call(_) => 0,
+		Node::Add
+		| Node::Subtract
+		| Node::Multiply
+		| Node::Divide
+		| Node::Modulus
+		| Node::Clamp
+		| Node::Floor
+		| Node::Ceil
+		| Node::Round
+		| Node::Min
+		| Node::Max
+		| Node::Mix
+		| Node::LessThan
+		| Node::GreaterThan
+		| Node::Equal
+		| Node::Select
+		| Node::Abs
+		| Node::Sign
+		| Node::Fract
+		| Node::Step => 1,
+		Node::Dot | Node::Cross | Node::Length | Node::Distance => 2,
+		Node::Normalize
+		| Node::Reflect
+		| Node::Sqrt
+		| Node::InverseSqrt
+		| Node::Smoothstep => 3,
+		Node::Sin
+		| Node::Cos
+		| Node::Tan
+		| Node::Exp
+		| Node::Log
+		| Node::Pow
+		| Node::Sample => 4,
+		Node::MatMul | Node::Refract => 6,
+		Node::Inverse => 16,
+	}
+}
+
+impl Graph {
+	/// Count the nodes by kind and estimate how expensive the graph is,
+	/// without generating any code
+	pub fn stats(&self) -> GraphStats {
+		let mut stats = GraphStats::default();
+		for (_, node) in self.nodes() {
+			match node {
+				Node::Input(_, _) => stats.inputs += 1,
+				Node::Uniform(_, _)
+				| Node::TimeUniform
+				| Node::ResolutionUniform => stats.uniforms += 1,
+				Node::Constant(_) => stats.constants += 1,
+				Node::Output(_, _) => stats.outputs += 1,
+				_ => stats.operations += 1,
+			}
+		}
+
+		let outputs: Vec<_> = self
+			.nodes()
+			.filter(|(_, node)| matches!(node, Node::Output(_, _)))
+			.map(|(index, _)| index)
+			.collect();
+
+		if !self.has_cycle() {
+			let mut depths = HashMap::new();
+			stats.max_depth = Some(
+				outputs
+					.iter()
+					.map(|output| self.depth(*output, &mut depths))
+					.max()
+					.unwrap_or(0),
+			);
+		}
+
+		let mut reachable = HashSet::new();
+		let mut stack = outputs;
+		while let Some(index) = stack.pop() {
+			if reachable.insert(index) {
+				stack.extend(self.neighbors(index, Some(Incoming)));
+			}
+		}
+		// Recursive subgraphs are invalid anyway and would never finish
+		// being counted
+		let recursive = self.called_subgraphs().is_err();
+		stats.instructions = reachable
+			.into_iter()
+			.map(|index| match &self[index] {
+				Node::Call(id) if !recursive => self
+					.subgraph_body(*id)
+					.map_or(0, |body| body.stats().instructions),
+				node => cost(node),
+			})
+			.sum();
+
+		stats
+	}
+
+	/// Longest path from `index` to a node without arguments, which must not
+	/// be part of a cycle
+	fn depth(
+		&self,
+		index: NodeIndex<u32>,
+		depths: &mut HashMap<NodeIndex<u32>, usize>,
+	) -> usize {
+		if let Some(depth) = depths.get(&index) {
+			return *depth;
+		}
+
+		let depth = self
+			.arguments(index)
+			.map(|argument| self.depth(argument, depths) + 1)
+			.max()
+			.unwrap_or(0);
+		depths.insert(index, depth);
+
+		depth
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::graph::{TypeName, TypedValue};
+
+	#[test]
+	fn max_depth_follows_longest_path() {
+		// Output(0) = sin(Input(0)) + 1, Output(1) = Input(0)
+		let mut graph = Graph::default();
+		let input =
+			graph.add_node(Node::Input(0, Box::new(TypeName::Float(false))));
+		let sin = graph.add_node(Node::Sin);
+		let one = graph.add_node(Node::Constant(TypedValue::Float(1.0)));
+		let add = graph.add_node(Node::Add);
+		let sum =
+			graph.add_node(Node::Output(0, Box::new(TypeName::Float(false))));
+		let copy =
+			graph.add_node(Node::Output(1, Box::new(TypeName::Float(false))));
+		graph.add_edge(input, sin, 0);
+		graph.add_edge(sin, add, 0);
+		graph.add_edge(one, add, 1);
+		graph.add_edge(add, sum, 0);
+		graph.add_edge(input, copy, 0);
+
+		assert_eq!(graph.stats().max_depth, Some(3));
+	}
+}
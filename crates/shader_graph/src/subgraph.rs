@@ -0,0 +1,177 @@
+use crate::graph::{check_identifier, Graph, Node, TypeName};
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashSet};
+
+/// Handle to a subgraph registered with [`Graph::register_subgraph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubgraphId(pub u32);
+
+/// A graph generated once as a function and evaluated by [`Node::Call`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subgraph {
+	pub name: String,
+	pub graph: Graph,
+}
+
+impl Subgraph {
+	/// Name of the function generated for the subgraph
+	pub fn function_name(&self) -> String {
+		format!("subgraph_{}", self.name)
+	}
+}
+
+impl Graph {
+	/// Add a subgraph that [`Node::Call`] nodes can evaluate like a function
+	///
+	/// Input `n` of the subgraph is argument `n` of the call, so the inputs
+	/// have to be at locations 0, 1, 2..., and its single output, at
+	/// location 0, is the call's result. Subgraphs can't read uniforms or
+	/// sample textures, which have to be passed in as arguments instead.
+	///
+	/// Subgraphs can call other subgraphs registered on this graph before
+	/// them, so calls can nest but never recurse.
+	pub fn register_subgraph(
+		&mut self,
+		name: impl Into<String>,
+		graph: Graph,
+	) -> Result<SubgraphId> {
+		let name = name.into();
+		check_identifier(&name)?;
+		if self.subgraphs.iter().any(|subgraph| subgraph.name == name) {
+			bail!("{name:?} already names another subgraph");
+		}
+		if !graph.subgraphs.is_empty() {
+			bail!(
+				"Subgraph {name:?} has subgraphs of its own, which have to be \
+				 registered on the outer graph instead"
+			);
+		}
+
+		for (_, node) in graph.nodes() {
+			match node {
+				Node::Uniform(_, _)
+				| Node::TimeUniform
+				| Node::ResolutionUniform
+				| Node::Sample => {
+					bail!("Subgraph {name:?} can't contain {node:?} nodes")
+				}
+				Node::Call(id) if id.0 as usize >= self.subgraphs.len() => {
+					bail!(
+						"Subgraph {name:?} calls {id:?}, which isn't registered \
+						 yet, so it could recurse"
+					)
+				}
+				_ => {}
+			}
+		}
+		graph
+			.signature()
+			.with_context(|| format!("Invalid subgraph {name:?}"))?;
+
+		let id = SubgraphId(self.subgraphs.len() as u32);
+		self.subgraphs.push(Subgraph { name, graph });
+
+		Ok(id)
+	}
+
+	pub fn subgraph(&self, id: SubgraphId) -> Option<&Subgraph> {
+		self.subgraphs.get(id.0 as usize)
+	}
+
+	/// Every registered subgraph, in the order they were registered
+	pub fn subgraphs(&self) -> impl Iterator<Item = (SubgraphId, &Subgraph)> {
+		self.subgraphs
+			.iter()
+			.enumerate()
+			.map(|(id, subgraph)| (SubgraphId(id as u32), subgraph))
+	}
+
+	/// Parameter types of the graph as a subgraph, by input location, and
+	/// the type of its output
+	pub(crate) fn signature(&self) -> Result<(Vec<TypeName>, TypeName)> {
+		let mut inputs = BTreeMap::new();
+		let mut outputs = Vec::new();
+		for (_, node) in self.nodes() {
+			match node {
+				Node::Input(location, ty) => {
+					match inputs.insert(*location, ty.as_ref()) {
+						Some(other) if other != ty.as_ref() => bail!(
+							"Input {location} is both a {other:?} and a {ty:?}"
+						),
+						_ => {}
+					}
+				}
+				Node::Output(location, ty) => outputs.push((*location, ty)),
+				_ => {}
+			}
+		}
+
+		if inputs.keys().copied().ne(0..inputs.len() as u32) {
+			bail!("Inputs have to be at locations 0, 1, 2...");
+		}
+		let output = match outputs.as_slice() {
+			[(0, ty)] => ty.as_ref().clone(),
+			_ => bail!("There has to be exactly one output, at location 0"),
+		};
+
+		Ok((inputs.into_values().cloned().collect(), output))
+	}
+
+	/// The body of a subgraph, with this graph's subgraphs registered so its
+	/// own calls resolve
+	pub(crate) fn subgraph_body(&self, id: SubgraphId) -> Result<Graph> {
+		let subgraph = self
+			.subgraph(id)
+			.with_context(|| format!("{id:?} isn't registered"))?;
+		let mut body = subgraph.graph.clone();
+		body.subgraphs = self.subgraphs.clone();
+
+		Ok(body)
+	}
+
+	/// Every subgraph the graph calls, directly or through other subgraphs,
+	/// ordered so each comes after the subgraphs it calls
+	///
+	/// Fails if a subgraph calls itself, which [`Graph::register_subgraph`]
+	/// prevents but deserialized graphs may still do.
+	pub(crate) fn called_subgraphs(&self) -> Result<Vec<SubgraphId>> {
+		fn visit(
+			root: &Graph,
+			graph: &Graph,
+			path: &mut Vec<SubgraphId>,
+			done: &mut HashSet<SubgraphId>,
+			order: &mut Vec<SubgraphId>,
+		) -> Result<()> {
+			for (_, node) in graph.nodes() {
+				let id = match node {
+					Node::Call(id) => *id,
+					_ => continue,
+				};
+				if done.contains(&id) {
+					continue;
+				}
+				let subgraph = root
+					.subgraph(id)
+					.with_context(|| format!("{id:?} isn't registered"))?;
+				if path.contains(&id) {
+					bail!("Subgraph {:?} calls itself", subgraph.name);
+				}
+
+				path.push(id);
+				visit(root, &subgraph.graph, path, done, order)?;
+				path.pop();
+				done.insert(id);
+				order.push(id);
+			}
+
+			Ok(())
+		}
+
+		let mut order = Vec::new();
+		visit(self, self, &mut Vec::new(), &mut HashSet::new(), &mut order)?;
+
+		Ok(order)
+	}
+}
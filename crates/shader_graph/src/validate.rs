@@ -0,0 +1,160 @@
+use crate::{
+	graph::{Graph, Node},
+	infer::{TypeError, Types},
+};
+use petgraph::{graph::NodeIndex, Incoming, Outgoing};
+use std::{collections::HashSet, fmt};
+
+/// A problem found by [`Graph::validate`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+	/// The nodes form a cycle, so none of them can be evaluated
+	Cycle { nodes: Vec<NodeIndex<u32>> },
+	/// A node's type could not be inferred
+	Type(TypeError),
+	/// A node has a different number of arguments than it takes
+	Arity {
+		node: NodeIndex<u32>,
+		expected: usize,
+		found: usize,
+	},
+	/// An output has nothing connected to it
+	UnconnectedOutput { node: NodeIndex<u32> },
+	/// The node's value never reaches an output
+	Orphaned { node: NodeIndex<u32> },
+}
+
+impl ValidationIssue {
+	/// Nodes the issue should be reported on
+	pub fn nodes(&self) -> Vec<NodeIndex<u32>> {
+		match self {
+			ValidationIssue::Cycle { nodes } => nodes.clone(),
+			ValidationIssue::Type(error) => vec![error.node()],
+			ValidationIssue::Arity { node, .. }
+			| ValidationIssue::UnconnectedOutput { node }
+			| ValidationIssue::Orphaned { node } => vec![*node],
+		}
+	}
+}
+
+impl fmt::Display for ValidationIssue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ValidationIssue::Cycle { nodes } => {
+				let nodes: Vec<_> =
+					nodes.iter().map(|node| node.index().to_string()).collect();
+				write!(f, "nodes {} form a cycle", nodes.join(", "))
+			}
+			ValidationIssue::Type(error) => error.fmt(f),
+			ValidationIssue::Arity {
+				node,
+				expected,
+				found,
+			} => write!(
+				f,
+				"node {} takes {expected} arguments but has {found}",
+				node.index()
+			),
+			ValidationIssue::UnconnectedOutput { node } => {
+				write!(f, "output node {} has no input", node.index())
+			}
+			ValidationIssue::Orphaned { node } => {
+				write!(f, "node {} is not used by any output", node.index())
+			}
+		}
+	}
+}
+
+impl Graph {
+	/// Collect every problem in the graph instead of stopping at the first
+	///
+	/// Nodes depending on a cycle are not type checked, and a type error is
+	/// only reported once, on the node it originates from. Nodes with the
+	/// wrong number of arguments are reported as [`ValidationIssue::Arity`]
+	/// rather than as missing an argument, except for outputs with no input,
+	/// which are only reported as [`ValidationIssue::UnconnectedOutput`].
+	pub fn validate(&self) -> Vec<ValidationIssue> {
+		let mut issues = Vec::new();
+
+		let cycles = self.cycles();
+		let mut downstream_of_cycle = HashSet::new();
+		let mut stack: Vec<_> = cycles.iter().flatten().copied().collect();
+		while let Some(index) = stack.pop() {
+			if downstream_of_cycle.insert(index) {
+				stack.extend(self.neighbors(index, Some(Outgoing)));
+			}
+		}
+		issues.extend(
+			cycles
+				.into_iter()
+				.map(|nodes| ValidationIssue::Cycle { nodes }),
+		);
+
+		let mut used = HashSet::new();
+		let mut unconnected = HashSet::new();
+		let mut stack = Vec::new();
+		for (index, node) in self.nodes() {
+			if let Node::Output(_, _) = node {
+				if self.neighbors(index, Some(Incoming)).count() == 0 {
+					unconnected.insert(index);
+					issues.push(ValidationIssue::UnconnectedOutput {
+						node: index,
+					});
+				}
+				stack.push(index);
+			}
+		}
+		while let Some(index) = stack.pop() {
+			if used.insert(index) {
+				stack.extend(self.neighbors(index, Some(Incoming)));
+			}
+		}
+		issues.extend(
+			self.nodes()
+				.filter(|(index, _)| !used.contains(index))
+				.map(|(node, _)| ValidationIssue::Orphaned { node }),
+		);
+
+		let mut wrong_arity = HashSet::new();
+		for (index, node) in self.nodes() {
+			let found = self.arguments(index).count();
+			match node.arity() {
+				Some(expected) if expected != found => {
+					wrong_arity.insert(index);
+					// Already reported as an unconnected output
+					if unconnected.contains(&index) {
+						continue;
+					}
+					issues.push(ValidationIssue::Arity {
+						node: index,
+						expected,
+						found,
+					});
+				}
+				_ => {}
+			}
+		}
+
+		let mut type_errors = Vec::new();
+		let mut types = Types::new();
+		for (index, _) in self.nodes() {
+			if downstream_of_cycle.contains(&index) {
+				continue;
+			}
+			if let Err(error) = self.infer_with(index, &mut types) {
+				let counted = match &error {
+					TypeError::MissingArgument { node, .. } => {
+						wrong_arity.contains(node)
+					}
+					_ => false,
+				};
+				if !counted && !type_errors.contains(&error) {
+					type_errors.push(error);
+				}
+			}
+		}
+		issues.extend(type_errors.into_iter().map(ValidationIssue::Type));
+
+		issues
+	}
+}